@@ -0,0 +1,108 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    parse_macro_input,
+    parse_quote,
+    Data,
+    DeriveInput,
+    Field,
+    Fields
+};
+
+/// Implements `aspen_engine::reflect::Reflect` for a struct with named fields.
+/// Fields marked `#[reflect(skip)]` are left out of the type info and field access.
+#[proc_macro_derive(Reflect, attributes(reflect))]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_reflect(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into()
+    }
+}
+
+fn expand_reflect(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "Reflect can only be derived for structs with named fields"))
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "Reflect can only be derived for structs"))
+    };
+
+    let mut reflected = Vec::new();
+    for field in fields {
+        if !skipped(field)? {
+            reflected.push(field);
+        }
+    }
+
+    // `Reflect` needs `Any`, so every type parameter has to be 'static
+    let mut generics = input.generics.clone();
+    let parameters: Vec<_> = generics.type_params().map(|parameter| parameter.ident.clone()).collect();
+    let bounds = generics.make_where_clause();
+    for parameter in parameters {
+        bounds.predicates.push(parse_quote!(#parameter: 'static));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let names: Vec<_> = reflected.iter().map(|field| field.ident.as_ref().unwrap()).collect();
+    let name_strings: Vec<_> = names.iter().map(|name| name.to_string()).collect();
+    let types: Vec<_> = reflected.iter().map(|field| &field.ty).collect();
+
+    Ok(quote! {
+        impl #impl_generics ::aspen_engine::reflect::Reflect for #ident #type_generics #where_clause {
+            fn type_info() -> ::aspen_engine::reflect::TypeInfo {
+                ::aspen_engine::reflect::TypeInfo {
+                    name: stringify!(#ident),
+                    type_name: ::std::any::type_name::<Self>(),
+                    type_id: ::std::any::TypeId::of::<Self>(),
+                    fields: vec![#(
+                        ::aspen_engine::reflect::FieldInfo {
+                            name: #name_strings,
+                            type_name: ::std::any::type_name::<#types>(),
+                            type_id: ::std::any::TypeId::of::<#types>(),
+                        }
+                    ),*],
+                }
+            }
+
+            fn field(&self, name: &str) -> Option<&dyn ::std::any::Any> {
+                match name {
+                    #(#name_strings => Some(&self.#names),)*
+                    _ => None
+                }
+            }
+
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn ::std::any::Any> {
+                match name {
+                    #(#name_strings => Some(&mut self.#names),)*
+                    _ => None
+                }
+            }
+
+            fn as_any(&self) -> &dyn ::std::any::Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn ::std::any::Any {
+                self
+            }
+        }
+    })
+}
+
+fn skipped(field: &Field) -> syn::Result<bool> {
+    let mut skip = false;
+    for attribute in field.attrs.iter().filter(|attribute| attribute.path().is_ident("reflect")) {
+        attribute.parse_nested_meta(|meta| match meta.path.is_ident("skip") {
+            true => {
+                skip = true;
+                Ok(())
+            },
+            false => Err(meta.error("unknown reflect attribute, expected `skip`"))
+        })?;
+    }
+    Ok(skip)
+}
\ No newline at end of file
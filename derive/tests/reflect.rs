@@ -0,0 +1,57 @@
+use std::any::TypeId;
+
+use aspen_engine::reflect::Reflect;
+
+#[derive(Reflect, Default)]
+struct Player {
+    name: String,
+    health: f32,
+    #[reflect(skip)]
+    cache: Vec<u8>,
+}
+
+#[derive(Reflect)]
+struct Pair<T> {
+    first: T,
+    second: T,
+}
+
+#[test]
+fn derived_fields_are_readable_and_writable() {
+    let mut player = Player::default();
+    let value: &mut dyn Reflect = &mut player;
+    assert!(value.set("health", 10.0f32));
+    assert!(!value.set("health", 10u32));
+    assert!(value.set("name", "aspen".to_string()));
+    assert_eq!(value.get::<String>("name").map(String::as_str), Some("aspen"));
+    assert_eq!(player.health, 10.0);
+
+    let info = Player::type_info();
+    assert_eq!(info.name, "Player");
+    assert_eq!(info.type_id, TypeId::of::<Player>());
+    assert_eq!(info.fields.iter().map(|field| field.name).collect::<Vec<_>>(), vec!["name", "health"]);
+    assert_eq!(info.field("health").unwrap().type_id, TypeId::of::<f32>());
+}
+
+#[test]
+fn skipped_fields_are_hidden() {
+    let mut player = Player { cache: vec![1], ..Default::default() };
+    let value: &mut dyn Reflect = &mut player;
+    assert!(value.get::<Vec<u8>>("cache").is_none());
+    assert!(!value.set("cache", vec![2u8]));
+    assert_eq!(player.cache, vec![1]);
+    assert!(Player::type_info().field("cache").is_none());
+}
+
+#[test]
+fn generic_fields_use_the_concrete_type() {
+    let mut pair = Pair { first: 1u32, second: 2u32 };
+    let value: &mut dyn Reflect = &mut pair;
+    assert!(value.set("second", 5u32));
+    assert_eq!(value.get::<u32>("first"), Some(&1));
+    assert_eq!(pair.second, 5);
+
+    let info = Pair::<u32>::type_info();
+    assert_eq!(info.field("first").unwrap().type_id, TypeId::of::<u32>());
+    assert_ne!(info.type_id, Pair::<u64>::type_info().type_id);
+}
\ No newline at end of file
@@ -0,0 +1,115 @@
+use crate::math::{
+    Quat,
+    Ray,
+    Vec3
+};
+
+/// Result of `two_bone`, in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwoBoneSolution {
+    /// Rotation to pre-multiply onto the root bone's world rotation
+    pub root: Quat,
+    /// Rotation to pre-multiply onto the middle bone's world rotation after the root was rotated
+    pub mid: Quat,
+    pub mid_position: Vec3,
+    pub end_position: Vec3,
+    /// Whether the target was within reach
+    pub reached: bool,
+}
+
+/// Bends a chain like hip, knee, ankle or shoulder, elbow, wrist so its end touches `target`.
+/// The middle joint bends toward `pole`, e.g. a point in front of the knee.
+/// Targets out of reach stretch the chain straight toward them.
+pub fn two_bone(root: Vec3, mid: Vec3, end: Vec3, target: Vec3, pole: Vec3) -> TwoBoneSolution {
+    let upper = root.distance(mid);
+    let lower = mid.distance(end);
+    let to_target = target - root;
+
+    let max_reach = upper + lower;
+    let min_reach = (upper - lower).abs();
+    let distance = to_target.length().clamp(min_reach + 1e-4, (max_reach - 1e-4).max(min_reach + 1e-4));
+    let reached = (min_reach..=max_reach).contains(&to_target.length());
+
+    let direction = to_target.try_normalize()
+        .unwrap_or_else(|| (end - root).normalize_or_zero());
+
+    // Bend in the plane through the pole, falling back to the current bend
+    let perpendicular = |point: Vec3| {
+        let offset = point - root;
+        (offset - direction * offset.dot(direction)).try_normalize()
+    };
+    let bend = perpendicular(pole)
+        .or_else(|| perpendicular(mid))
+        .unwrap_or_else(|| direction.any_orthonormal_vector());
+
+    // Law of cosines for how far along the root to target line the middle joint sits
+    let along = (upper * upper - lower * lower + distance * distance) / (2.0 * distance);
+    let height = (upper * upper - along * along).max(0.0).sqrt();
+    let mid_position = root + direction * along + bend * height;
+    let end_position = root + direction * distance;
+
+    let root_rotation = arc(mid - root, mid_position - root);
+    let mid_rotation = arc(root_rotation * (end - mid), end_position - mid_position);
+
+    TwoBoneSolution {
+        root: root_rotation,
+        mid: mid_rotation,
+        mid_position,
+        end_position,
+        reached,
+    }
+}
+
+/// Turns a bone so its `forward` axis (in bone space) points at `target`, limited to `max_angle` radians
+/// from its animated rotation and blended in by `weight` in 0..1. Returns the new world rotation.
+pub fn look_at(rotation: Quat, position: Vec3, forward: Vec3, target: Vec3, max_angle: f32, weight: f32) -> Quat {
+    let current = rotation * forward;
+    let Some(desired) = (target - position).try_normalize() else {
+        return rotation
+    };
+
+    let full = arc(current, desired);
+    let (axis, angle) = full.to_axis_angle();
+    let limited = Quat::from_axis_angle(axis, angle.min(max_angle));
+    Quat::IDENTITY.slerp(limited, weight.clamp(0.0, 1.0)) * rotation
+}
+
+/// Where a foot should be planted on the ground below it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FootPlacement {
+    pub position: Vec3,
+    pub normal: Vec3,
+    /// Height of the ground relative to the animated foot along `up`, lower the pelvis by the lowest foot's offset
+    pub offset: f32,
+}
+
+/// Casts down from `max_step` above the animated foot to find the ground within `max_step` below it.
+/// `raycast` returns the hit point and surface normal, e.g. from the physics world or
+/// `SpatialIndex::raycast` with a flat normal.
+pub fn place_foot<F>(foot: Vec3, up: Vec3, max_step: f32, mut raycast: F) -> Option<FootPlacement>
+where
+    F: FnMut(&Ray, f32) -> Option<(Vec3, Vec3)>
+{
+    let up = up.normalize_or_zero();
+    let ray = Ray::new(foot + up * max_step, -up);
+    let (position, normal) = raycast(&ray, max_step * 2.0)?;
+
+    Some(FootPlacement {
+        position,
+        normal: normal.normalize_or_zero(),
+        offset: (position - foot).dot(up),
+    })
+}
+
+/// Tilts a foot's world rotation to lie flat on a surface with the given normal
+pub fn align_to_ground(rotation: Quat, up: Vec3, normal: Vec3) -> Quat {
+    arc(up, normal) * rotation
+}
+
+/// Shortest rotation between two directions of any length
+fn arc(from: Vec3, to: Vec3) -> Quat {
+    match (from.try_normalize(), to.try_normalize()) {
+        (Some(from), Some(to)) => Quat::from_rotation_arc(from, to),
+        _ => Quat::IDENTITY
+    }
+}
\ No newline at end of file
@@ -0,0 +1,458 @@
+pub mod ik;
+
+use std::collections::HashMap;
+
+use crate::{
+    events::EventBus,
+    math::Vec2
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClipId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateId(usize);
+
+/// Named point in a clip, sent as an `AnimationEvent` when playback passes it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipEvent {
+    /// Seconds from the start of the clip
+    pub time: f32,
+    pub name: String,
+}
+
+/// Timing of an animation clip, the keyframes themselves live with whatever samples the clip
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    pub name: String,
+    /// Seconds
+    pub duration: f32,
+    pub looping: bool,
+    pub events: Vec<ClipEvent>,
+}
+
+impl Clip {
+    pub fn new(name: &str, duration: f32, looping: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            duration,
+            looping,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_event(mut self, time: f32, name: &str) -> Self {
+        self.events.push(ClipEvent {
+            time,
+            name: name.to_string(),
+        });
+        self
+    }
+}
+
+/// What a state plays
+#[derive(Debug, Clone, PartialEq)]
+pub enum Motion {
+    Clip(ClipId),
+    /// Blends the two clips around the parameter's value, e.g. idle, walk and run by speed
+    Blend1D { parameter: String, points: Vec<(f32, ClipId)> },
+    /// Blends clips placed on a plane by two parameters, e.g. strafing by velocity
+    Blend2D { x: String, y: String, points: Vec<(Vec2, ClipId)> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parameter {
+    Float(f32),
+    Bool(bool),
+    /// Set until a transition uses it
+    Trigger(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Greater(String, f32),
+    Less(String, f32),
+    IsTrue(String),
+    IsFalse(String),
+    Trigger(String),
+    /// The current state played to its end, or through another loop
+    Finished,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    /// `None` transitions from any state
+    pub from: Option<StateId>,
+    pub to: StateId,
+    /// All have to hold
+    pub conditions: Vec<Condition>,
+    /// Crossfade in seconds
+    pub duration: f32,
+}
+
+impl Transition {
+    pub fn new(from: StateId, to: StateId) -> Self {
+        Self {
+            from: Some(from),
+            to,
+            conditions: Vec::new(),
+            duration: 0.2,
+        }
+    }
+
+    pub fn from_any(to: StateId) -> Self {
+        Self {
+            from: None,
+            ..Self::new(to, to)
+        }
+    }
+
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn over(mut self, duration: f32) -> Self {
+        self.duration = duration;
+        self
+    }
+}
+
+/// A clip to sample at `time` seconds and blend in with `weight`. The weights of a frame sum to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipWeight {
+    pub clip: ClipId,
+    pub time: f32,
+    pub weight: f32,
+}
+
+/// Sent to the event bus when playback passes a clip event
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationEvent {
+    pub state: String,
+    pub clip: String,
+    pub name: String,
+}
+
+struct State {
+    name: String,
+    motion: Motion,
+    speed: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Playing {
+    state: StateId,
+    /// Progress through the state's motion, 1 per play through
+    phase: f32,
+    finished: bool,
+}
+
+struct Fade {
+    from: Playing,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// State machine choosing and blending animation clips from gameplay parameters.
+/// It only decides clip times and weights, sampling and blending poses is up to the caller.
+pub struct AnimationGraph {
+    clips: Vec<Clip>,
+    states: Vec<State>,
+    transitions: Vec<Transition>,
+    parameters: HashMap<String, Parameter>,
+    current: Option<Playing>,
+    fade: Option<Fade>,
+}
+
+impl AnimationGraph {
+    pub fn new() -> Self {
+        Self {
+            clips: Vec::new(),
+            states: Vec::new(),
+            transitions: Vec::new(),
+            parameters: HashMap::new(),
+            current: None,
+            fade: None,
+        }
+    }
+
+    pub fn add_clip(&mut self, clip: Clip) -> ClipId {
+        self.clips.push(clip);
+        ClipId(self.clips.len() - 1)
+    }
+
+    pub fn clip(&self, id: ClipId) -> &Clip {
+        &self.clips[id.0]
+    }
+
+    /// The first state added is the entry state
+    pub fn add_state(&mut self, name: &str, motion: Motion) -> StateId {
+        self.states.push(State {
+            name: name.to_string(),
+            motion,
+            speed: 1.0,
+        });
+        let id = StateId(self.states.len() - 1);
+        if self.current.is_none() {
+            self.current = Some(Playing::new(id));
+        }
+        id
+    }
+
+    pub fn set_speed(&mut self, state: StateId, speed: f32) {
+        self.states[state.0].speed = speed;
+    }
+
+    /// Transitions are checked in the order they were added
+    pub fn add_transition(&mut self, transition: Transition) {
+        self.transitions.push(transition);
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.parameters.insert(name.to_string(), Parameter::Float(value));
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.parameters.insert(name.to_string(), Parameter::Bool(value));
+    }
+
+    pub fn set_trigger(&mut self, name: &str) {
+        self.parameters.insert(name.to_string(), Parameter::Trigger(true));
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<Parameter> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn state(&self) -> Option<StateId> {
+        self.current.map(|playing| playing.state)
+    }
+
+    pub fn state_name(&self, state: StateId) -> &str {
+        &self.states[state.0].name
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    /// Jumps to a state, crossfading over `duration` seconds
+    pub fn play(&mut self, state: StateId, duration: f32) {
+        let previous = self.current.replace(Playing::new(state));
+        self.fade = match (previous, duration > 0.0) {
+            (Some(from), true) => Some(Fade {
+                from,
+                elapsed: 0.0,
+                duration,
+            }),
+            _ => None
+        };
+    }
+
+    /// Advances playback, takes transitions whose conditions hold and sends passed clip events
+    pub fn update(&mut self, delta: f32, events: &mut EventBus) {
+        let Some(mut current) = self.current else {
+            return
+        };
+
+        let previous_phase = current.phase;
+        current.finished = self.advance(&mut current, delta);
+        self.send_events(&current, previous_phase, events);
+        self.current = Some(current);
+
+        if let Some(mut fade) = self.fade.take() {
+            fade.elapsed += delta;
+            self.advance(&mut fade.from, delta);
+            if fade.elapsed < fade.duration {
+                self.fade = Some(fade);
+            }
+        }
+
+        let taken = self.transitions.iter()
+            .find(|transition| {
+                transition.from.is_none_or(|from| from == current.state)
+                    && transition.to != current.state
+                    && transition.conditions.iter().all(|condition| self.holds(condition, &current))
+            })
+            .cloned();
+
+        if let Some(transition) = taken {
+            for condition in transition.conditions.iter() {
+                if let Condition::Trigger(name) = condition {
+                    self.parameters.insert(name.clone(), Parameter::Trigger(false));
+                }
+            }
+            self.play(transition.to, transition.duration);
+        }
+    }
+
+    /// Clips to sample this frame with their times and blend weights
+    pub fn weights(&self) -> Vec<ClipWeight> {
+        let Some(current) = self.current else {
+            return Vec::new()
+        };
+
+        let fade = self.fade.as_ref()
+            .map(|fade| (fade.from, (fade.elapsed / fade.duration).clamp(0.0, 1.0)));
+        let current_weight = fade.map_or(1.0, |(_, t)| t);
+
+        let mut weights: Vec<ClipWeight> = self.clip_weights(&current)
+            .into_iter()
+            .map(|clip| ClipWeight { weight: clip.weight * current_weight, ..clip })
+            .collect();
+        if let Some((from, t)) = fade {
+            weights.extend(self.clip_weights(&from)
+                .into_iter()
+                .map(|clip| ClipWeight { weight: clip.weight * (1.0 - t), ..clip }));
+        }
+
+        weights.retain(|clip| clip.weight > 0.0);
+        weights
+    }
+
+    /// Returns whether the motion finished or looped
+    fn advance(&self, playing: &mut Playing, delta: f32) -> bool {
+        let state = &self.states[playing.state.0];
+        let duration = self.motion_duration(&state.motion);
+        if duration <= 0.0 {
+            return true
+        }
+
+        playing.phase += delta * state.speed / duration;
+        match self.motion_loops(&state.motion) {
+            true => {
+                let looped = playing.phase >= 1.0;
+                playing.phase = playing.phase.rem_euclid(1.0);
+                looped
+            },
+            false => {
+                playing.phase = playing.phase.min(1.0);
+                playing.phase >= 1.0
+            }
+        }
+    }
+
+    fn holds(&self, condition: &Condition, current: &Playing) -> bool {
+        let flag = |name: &String| matches!(self.parameters.get(name), Some(Parameter::Bool(true) | Parameter::Trigger(true)));
+
+        match condition {
+            Condition::Greater(name, value) => self.float(name) > *value,
+            Condition::Less(name, value) => self.float(name) < *value,
+            Condition::IsTrue(name) | Condition::Trigger(name) => flag(name),
+            Condition::IsFalse(name) => !flag(name),
+            Condition::Finished => current.finished
+        }
+    }
+
+    fn float(&self, name: &str) -> f32 {
+        match self.parameters.get(name) {
+            Some(Parameter::Float(value)) => *value,
+            _ => 0.0
+        }
+    }
+
+    /// Blend weights of the clips in a motion, before crossfading
+    fn motion_weights(&self, motion: &Motion) -> Vec<(ClipId, f32)> {
+        match motion {
+            Motion::Clip(clip) => vec![(*clip, 1.0)],
+            Motion::Blend1D { parameter, points } => {
+                let value = self.float(parameter);
+                let mut sorted = points.clone();
+                sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                match sorted.iter().position(|(point, _)| *point >= value) {
+                    None => sorted.last().map(|(_, clip)| vec![(*clip, 1.0)]).unwrap_or_default(),
+                    Some(0) => vec![(sorted[0].1, 1.0)],
+                    Some(index) => {
+                        let (low, low_clip) = sorted[index - 1];
+                        let (high, high_clip) = sorted[index];
+                        let t = (value - low) / (high - low);
+                        vec![(low_clip, 1.0 - t), (high_clip, t)]
+                    }
+                }
+            },
+            Motion::Blend2D { x, y, points } => {
+                let value = Vec2::new(self.float(x), self.float(y));
+                // Inverse distance weighting, a point under the value takes over completely
+                if let Some((_, clip)) = points.iter().find(|(point, _)| point.distance_squared(value) < 1e-6) {
+                    return vec![(*clip, 1.0)]
+                }
+                let inverse: Vec<(ClipId, f32)> = points.iter()
+                    .map(|(point, clip)| (*clip, 1.0 / point.distance_squared(value)))
+                    .collect();
+                let total: f32 = inverse.iter().map(|(_, weight)| weight).sum();
+                inverse.into_iter().map(|(clip, weight)| (clip, weight / total)).collect()
+            }
+        }
+    }
+
+    /// Length of one play through, blended clips are time scaled to stay in step like footsteps should
+    fn motion_duration(&self, motion: &Motion) -> f32 {
+        self.motion_weights(motion).iter()
+            .map(|(clip, weight)| self.clips[clip.0].duration * weight)
+            .sum()
+    }
+
+    fn motion_loops(&self, motion: &Motion) -> bool {
+        self.motion_weights(motion).iter().any(|(clip, _)| self.clips[clip.0].looping)
+    }
+
+    fn clip_weights(&self, playing: &Playing) -> Vec<ClipWeight> {
+        self.motion_weights(&self.states[playing.state.0].motion)
+            .into_iter()
+            .map(|(clip, weight)| ClipWeight {
+                clip,
+                time: playing.phase * self.clips[clip.0].duration,
+                weight,
+            })
+            .collect()
+    }
+
+    /// Events of the state's strongest clip passed between the two phases
+    fn send_events(&self, playing: &Playing, previous_phase: f32, events: &mut EventBus) {
+        let state = &self.states[playing.state.0];
+        let Some((clip_id, _)) = self.motion_weights(&state.motion)
+            .into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1)) else {
+            return
+        };
+        let clip = &self.clips[clip_id.0];
+        if clip.duration <= 0.0 {
+            return
+        }
+
+        let from = previous_phase * clip.duration;
+        let to = playing.phase * clip.duration;
+        let wrapped = to < from;
+        for event in clip.events.iter() {
+            let passed = match wrapped {
+                true => event.time > from || event.time <= to,
+                false => event.time > from && event.time <= to
+            };
+            if passed {
+                events.send(AnimationEvent {
+                    state: state.name.clone(),
+                    clip: clip.name.clone(),
+                    name: event.name.clone(),
+                });
+            }
+        }
+    }
+}
+
+impl Default for AnimationGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Playing {
+    fn new(state: StateId) -> Self {
+        Self {
+            state,
+            phase: 0.0,
+            finished: false,
+        }
+    }
+}
\ No newline at end of file
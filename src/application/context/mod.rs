@@ -0,0 +1,351 @@
+use std::{
+    io,
+    path::PathBuf,
+    sync::{
+        Arc,
+        OnceLock
+    }
+};
+
+use web_time::SystemTime;
+
+use crate::{
+    asset::AssetServer,
+    config::{
+        Config,
+        CONFIG_FILE
+    },
+    events::EventBus,
+    input::{
+        actions::ActionMap,
+        Input,
+        TimedInput
+    },
+    locale::{
+        LanguageChanged,
+        Localization
+    },
+    random::Random,
+    reflect::TypeRegistry,
+    renderer::Renderer,
+    tasks::TaskPool,
+    replay::{
+        Recording,
+        Replay
+    },
+    timing::{
+        timers::Timers,
+        FrameLimiter,
+        TimingStruct,
+        UpdateTimes
+    },
+    tween::Tweens,
+    window::AspenWindow
+};
+
+use super::LoopMode;
+
+/// Engine services handed to the client during updates
+pub struct Context<UD> {
+    pub(crate) config: Config,
+    pub(crate) config_path: Option<PathBuf>,
+    pub(crate) timing: TimingStruct,
+    pub(crate) times: UpdateTimes,
+    pub(crate) timers: Timers<UD>,
+    pub(crate) tweens: Tweens,
+    pub(crate) events: EventBus,
+    pub(crate) tasks: OnceLock<Arc<TaskPool>>,
+    pub(crate) assets: Option<AssetServer>,
+    pub(crate) frame_limiter: FrameLimiter,
+    pub(crate) loop_mode: LoopMode,
+    pub(crate) frame_requested: bool,
+    pub(crate) exit_requested: bool,
+    pub(crate) suspended: bool,
+    pub(crate) window: Option<AspenWindow>,
+    pub(crate) renderer: Option<Renderer>,
+    pub(crate) input: Input,
+    pub(crate) fixed_input: Input,
+    pub(crate) input_events: Vec<TimedInput>,
+    /// Events not yet latched into a fixed step
+    pub(crate) fixed_pending: Vec<TimedInput>,
+    pub(crate) actions: ActionMap,
+    pub(crate) raw_mouse: bool,
+    pub(crate) localization: Option<Localization>,
+    pub(crate) pending_input: Vec<TimedInput>,
+    pub(crate) replay: Replay,
+    pub(crate) seed: u64,
+    pub(crate) random: Random,
+    pub(crate) types: TypeRegistry,
+}
+
+impl<UD> Context<UD> {
+    pub(crate) fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Self {
+            config: Config::default(),
+            config_path: None,
+            timing: TimingStruct::new(),
+            times: UpdateTimes::default(),
+            timers: Timers::new(),
+            tweens: Tweens::new(),
+            events: EventBus::new(),
+            tasks: OnceLock::new(),
+            assets: None,
+            frame_limiter: FrameLimiter::new(None),
+            loop_mode: LoopMode::Poll,
+            frame_requested: false,
+            exit_requested: false,
+            suspended: false,
+            window: None,
+            renderer: None,
+            input: Input::new(),
+            fixed_input: Input::new(),
+            input_events: Vec::new(),
+            fixed_pending: Vec::new(),
+            actions: ActionMap::new(),
+            raw_mouse: false,
+            localization: None,
+            pending_input: Vec::new(),
+            replay: Replay::Idle,
+            seed,
+            random: Random::new(seed),
+            types: TypeRegistry::new(),
+        }
+    }
+
+    /// The config the application started with, including changes made through `config_mut`
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Changes here are only stored, use the matching setters to apply them at runtime
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    /// Writes the config back to the file it was loaded from, or `aspen.toml` if there was none.
+    /// The current render settings, frame cap and action bindings are copied into it first.
+    pub fn save_config(&mut self) -> io::Result<()> {
+        if let Some(renderer) = &self.renderer {
+            self.config.render = *renderer.settings();
+        }
+        self.config.timing.target_fps = self.frame_limiter.target_fps;
+        self.config.timing.max_fixed_steps = self.timing.fixed.max_steps;
+        self.config.input = self.actions.clone();
+
+        let path = self.config_path.clone().unwrap_or_else(|| PathBuf::from(CONFIG_FILE));
+        self.config.save(path)
+    }
+
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    pub fn set_loop_mode(&mut self, mode: LoopMode) {
+        self.loop_mode = mode;
+    }
+
+    pub fn target_fps(&self) -> Option<f64> {
+        self.frame_limiter.target_fps
+    }
+
+    /// Caps the frame rate, `None` runs uncapped
+    pub fn set_target_fps(&mut self, fps: Option<f64>) {
+        self.frame_limiter.target_fps = fps;
+    }
+
+    /// Runs another frame even if no events arrive, only needed in the waiting loop modes
+    pub fn request_frame(&mut self) {
+        self.frame_requested = true;
+    }
+
+    /// The wall, game and fixed clocks, with settings such as time scale and pause
+    pub fn timing(&mut self) -> &mut TimingStruct {
+        &mut self.timing
+    }
+
+    /// Timing info of the current frame, including the unscaled delta for UI animations
+    pub fn times(&self) -> &UpdateTimes {
+        &self.times
+    }
+
+    pub fn timers(&mut self) -> &mut Timers<UD> {
+        &mut self.timers
+    }
+
+    /// Tweens and sequences played on the game clock, read their values during update
+    pub fn tweens(&mut self) -> &mut Tweens {
+        &mut self.tweens
+    }
+
+    /// Events sent last frame are read here, sending needs `events_mut`
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub fn events_mut(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+
+    /// The shared task pool, started on first use with the thread counts from the config
+    pub fn tasks(&self) -> &Arc<TaskPool> {
+        self.tasks.get_or_init(|| Arc::new(TaskPool::new(&self.config.tasks)))
+    }
+
+    /// Background asset loading from the configured asset root, started on first use
+    pub fn assets(&mut self) -> &mut AssetServer {
+        if self.assets.is_none() {
+            self.assets = Some(AssetServer::new(&self.config.assets, self.tasks().clone()));
+        }
+        self.assets.as_mut().unwrap()
+    }
+
+    /// Whether the OS has sent the application to the background
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// `None` until the window is created, while suspended on Android, or when running without graphics
+    pub fn window(&self) -> Option<&AspenWindow> {
+        self.window.as_ref()
+    }
+
+    /// `None` when running without graphics
+    pub fn renderer(&mut self) -> Option<&mut Renderer> {
+        self.renderer.as_mut()
+    }
+
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    /// Input latched for the current fixed step, read this in `fixed_update`.
+    /// Each event lands in exactly one step, so presses are neither doubled when a frame
+    /// runs several steps nor lost when it runs none. While the fixed clock is stopped by a pause
+    /// events only change what is held, the first step after it sees no presses from during the pause.
+    pub fn fixed_input(&self) -> &Input {
+        &self.fixed_input
+    }
+
+    /// This frame's input events with their timestamps, in arrival order
+    pub fn input_events(&self) -> &[TimedInput] {
+        &self.input_events
+    }
+
+    /// Locks and hides the cursor and reports device mouse motion through `Input::raw_mouse_delta`,
+    /// for camera controls that should not stop at the screen edge
+    pub fn set_raw_mouse(&mut self, enabled: bool) {
+        self.raw_mouse = enabled;
+        if let Some(window) = &self.window {
+            window.set_cursor_grabbed(enabled);
+        }
+    }
+
+    pub fn raw_mouse(&self) -> bool {
+        self.raw_mouse
+    }
+
+    pub fn actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    pub fn actions_mut(&mut self) -> &mut ActionMap {
+        &mut self.actions
+    }
+
+    /// `None` until a language was loaded with `set_language`
+    pub fn localization(&self) -> Option<&Localization> {
+        self.localization.as_ref()
+    }
+
+    /// Loads a language from the configured locale directory and sends `LanguageChanged`.
+    /// The first call also loads the fallback language, `tr!` returns keys until then.
+    pub fn set_language(&mut self, language: &str) -> io::Result<()> {
+        match &mut self.localization {
+            Some(localization) => localization.set_language(language)?,
+            None => {
+                let dir = self.config.assets.root.join(&self.config.locale.dir);
+                let mut config = self.config.locale.clone();
+                config.language = language.to_string();
+                self.localization = Some(Localization::new(dir, &config)?);
+            }
+        }
+
+        self.config.locale.language = language.to_string();
+        self.events.send(LanguageChanged {
+            language: language.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Reflection metadata of the game's types, for scene serialization, inspectors and scripting
+    pub fn types(&self) -> &TypeRegistry {
+        &self.types
+    }
+
+    pub fn types_mut(&mut self) -> &mut TypeRegistry {
+        &mut self.types
+    }
+
+    /// Seed for the session's randomness, replaced by the recording's seed during playback
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restarts `random` from a new seed, e.g. a level seed or one received from the server
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.random.reseed(seed);
+    }
+
+    /// Seeded random streams, reseeded when recording or playback starts so replays draw the same numbers
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    /// Number of fixed steps run so far
+    pub fn fixed_tick(&self) -> u64 {
+        self.timing.fixed.ticks()
+    }
+
+    /// Starts capturing input and frame timing from the next frame on
+    pub fn start_recording(&mut self) {
+        self.random.reseed(self.seed);
+        self.replay = Replay::Recording(Recording::new(self.seed));
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        match std::mem::replace(&mut self.replay, Replay::Idle) {
+            Replay::Recording(recording) => Some(recording),
+            other => {
+                self.replay = other;
+                None
+            }
+        }
+    }
+
+    /// Replays a recording from the next frame on, ignoring live input until it ends.
+    /// The game should reset its state before the first replayed frame, `random` is reseeded from the recording.
+    pub fn play_recording(&mut self, recording: Recording) {
+        self.seed = recording.seed;
+        self.random.reseed(recording.seed);
+        self.replay = Replay::Playing { recording, next: 0 };
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(self.replay, Replay::Recording(_))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.replay, Replay::Playing { .. })
+    }
+
+    /// Shuts the application down once the current frame finishes
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+}
\ No newline at end of file
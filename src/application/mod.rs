@@ -1,19 +1,61 @@
 //#![allow(unused)]
+mod context;
+
+pub use context::Context;
+
+use std::{
+    io,
+    path::Path,
+    time::Duration
+};
+
+use web_time::Instant;
+
 use winit::{
-    event::Event, 
+    event::{
+        DeviceEvent,
+        ElementState,
+        Event,
+        MouseButton,
+        StartCause,
+        WindowEvent
+    }, 
     event_loop::{
         ControlFlow, 
-        EventLoop, 
-        EventLoopBuilder
+        EventLoopBuilder,
+        EventLoopWindowTarget
     }
 };
 
 use crate::{
+    config::{
+        args::EngineArgs,
+        Config
+    },
+    input::{
+        InputEvent,
+        TimedInput
+    },
+    math::Vec2,
+    profile_scope,
+    profiler,
     renderer::Renderer, 
-    timing::TimingStruct, 
-    interface::Client
+    timing::{
+        pacing::FrameHitch,
+        timers::Timers,
+        UpdateTimes
+    }, 
+    interface::Client,
+    window::AspenWindow
 };
 
+#[cfg(target_os = "android")]
+pub use winit::platform::android::activity::AndroidApp;
+#[cfg(target_os = "android")]
+use winit::platform::android::EventLoopBuilderExtAndroid;
+#[cfg(target_arch = "wasm32")]
+use winit::platform::web::EventLoopExtWebSys;
+
 #[derive(Debug)]
 enum GlobalEvent {
     Update,
@@ -21,35 +63,135 @@ enum GlobalEvent {
 }
 
 pub struct Application<UD: Client> {
-    event_loop: EventLoop<GlobalEvent>,
     user_data: UD,
-    timer: TimingStruct,
-    renderer: Option<Renderer>,
+    context: Context<UD>,
+    #[cfg(target_os = "android")]
+    android_app: Option<AndroidApp>,
+}
+
+/// How the application decides when to run a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopMode {
+    /// Runs frames continuously, as fast as the frame limiter allows
+    Poll,
+    /// Sleeps until an OS event arrives or a frame is requested, for tools and event driven apps
+    Wait,
+    /// Like `Wait`, but also wakes up after the timeout passes without events
+    WaitTimeout(Duration),
 }
 
 impl<UD: Client> Application<UD> {
     pub fn new(user_data: UD, use_graphics: bool) -> Self {
-        let event_loop = EventLoopBuilder::<GlobalEvent>::with_user_event()
-            .build()
-            .expect("event loop creation failed");
-
-        let renderer = match use_graphics {
+        let mut context = Context::new();
+        context.renderer = match use_graphics {
             true => Some(Renderer::new()),
             false => None
         };
 
         Self {
-            event_loop,
             user_data,
-            timer: TimingStruct::new(),
-            renderer,
+            context,
+            #[cfg(target_os = "android")]
+            android_app: None,
+        }
+    }
+
+    /// Applies the config's window, timing, render settings and action bindings
+    pub fn with_config(mut self, mut config: Config) -> Self {
+        config.timing.fixed_rate = config.timing.fixed_rate.max(1);
+        config.timing.max_fixed_steps = config.timing.max_fixed_steps.max(1);
+        config.render = config.render.validated();
+
+        self.context.frame_limiter.target_fps = config.timing.target_fps;
+        self.context.timing.fixed.max_steps = config.timing.max_fixed_steps;
+        self.context.actions = config.input.clone();
+        if let Some(renderer) = &mut self.context.renderer {
+            renderer.set_settings(config.render);
+        }
+
+        self.context.config = config;
+        self
+    }
+
+    /// Loads the config from `path` if it exists, `Context::save_config` writes back to the same file
+    pub fn with_config_file(mut self, path: impl AsRef<Path>) -> io::Result<Self> {
+        let config = Config::load_or_default(path.as_ref())?;
+        self.context.config_path = Some(path.as_ref().to_path_buf());
+        Ok(self.with_config(config))
+    }
+
+    /// Applies command line options on top of the current config, call after loading the config file.
+    /// `--headless` drops the renderer so no window is created.
+    pub fn with_args(mut self, args: &EngineArgs) -> io::Result<Self> {
+        let mut config = self.context.config.clone();
+        args.apply(&mut config)?;
+
+        if args.headless {
+            self.context.renderer = None;
+        }
+
+        Ok(self.with_config(config))
+    }
+
+    /// Required on Android, the app handle passed to `android_main`.
+    /// Needs the `android-native-activity` or `android-game-activity` feature.
+    #[cfg(target_os = "android")]
+    pub fn with_android_app(mut self, app: AndroidApp) -> Self {
+        self.android_app = Some(app);
+        self
+    }
+
+    pub fn context(&mut self) -> &mut Context<UD> {
+        &mut self.context
+    }
+
+    pub fn client(&self) -> &UD {
+        &self.user_data
+    }
+
+    pub fn client_mut(&mut self) -> &mut UD {
+        &mut self.user_data
+    }
+
+    /// Runs frames without an event loop or window, each one fixed step long.
+    /// Meant for testing client logic where no display is available.
+    pub fn tick(&mut self, frames: u64) {
+        let fixed_delta = 1.0 / self.context.config.timing.fixed_rate as f64;
+        for _ in 0..frames {
+            self.tick_delta(fixed_delta);
         }
     }
 
-    pub fn run(mut self) {
-        let proxy = self.event_loop.create_proxy();
-        self.event_loop.run(move |event, elwt| {
-            elwt.set_control_flow(ControlFlow::Poll);
+    /// Runs a single frame as if `delta` seconds had passed since the last one
+    pub fn tick_delta(&mut self, delta: f64) {
+        let times = self.context.timing.advance(delta, self.context.config.timing.fixed_rate);
+        run_frame(&mut self.user_data, &mut self.context, times);
+    }
+
+    pub fn run(self) {
+        let Application {
+            mut user_data,
+            mut context,
+            #[cfg(target_os = "android")]
+            android_app,
+        } = self;
+
+        #[cfg_attr(not(target_os = "android"), allow(unused_mut))]
+        let mut builder = EventLoopBuilder::<GlobalEvent>::with_user_event();
+
+        #[cfg(target_os = "android")]
+        if let Some(app) = android_app {
+            builder.with_android_app(app);
+        }
+
+        let event_loop = builder
+            .build()
+            .expect("event loop creation failed");
+
+        let proxy = event_loop.create_proxy();
+        let mut events_pending = true;
+        let mut cursor_position = Vec2::ZERO;
+        let handler = move |event: Event<GlobalEvent>, elwt: &EventLoopWindowTarget<GlobalEvent>| {
             match event {
                 //Event::WindowEvent { event, .. } => match event {
                 //    WindowEvent::Resized(size) => {
@@ -67,19 +209,134 @@ impl<UD: Client> Application<UD> {
                 //    } => window_target.exit(),
                 //    _ => (),
                 //},
+                Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
+                    events_pending = true;
+                },
+                Event::Resumed => {
+                    events_pending = true;
+                    context.suspended = false;
+
+                    // Android only allows creating the window once the app has been resumed
+                    if context.renderer.is_some() && context.window.is_none() {
+                        let window = AspenWindow::new(elwt, &context.config.window);
+                        if context.raw_mouse {
+                            window.set_cursor_grabbed(true);
+                        }
+                        context.window = Some(window);
+                    }
+
+                    // Time spent in the background should not be simulated in one huge step
+                    context.timing.skip_elapsed();
+                    user_data.resumed(&mut context);
+                },
+                Event::Suspended => {
+                    context.suspended = true;
+                    user_data.suspended(&mut context);
+
+                    // The native window is destroyed on Android, so anything drawing to it must go
+                    if cfg!(target_os = "android") {
+                        context.window = None;
+                    }
+                },
+                Event::WindowEvent { event, .. } => {
+                    events_pending = true;
+
+                    // Live input is ignored while a recording is played back
+                    if !context.is_playing() {
+                        InputEvent::push_window_event(&event, context.timing.now(), &mut context.pending_input);
+                    }
+
+                    match event {
+                        WindowEvent::CloseRequested => {
+                            context.exit();
+                            proxy.send_event(GlobalEvent::Shutdown).unwrap();
+                        },
+                        WindowEvent::Focused(focused) => {
+                            if let Some(window) = &context.window {
+                                window.focus_changed(focused);
+                                if focused && context.raw_mouse {
+                                    window.set_cursor_grabbed(true);
+                                }
+                            }
+                        },
+                        WindowEvent::CursorMoved { position, .. } => {
+                            cursor_position = Vec2::new(position.x as f32, position.y as f32);
+                            if let Some(window) = &context.window {
+                                window.cursor_moved(cursor_position);
+                            }
+                        },
+                        // Custom title bars and resize borders move the window while the button is still held
+                        WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } => {
+                            if let Some(window) = &context.window {
+                                window.handle_press(cursor_position);
+                            }
+                        },
+                        WindowEvent::HoveredFile(path) => user_data.file_hovered(&mut context, path),
+                        WindowEvent::HoveredFileCancelled => user_data.file_hover_cancelled(&mut context),
+                        WindowEvent::DroppedFile(path) => user_data.file_dropped(&mut context, path),
+                        #[cfg(target_arch = "wasm32")]
+                        WindowEvent::RedrawRequested => proxy.send_event(GlobalEvent::Update).unwrap(),
+                        _ => ()
+                    }
+                },
+                // Devices report motion even while the window is in the background, only raw mouse input wakes the loop
+                Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta: (x, y) }, .. } => {
+                    let focused = context.window.as_ref().is_some_and(|window| window.has_focus());
+                    if context.raw_mouse && focused && !context.is_playing() {
+                        events_pending = true;
+                        context.pending_input.push(TimedInput {
+                            time: context.timing.now(),
+                            event: InputEvent::RawMouseMotion {
+                                delta: Vec2::new(x as f32, y as f32),
+                            },
+                        });
+                    }
+                },
                 Event::AboutToWait => {
+                    if context.suspended {
+                        elwt.set_control_flow(ControlFlow::Wait);
+                        return
+                    }
+
+                    let frame_due = match context.loop_mode {
+                        LoopMode::Poll => true,
+                        LoopMode::Wait | LoopMode::WaitTimeout(_) => events_pending || context.frame_requested
+                    };
+
+                    if !frame_due {
+                        elwt.set_control_flow(match context.loop_mode {
+                            LoopMode::WaitTimeout(timeout) => ControlFlow::WaitUntil(Instant::now() + timeout),
+                            _ => ControlFlow::Wait
+                        });
+                        return
+                    }
+
+                    if let Some(wake_time) = context.frame_limiter.wait() {
+                        elwt.set_control_flow(ControlFlow::WaitUntil(wake_time));
+                        return
+                    }
+
+                    // Browsers pace frames with requestAnimationFrame, which winit delivers as RedrawRequested
+                    #[cfg(target_arch = "wasm32")]
+                    if let Some(window) = &context.window {
+                        elwt.set_control_flow(ControlFlow::Wait);
+                        window.winit().request_redraw();
+                        return
+                    }
+
+                    elwt.set_control_flow(ControlFlow::Poll);
                     proxy.send_event(GlobalEvent::Update).unwrap();
                 },
                 Event::UserEvent(global_event) => {
                     match global_event {
                         GlobalEvent::Update => {
-                            let time_info = self.timer.update(100);
+                            events_pending = false;
+                            let times = context.timing.update(context.config.timing.fixed_rate);
+                            run_frame(&mut user_data, &mut context, times);
 
-                            for _ in 0..time_info.fixed_steps {
-                                self.user_data.fixed_update(time_info.fixed_delta);
+                            if context.exit_requested {
+                                proxy.send_event(GlobalEvent::Shutdown).unwrap();
                             }
-                            
-                            self.user_data.update(time_info.delta);
                         },
                         GlobalEvent::Shutdown => {
                             elwt.exit()
@@ -88,6 +345,191 @@ impl<UD: Client> Application<UD> {
                 },
                 _ => ()
             }
-        }).unwrap()
+        };
+
+        // Browsers own the loop, so spawning returns straight away instead of blocking
+        #[cfg(target_arch = "wasm32")]
+        event_loop.spawn(handler);
+        #[cfg(not(target_arch = "wasm32"))]
+        event_loop.run(handler).unwrap()
+    }
+}
+
+fn run_frame<UD: Client>(user_data: &mut UD, context: &mut Context<UD>, live_times: UpdateTimes) {
+    let frame_start = Instant::now();
+    context.frame_requested = false;
+    context.frame_limiter.frame_started();
+    profiler::new_frame();
+    context.events.flush();
+
+    if let Some(assets) = &mut context.assets {
+        assets.update();
+    }
+
+    // The profiler's last frame is the slow one, its spans show where the time went
+    if let Some(frame_time) = context.timing.stats.take_hitch() {
+        context.events.send(FrameHitch {
+            frame_time,
+            profile: profiler::last_frame(),
+        });
+    }
+
+    let live_input = std::mem::take(&mut context.pending_input);
+    let (time_info, input_events) = context.replay.next_frame(live_times, live_input);
+    context.times = time_info;
+    context.timing.game.advance(time_info.delta);
+
+    context.input.begin_frame(time_info.unscaled_delta);
+    for timed in input_events.iter() {
+        context.input.apply(&timed.event);
+    }
+    context.actions.update_capture(&context.input, &input_events);
+    context.actions.update_axes(&context.input, time_info.unscaled_delta);
+
+    context.fixed_pending.extend(input_events.iter().cloned());
+    context.input_events = input_events;
+
+    // While the fixed clock stands still no step takes the events, so they only update the held state.
+    // Otherwise they would pile up and the first step after a pause would replay every press of the pause menu.
+    if context.timing.fixed.scaled && context.timing.game.is_stopped() {
+        for timed in context.fixed_pending.drain(..) {
+            context.fixed_input.apply(&timed.event);
+        }
+    }
+
+    // Steps split the frame's time evenly, each takes the events that arrived before its end.
+    // Events of frames without steps wait for the next step.
+    let frame_begin = time_info.time - time_info.unscaled_delta;
+    for step in 0..time_info.fixed_steps {
+        profile_scope!("fixed_update");
+
+        let step_end = match step + 1 == time_info.fixed_steps {
+            true => f64::INFINITY,
+            false => frame_begin + time_info.unscaled_delta * (step + 1) as f64 / time_info.fixed_steps as f64
+        };
+        let count = context.fixed_pending.partition_point(|timed| timed.time <= step_end);
+
+        context.fixed_input.begin_frame(time_info.fixed_delta);
+        for timed in context.fixed_pending.drain(..count) {
+            context.fixed_input.apply(&timed.event);
+        }
+        context.actions.update_fixed_axes(&context.fixed_input, time_info.fixed_delta);
+
+        user_data.fixed_update(context, time_info.fixed_delta);
+        context.timing.fixed.tick();
+    }
+
+    if !context.timing.game.paused {
+        profile_scope!("timers");
+        Timers::run(user_data, context, time_info.delta);
+        context.tweens.tick(time_info.delta);
+    }
+
+    {
+        profile_scope!("update");
+        user_data.update(context, time_info.delta);
+    }
+
+    {
+        profile_scope!("render");
+        user_data.render(context, time_info.alpha);
+    }
+
+    // Measured without the frame limiter's wait, so a capped frame rate does not read as load
+    if let Some(renderer) = &mut context.renderer {
+        renderer.frame_finished(frame_start.elapsed().as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        input::{
+            actions::{
+                AxisBinding,
+                Binding
+            },
+            KeyCode
+        },
+        tween::Tween
+    };
+
+    #[derive(Default)]
+    struct Recorder {
+        pressed: Vec<bool>,
+        down: Vec<bool>,
+        axis: Vec<f32>,
+    }
+
+    impl Client for Recorder {
+        fn fixed_update(&mut self, ctx: &mut Context<Self>, _delta: f64) {
+            self.pressed.push(ctx.fixed_input().key_pressed(KeyCode::Space));
+            self.down.push(ctx.fixed_input().key_down(KeyCode::Space));
+            self.axis.push(ctx.actions().fixed_axis("move"));
+        }
+
+        fn update(&mut self, _ctx: &mut Context<Self>, _delta: f64) {}
+    }
+
+    fn key(app: &mut Application<Recorder>, key: KeyCode, pressed: bool) {
+        let time = app.context.timing.now();
+        app.context.pending_input.push(TimedInput {
+            time,
+            event: InputEvent::Key { key, pressed },
+        });
+    }
+
+    #[test]
+    fn input_during_a_pause_is_not_replayed() {
+        let mut app = Application::new(Recorder::default(), false);
+        app.context().timing().game.paused = true;
+        for _ in 0..100 {
+            key(&mut app, KeyCode::Space, true);
+            key(&mut app, KeyCode::Space, false);
+            app.tick(1);
+        }
+        key(&mut app, KeyCode::Space, true);
+        app.tick(1);
+        assert!(app.context.fixed_pending.is_empty());
+        assert!(app.client().pressed.is_empty());
+
+        app.context().timing().game.paused = false;
+        app.tick(1);
+        assert_eq!(app.client().pressed, vec![false]);
+        assert_eq!(app.client().down, vec![true]);
+    }
+
+    #[test]
+    fn fixed_axes_follow_the_fixed_input() {
+        let mut app = Application::new(Recorder::default(), false);
+        app.context().actions_mut().bind_axis("move", AxisBinding::Pair {
+            negative: Binding::Key(KeyCode::KeyA),
+            positive: Binding::Key(KeyCode::KeyD),
+        });
+
+        key(&mut app, KeyCode::KeyD, true);
+        app.tick(1);
+        key(&mut app, KeyCode::KeyD, false);
+        app.tick(1);
+        assert_eq!(app.client().axis, vec![1.0, 0.0]);
+    }
+
+    #[test]
+    fn tweens_run_on_the_game_clock() {
+        let mut app = Application::new(Recorder::default(), false);
+        let tween = app.context().tweens().play(Tween::new(0.0f64, 8.0, 8.0));
+
+        app.tick_delta(1.0);
+        assert_eq!(app.context().tweens().value(tween), Some(1.0));
+
+        app.context().timing().game.paused = true;
+        app.tick_delta(1.0);
+        assert_eq!(app.context().tweens().value(tween), Some(1.0));
+
+        app.context().timing().game.paused = false;
+        app.context().timing().game.time_scale = 0.5;
+        app.tick_delta(1.0);
+        assert_eq!(app.context().tweens().value(tween), Some(1.5));
     }
 }
\ No newline at end of file
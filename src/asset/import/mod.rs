@@ -0,0 +1,75 @@
+use std::{
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+
+use serde::{
+    de::DeserializeOwned,
+    Serialize
+};
+
+use crate::random::fnv1a;
+
+use super::AssetServer;
+
+impl AssetServer {
+    /// Like `register_loader`, but for source formats that are slow to decode, such as PNG or glTF.
+    /// `import` converts the source into `T` once, after that `T` is read back from the import cache
+    /// as long as the source bytes stay the same. Bump `version` whenever `import` produces different
+    /// output, so older cache entries are not used.
+    pub fn register_importer<T, F>(&mut self, extensions: &[&str], version: u32, import: F)
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+        F: Fn(&[u8]) -> io::Result<T> + Send + Sync + 'static
+    {
+        let cache = self.cache.clone();
+        self.register_loader(extensions, move |source, _| {
+            let path = cache_path(&cache, cache_key::<T>(source, version));
+
+            if let Ok(cached) = fs::read(&path) {
+                if let Ok(asset) = bincode::deserialize(&cached) {
+                    return Ok(asset)
+                }
+            }
+
+            let asset = import(source)?;
+            // A failed cache write only costs the next load another import
+            let _ = write_cache(&path, &asset);
+            Ok(asset)
+        });
+    }
+
+    /// Removes every cached import, they are redone on their next load
+    pub fn clear_import_cache(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.cache) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Content hash of the source, combined with the output type and importer version
+fn cache_key<T>(source: &[u8], version: u32) -> u64 {
+    let key = format!("{}:{}:{:016x}", std::any::type_name::<T>(), version, fnv1a(source));
+    fnv1a(key.as_bytes())
+}
+
+fn cache_path(cache: &Path, key: u64) -> PathBuf {
+    cache.join(format!("{:016x}.bin", key))
+}
+
+/// Written to a temporary file first, so a concurrent load never reads a partial entry
+fn write_cache<T: Serialize>(path: &Path, asset: &T) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let bytes = bincode::serialize(asset).map_err(io::Error::other)?;
+    let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&temporary, bytes)?;
+    fs::rename(&temporary, path)
+}
\ No newline at end of file
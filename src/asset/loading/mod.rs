@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use super::{
+    AssetId,
+    AssetServer,
+    BatchProgress,
+    LoadState
+};
+
+/// Tracks a batch of assets behind a loading screen and hands over the next state once all of them are done.
+///
+/// The client keeps rendering its loading scene from `progress` while `poll` returns `None`:
+///
+/// ```ignore
+/// if let Some(next) = self.loading.poll(context.assets()) {
+///     self.state = next;
+/// }
+/// ```
+pub struct Loading<S> {
+    batch: Vec<AssetId>,
+    next: Option<S>,
+}
+
+impl<S> Loading<S> {
+    pub fn new(next: S) -> Self {
+        Self {
+            batch: Vec::new(),
+            next: Some(next),
+        }
+    }
+
+    pub fn add(&mut self, id: impl Into<AssetId>) {
+        self.batch.push(id.into());
+    }
+
+    pub fn with(mut self, id: impl Into<AssetId>) -> Self {
+        self.add(id);
+        self
+    }
+
+    /// Adds every asset of a preload group, see `AssetServer::load_group`
+    pub fn extend(&mut self, ids: impl IntoIterator<Item = AssetId>) {
+        self.batch.extend(ids);
+    }
+
+    /// How far the batch is, in 0..1, for a progress bar
+    pub fn progress(&self, assets: &AssetServer) -> f32 {
+        self.details(assets).fraction
+    }
+
+    /// Asset counts per stage and bytes read, for more detailed loading screens
+    pub fn details(&self, assets: &AssetServer) -> BatchProgress {
+        assets.batch_progress(self.batch.iter().copied())
+    }
+
+    pub fn len(&self) -> usize {
+        self.batch.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+
+    /// Paths and errors of the assets and dependencies that failed so far, the next state still follows so it can handle them
+    pub fn failed(&self, assets: &AssetServer) -> Vec<(PathBuf, String)> {
+        assets.with_dependencies(self.batch.iter().copied()).into_iter()
+            .filter_map(|id| match assets.state(id) {
+                LoadState::Failed(error) => Some((assets.path(id).to_path_buf(), error)),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Returns the next state once, when every asset of the batch and its dependencies is done
+    pub fn poll(&mut self, assets: &AssetServer) -> Option<S> {
+        match self.details(assets).is_done() {
+            true => self.next.take(),
+            false => None
+        }
+    }
+}
\ No newline at end of file
@@ -1,11 +1,545 @@
-pub struct Manager {
-    
+pub mod import;
+pub mod loading;
+
+use std::{
+    any::{
+        Any,
+        TypeId
+    },
+    collections::{
+        HashMap,
+        HashSet
+    },
+    fmt,
+    fs::File,
+    hash::{
+        Hash,
+        Hasher
+    },
+    io::{
+        self,
+        Read
+    },
+    marker::PhantomData,
+    path::{
+        Path,
+        PathBuf
+    },
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering
+        },
+        Arc
+    }
+};
+
+use crate::{
+    config::AssetConfig,
+    tasks::{
+        Task,
+        TaskPool
+    }
+};
+
+type AnyAsset = Box<dyn Any + Send + Sync>;
+type Loader = Arc<dyn Fn(&[u8], &mut LoadContext) -> io::Result<AnyAsset> + Send + Sync>;
+/// `AssetServer::load` for a type known when it was stored
+type LoadFn = fn(&mut AssetServer, &Path) -> AssetId;
+type Dependencies = Vec<(PathBuf, LoadFn)>;
+
+/// Index of an asset in its `AssetServer`, shared by every handle to the same path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AssetId(u32);
+
+/// Typed reference to an asset that may still be loading
+pub struct Handle<T> {
+    id: AssetId,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn id(&self) -> AssetId {
+        self.id
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.id.0).finish()
+    }
+}
+
+impl<T> From<Handle<T>> for AssetId {
+    fn from(handle: Handle<T>) -> Self {
+        handle.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadState {
+    /// The file is being read on the IO pool
+    Reading,
+    /// The bytes are being turned into the asset on the compute pool
+    Decoding,
+    Loaded,
+    Failed(String),
+    /// Dropped by `unload_group`, loading it again starts over
+    Unloaded,
+}
+
+impl LoadState {
+    /// Loaded or failed, either way nothing more will happen
+    pub fn is_done(&self) -> bool {
+        matches!(self, LoadState::Loaded | LoadState::Failed(_))
+    }
+}
+
+/// Progress of one asset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub state: LoadState,
+    pub bytes_read: u64,
+    /// File size, 0 until the file is opened
+    pub total_bytes: u64,
 }
 
-impl Manager {
-    pub fn new() -> Self {
+impl LoadProgress {
+    /// Reading counts as the first half and decoding as the second, in 0..1
+    pub fn fraction(&self) -> f32 {
+        match self.state {
+            LoadState::Reading if self.total_bytes > 0 => 0.5 * self.bytes_read as f32 / self.total_bytes as f32,
+            LoadState::Reading => 0.0,
+            LoadState::Decoding => 0.5,
+            LoadState::Loaded | LoadState::Failed(_) => 1.0,
+            LoadState::Unloaded => 0.0
+        }
+    }
+}
+
+/// Combined progress of several assets
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BatchProgress {
+    pub assets: usize,
+    pub reading: usize,
+    pub decoding: usize,
+    pub loaded: usize,
+    pub failed: usize,
+    pub unloaded: usize,
+    pub bytes_read: u64,
+    /// Sizes of the files opened so far
+    pub total_bytes: u64,
+    /// Average of the assets' `LoadProgress::fraction`, 1 for an empty batch
+    pub fraction: f32,
+}
+
+impl BatchProgress {
+    pub fn is_done(&self) -> bool {
+        self.loaded + self.failed == self.assets
+    }
+}
+
+/// Byte counts shared with the reading task
+#[derive(Default)]
+struct ReadCounter {
+    read: AtomicU64,
+    total: AtomicU64,
+}
+
+/// Handed to loaders to declare the assets the decoded one depends on
+pub struct LoadContext<'a> {
+    path: &'a Path,
+    dependencies: Dependencies,
+}
+
+impl LoadContext<'_> {
+    /// Path of the asset being decoded, relative to the asset root
+    pub fn path(&self) -> &Path {
+        self.path
+    }
+
+    /// Loads another asset along with this one, such as a material's textures.
+    /// `AssetServer::load` with the same path and type later returns its handle.
+    pub fn depend<T: Send + Sync + 'static>(&mut self, path: impl AsRef<Path>) {
+        self.dependencies.push((path.as_ref().to_path_buf(), load_erased::<T>));
+    }
+}
+
+enum Stage {
+    Reading(Task<io::Result<Vec<u8>>>),
+    Decoding(Task<io::Result<(AnyAsset, Dependencies)>>),
+    Loaded(AnyAsset),
+    Failed(String),
+    Unloaded,
+}
+
+struct Entry {
+    path: PathBuf,
+    type_id: TypeId,
+    type_name: &'static str,
+    stage: Stage,
+    counter: Arc<ReadCounter>,
+    dependencies: Vec<AssetId>,
+}
+
+/// Assets loaded and unloaded as a unit, such as everything a level needs
+#[derive(Default)]
+struct Group {
+    members: Vec<(PathBuf, LoadFn)>,
+    loaded: Option<Vec<AssetId>>,
+}
+
+/// Loads assets in the background, files are read on the IO pool and decoded on the compute pool.
+/// Paths are relative to the configured asset root, then the search paths in order.
+pub struct AssetServer {
+    roots: Arc<Vec<PathBuf>>,
+    cache: PathBuf,
+    tasks: Arc<TaskPool>,
+    loaders: HashMap<(TypeId, String), Loader>,
+    entries: Vec<Entry>,
+    by_path: HashMap<(TypeId, PathBuf), AssetId>,
+    groups: HashMap<String, Group>,
+}
+
+impl AssetServer {
+    pub fn new(config: &AssetConfig, tasks: Arc<TaskPool>) -> Self {
+        let roots = std::iter::once(config.root.clone())
+            .chain(config.search_paths.iter().cloned())
+            .collect();
+
         Self {
+            roots: Arc::new(roots),
+            cache: config.cache.clone(),
+            tasks,
+            loaders: HashMap::new(),
+            entries: Vec::new(),
+            by_path: HashMap::new(),
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Decodes files with one of the extensions into `T`, replacing an earlier loader for the same type and extension
+    pub fn register_loader<T, F>(&mut self, extensions: &[&str], load: F)
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&[u8], &mut LoadContext) -> io::Result<T> + Send + Sync + 'static
+    {
+        let load: Loader = Arc::new(move |bytes, context| load(bytes, context).map(|asset| Box::new(asset) as AnyAsset));
+        for extension in extensions {
+            self.loaders.insert((TypeId::of::<T>(), extension.to_lowercase()), load.clone());
+        }
+    }
+
+    /// Starts loading the file unless it is already loaded or loading as `T`
+    pub fn load<T: Send + Sync + 'static>(&mut self, path: impl AsRef<Path>) -> Handle<T> {
+        let path = path.as_ref().to_path_buf();
+        let key = (TypeId::of::<T>(), path.clone());
+        let id = match self.by_path.get(&key) {
+            Some(&id) => id,
+            None => {
+                let id = AssetId(self.entries.len() as u32);
+                self.entries.push(Entry {
+                    path,
+                    type_id: TypeId::of::<T>(),
+                    type_name: std::any::type_name::<T>(),
+                    stage: Stage::Unloaded,
+                    counter: Arc::new(ReadCounter::default()),
+                    dependencies: Vec::new(),
+                });
+                self.by_path.insert(key, id);
+                id
+            }
+        };
+
+        if matches!(self.entries[id.0 as usize].stage, Stage::Unloaded) {
+            self.start(id);
+        }
+
+        Handle {
+            id,
+            marker: PhantomData,
+        }
+    }
+
+    /// `None` until the asset is loaded
+    pub fn get<T: 'static>(&self, handle: Handle<T>) -> Option<&T> {
+        match &self.entries[handle.id.0 as usize].stage {
+            Stage::Loaded(asset) => asset.downcast_ref(),
+            _ => None
+        }
+    }
+
+    pub fn state(&self, id: impl Into<AssetId>) -> LoadState {
+        match &self.entries[id.into().0 as usize].stage {
+            Stage::Reading(_) => LoadState::Reading,
+            Stage::Decoding(_) => LoadState::Decoding,
+            Stage::Loaded(_) => LoadState::Loaded,
+            Stage::Failed(error) => LoadState::Failed(error.clone()),
+            Stage::Unloaded => LoadState::Unloaded
+        }
+    }
+
+    pub fn progress(&self, id: impl Into<AssetId>) -> LoadProgress {
+        let id = id.into();
+        let counter = &self.entries[id.0 as usize].counter;
+        LoadProgress {
+            state: self.state(id),
+            bytes_read: counter.read.load(Ordering::Relaxed),
+            total_bytes: counter.total.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Combined progress of the given assets and their dependencies, for loading screens.
+    /// Dependencies are only known once an asset is decoded, so the fraction can drop when they show up.
+    pub fn batch_progress(&self, ids: impl IntoIterator<Item = AssetId>) -> BatchProgress {
+        let mut batch = BatchProgress::default();
+        let mut fraction = 0.0;
+
+        for id in self.with_dependencies(ids) {
+            let progress = self.progress(id);
+            batch.assets += 1;
+            batch.bytes_read += progress.bytes_read;
+            batch.total_bytes += progress.total_bytes;
+            fraction += progress.fraction();
+            match progress.state {
+                LoadState::Reading => batch.reading += 1,
+                LoadState::Decoding => batch.decoding += 1,
+                LoadState::Loaded => batch.loaded += 1,
+                LoadState::Failed(_) => batch.failed += 1,
+                LoadState::Unloaded => batch.unloaded += 1
+            }
+        }
+
+        batch.fraction = match batch.assets {
+            0 => 1.0,
+            assets => fraction / assets as f32
+        };
+        batch
+    }
+
+    /// Combined progress of every asset requested so far
+    pub fn total_progress(&self) -> BatchProgress {
+        self.batch_progress((0..self.entries.len() as u32).map(AssetId))
+    }
+
+    pub fn is_loaded(&self, id: impl Into<AssetId>) -> bool {
+        matches!(self.entries[id.into().0 as usize].stage, Stage::Loaded(_))
+    }
+
+    /// Whether the asset and everything it depends on, directly or not, is loaded
+    pub fn is_loaded_with_dependencies(&self, id: impl Into<AssetId>) -> bool {
+        self.with_dependencies([id.into()]).into_iter().all(|id| self.is_loaded(id))
+    }
+
+    /// Assets the loader declared with `LoadContext::depend`, empty until the asset is decoded
+    pub fn dependencies(&self, id: impl Into<AssetId>) -> &[AssetId] {
+        &self.entries[id.into().0 as usize].dependencies
+    }
+
+    /// The assets and their dependencies, directly or not, each once
+    pub fn with_dependencies(&self, ids: impl IntoIterator<Item = AssetId>) -> Vec<AssetId> {
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        let mut stack: Vec<AssetId> = ids.into_iter().collect();
+        stack.reverse();
+
+        while let Some(id) = stack.pop() {
+            if seen.insert(id) {
+                result.push(id);
+                stack.extend(self.dependencies(id).iter().rev());
+            }
+        }
+        result
+    }
+
+    /// Adds an asset to a named preload group, creating the group if needed.
+    /// If the group is loaded, the asset is loaded right away.
+    pub fn add_to_group<T: Send + Sync + 'static>(&mut self, group: &str, path: impl AsRef<Path>) {
+        let path = path.as_ref().to_path_buf();
+        let loaded = self.groups.get(group).is_some_and(|group| group.loaded.is_some());
+        let id = loaded.then(|| self.load::<T>(&path).id);
+
+        let group = self.groups.entry(group.to_string()).or_default();
+        group.members.push((path, load_erased::<T>));
+        if let (Some(loaded), Some(id)) = (&mut group.loaded, id) {
+            loaded.push(id);
+        }
+    }
+
+    /// Starts loading every asset of the group, returning their ids for a `Loading` batch.
+    /// Unknown groups are empty.
+    pub fn load_group(&mut self, group: &str) -> Vec<AssetId> {
+        let members = match self.groups.get(group) {
+            Some(group) => group.members.clone(),
+            None => return Vec::new()
+        };
+
+        let ids: Vec<AssetId> = members.iter().map(|(path, load)| load(self, path)).collect();
+        if let Some(group) = self.groups.get_mut(group) {
+            group.loaded = Some(ids.clone());
+        }
+        ids
+    }
+
+    /// Drops the group's assets and their dependencies, except those another loaded group still uses.
+    /// Handles stay valid, their assets read as unloaded until loaded again.
+    pub fn unload_group(&mut self, group: &str) {
+        let ids = match self.groups.get_mut(group).and_then(|group| group.loaded.take()) {
+            Some(ids) => ids,
+            None => return
+        };
+
+        let kept: HashSet<AssetId> = self.with_dependencies(
+            self.groups.values().filter_map(|group| group.loaded.as_ref()).flatten().copied().collect::<Vec<_>>()
+        ).into_iter().collect();
+
+        for id in self.with_dependencies(ids) {
+            if !kept.contains(&id) {
+                let entry = &mut self.entries[id.0 as usize];
+                entry.stage = Stage::Unloaded;
+                entry.dependencies.clear();
+            }
+        }
+    }
+
+    pub fn is_group_loaded(&self, group: &str) -> bool {
+        self.groups.get(group).is_some_and(|group| group.loaded.is_some())
+    }
+
+    /// Combined progress of a loaded group and its dependencies
+    pub fn group_progress(&self, group: &str) -> BatchProgress {
+        let ids = self.groups.get(group)
+            .and_then(|group| group.loaded.clone())
+            .unwrap_or_default();
+        self.batch_progress(ids)
+    }
+
+    /// Path the asset was requested with
+    pub fn path(&self, id: impl Into<AssetId>) -> &Path {
+        &self.entries[id.into().0 as usize].path
+    }
+
+    /// Moves finished reads on to decoding, stores finished assets and starts loading their dependencies.
+    /// Called by the engine every frame.
+    pub(crate) fn update(&mut self) {
+        for index in 0..self.entries.len() {
+            let entry = &mut self.entries[index];
+            let mut dependencies = Vec::new();
+            let next = match &mut entry.stage {
+                Stage::Reading(task) => match task.try_take() {
+                    Some(Ok(bytes)) => {
+                        let load = self.loaders[&(entry.type_id, extension(&entry.path))].clone();
+                        let path = entry.path.clone();
+                        Stage::Decoding(self.tasks.spawn("asset_decode", move || {
+                            let mut context = LoadContext {
+                                path: &path,
+                                dependencies: Vec::new(),
+                            };
+                            let asset = load(&bytes, &mut context)?;
+                            Ok((asset, context.dependencies))
+                        }))
+                    },
+                    Some(Err(error)) => Stage::Failed(format!("{}: {}", entry.path.display(), error)),
+                    None => continue
+                },
+                Stage::Decoding(task) => match task.try_take() {
+                    Some(Ok((asset, declared))) => {
+                        dependencies = declared;
+                        Stage::Loaded(asset)
+                    },
+                    Some(Err(error)) => Stage::Failed(format!("{}: {}", entry.path.display(), error)),
+                    None => continue
+                },
+                _ => continue
+            };
+            entry.stage = next;
+
+            for (path, load) in dependencies {
+                let id = load(self, &path);
+                self.entries[index].dependencies.push(id);
+            }
+        }
+    }
+
+    fn start(&mut self, id: AssetId) {
+        let entry = &self.entries[id.0 as usize];
+        let stage = match self.loaders.contains_key(&(entry.type_id, extension(&entry.path))) {
+            true => {
+                let roots = self.roots.clone();
+                let path = entry.path.clone();
+                let counter = Arc::new(ReadCounter::default());
+                self.entries[id.0 as usize].counter = counter.clone();
+                Stage::Reading(self.tasks.spawn_io("asset_read", move || read(&roots, &path, &counter)))
+            },
+            false => Stage::Failed(format!("no loader for {} as {}", entry.path.display(), entry.type_name))
+        };
+        self.entries[id.0 as usize].stage = stage;
+    }
+}
+
+fn load_erased<T: Send + Sync + 'static>(server: &mut AssetServer, path: &Path) -> AssetId {
+    server.load::<T>(path).id
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Chunk size of reads, the byte count is updated after each one
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Reads from the first root that has the file
+fn read(roots: &[PathBuf], path: &Path, counter: &ReadCounter) -> io::Result<Vec<u8>> {
+    for root in roots {
+        match File::open(root.join(path)) {
+            Ok(file) => return read_counted(file, counter),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error)
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::NotFound, "not found in the asset root or search paths"))
+}
+
+fn read_counted(mut file: File, counter: &ReadCounter) -> io::Result<Vec<u8>> {
+    let total = file.metadata()?.len();
+    counter.total.store(total, Ordering::Relaxed);
 
+    let mut bytes = Vec::with_capacity(total as usize);
+    let mut chunk = vec![0; READ_CHUNK];
+    loop {
+        match file.read(&mut chunk) {
+            Ok(0) => return Ok(bytes),
+            Ok(read) => {
+                bytes.extend_from_slice(&chunk[..read]);
+                counter.read.store(bytes.len() as u64, Ordering::Relaxed);
+            },
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error)
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,180 @@
+use crate::math::{
+    Mat4,
+    Vec2
+};
+
+/// Largest shake offset in world units at full trauma
+const SHAKE_OFFSET: f32 = 16.0;
+/// Largest shake rotation in radians at full trauma
+const SHAKE_ANGLE: f32 = 0.1;
+/// Shake noise samples per second
+const SHAKE_FREQUENCY: f32 = 25.0;
+
+/// Camera for 2D scenes. World units are pixels at zoom 1 and the y axis points down like on screen,
+/// matching tilemaps. `position` is the world point at the center of the viewport.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera2D {
+    pub position: Vec2,
+    /// Screen pixels per world unit
+    pub zoom: f32,
+    /// Radians, positive turns the view clockwise
+    pub rotation: f32,
+    /// Rounds the zoom to whole numbers and the view to whole pixels, so pixel art stays crisp
+    pub pixel_perfect: bool,
+    /// Half size of the area around the center the follow target can move in without the camera moving
+    pub deadzone: Vec2,
+    /// How fast `follow` catches up, higher is tighter. Zero snaps to the target.
+    pub follow_speed: f32,
+    /// Trauma lost per second
+    pub trauma_decay: f32,
+    trauma: f32,
+    shake_time: f32,
+    shake_seed: u32,
+}
+
+impl Camera2D {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position,
+            zoom: 1.0,
+            rotation: 0.0,
+            pixel_perfect: false,
+            deadzone: Vec2::ZERO,
+            follow_speed: 8.0,
+            trauma_decay: 1.0,
+            trauma: 0.0,
+            shake_time: 0.0,
+            shake_seed: 0,
+        }
+    }
+
+    /// Moves toward `target` once it leaves the deadzone. Call once per update.
+    pub fn follow(&mut self, target: Vec2, delta: f32) {
+        let offset = target - self.position;
+        let outside = offset - offset.clamp(-self.deadzone, self.deadzone);
+        if outside == Vec2::ZERO {
+            return
+        }
+
+        // Frame rate independent exponential smoothing
+        let t = match self.follow_speed > 0.0 {
+            true => 1.0 - (-self.follow_speed * delta).exp(),
+            false => 1.0
+        };
+        self.position += outside * t;
+    }
+
+    /// Adds shake, clamped to 0..1. Small hits add around 0.2, explosions 0.5 or more.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Seed for the shake noise, set from the engine's random service to keep replays identical
+    pub fn set_shake_seed(&mut self, seed: u32) {
+        self.shake_seed = seed;
+    }
+
+    /// Advances the shake and decays trauma
+    pub fn update(&mut self, delta: f32) {
+        self.shake_time += delta;
+        self.trauma = (self.trauma - self.trauma_decay * delta).max(0.0);
+    }
+
+    /// Current shake as a world offset and an angle, proportional to trauma squared so small hits stay subtle
+    pub fn shake(&self) -> (Vec2, f32) {
+        let amount = self.trauma * self.trauma;
+        if amount == 0.0 {
+            return (Vec2::ZERO, 0.0)
+        }
+
+        let t = self.shake_time * SHAKE_FREQUENCY;
+        let offset = Vec2::new(noise(self.shake_seed, t), noise(self.shake_seed.wrapping_add(1), t)) * SHAKE_OFFSET;
+        let angle = noise(self.shake_seed.wrapping_add(2), t) * SHAKE_ANGLE;
+        (offset * amount / self.zoom(), angle * amount)
+    }
+
+    /// Zoom in use, rounded in pixel perfect mode
+    pub fn zoom(&self) -> f32 {
+        match self.pixel_perfect {
+            true => self.zoom.round().max(1.0),
+            false => self.zoom.max(f32::EPSILON)
+        }
+    }
+
+    /// Center of the view with shake applied, snapped to whole screen pixels in pixel perfect mode
+    pub fn view_center(&self) -> Vec2 {
+        let center = self.position + self.shake().0;
+        match self.pixel_perfect {
+            true => (center * self.zoom()).round() / self.zoom(),
+            false => center
+        }
+    }
+
+    fn view_rotation(&self) -> f32 {
+        self.rotation + self.shake().1
+    }
+
+    /// World to clip space with a 0..1 depth range, for a viewport size in pixels
+    pub fn view_projection(&self, viewport_size: Vec2) -> Mat4 {
+        let half = viewport_size * 0.5 / self.zoom();
+        // Top and bottom swapped so y points down
+        let projection = Mat4::orthographic_rh(-half.x, half.x, half.y, -half.y, -1.0, 1.0);
+        let view = Mat4::from_rotation_z(-self.view_rotation()) * Mat4::from_translation(-self.view_center().extend(0.0));
+        projection * view
+    }
+
+    /// Pixel position from the top left of the viewport to world position
+    pub fn screen_to_world(&self, screen: Vec2, viewport_size: Vec2) -> Vec2 {
+        let local = (screen - viewport_size * 0.5) / self.zoom();
+        Vec2::from_angle(self.view_rotation()).rotate(local) + self.view_center()
+    }
+
+    /// World position to pixels from the top left of the viewport
+    pub fn world_to_screen(&self, world: Vec2, viewport_size: Vec2) -> Vec2 {
+        let local = Vec2::from_angle(-self.view_rotation()).rotate(world - self.view_center());
+        local * self.zoom() + viewport_size * 0.5
+    }
+
+    /// Corners of the visible world area's bounding box, for culling
+    pub fn visible_bounds(&self, viewport_size: Vec2) -> (Vec2, Vec2) {
+        let corners = [
+            Vec2::ZERO,
+            Vec2::new(viewport_size.x, 0.0),
+            Vec2::new(0.0, viewport_size.y),
+            viewport_size
+        ].map(|corner| self.screen_to_world(corner, viewport_size));
+
+        corners.iter().fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)), |(min, max), corner| {
+            (min.min(*corner), max.max(*corner))
+        })
+    }
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self::new(Vec2::ZERO)
+    }
+}
+
+/// Smooth value noise in -1..1
+fn noise(seed: u32, t: f32) -> f32 {
+    let cell = t.floor();
+    let fraction = t - cell;
+    let blend = fraction * fraction * (3.0 - 2.0 * fraction);
+
+    let a = hash(seed, cell as i32);
+    let b = hash(seed, cell as i32 + 1);
+    a + (b - a) * blend
+}
+
+fn hash(seed: u32, x: i32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x27D4_EB2D) ^ seed.wrapping_mul(0x1656_67B1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x85EB_CA6B);
+    h ^= h >> 13;
+    (h & 0xFFFF) as f32 / 32767.5 - 1.0
+}
\ No newline at end of file
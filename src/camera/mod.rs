@@ -0,0 +1,97 @@
+pub mod camera2d;
+
+use std::hash::Hash;
+
+use crate::{
+    math::{
+        Mat4,
+        Quat,
+        Ray,
+        Vec2,
+        Vec3
+    },
+    spatial::{
+        RayHit,
+        SpatialIndex
+    }
+};
+
+pub use camera2d::Camera2D;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Vertical field of view in radians
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// Height of the view volume in world units
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+/// Right handed camera looking down its local -Z axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub projection: Projection,
+}
+
+impl Camera {
+    pub fn new(projection: Projection) -> Self {
+        Self {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            projection,
+        }
+    }
+
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let view = Mat4::look_at_rh(self.position, target, up);
+        self.rotation = Quat::from_mat4(&view.inverse());
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.rotation, self.position).inverse()
+    }
+
+    /// Projection with a 0..1 depth range
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                Mat4::perspective_rh(fov_y, aspect_ratio, near, far)
+            },
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * aspect_ratio;
+                Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, near, far)
+            }
+        }
+    }
+
+    pub fn view_projection(&self, aspect_ratio: f32) -> Mat4 {
+        self.projection_matrix(aspect_ratio) * self.view()
+    }
+
+    /// Ray from the near plane through a cursor position given in pixels from the top left of the viewport.
+    /// Also returns the distance to the far plane along the ray.
+    pub fn viewport_to_ray(&self, cursor: Vec2, viewport_size: Vec2) -> (Ray, f32) {
+        let ndc = Vec2::new(
+            2.0 * cursor.x / viewport_size.x - 1.0,
+            1.0 - 2.0 * cursor.y / viewport_size.y
+        );
+
+        let inverse = self.view_projection(viewport_size.x / viewport_size.y).inverse();
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+
+        (Ray::new(near, far - near), near.distance(far))
+    }
+
+    /// Nearest object in the index under the cursor, limited to the view volume
+    pub fn pick<K: Copy + Eq + Hash>(&self, cursor: Vec2, viewport_size: Vec2, index: &SpatialIndex<K>) -> Option<RayHit<K>> {
+        let (ray, max_distance) = self.viewport_to_ray(cursor, viewport_size);
+        index.raycast(&ray, max_distance)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::{
+    io,
+    path::PathBuf
+};
+
+use super::Config;
+
+/// Engine options given on the command line, applied on top of the config file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EngineArgs {
+    /// `--windowed` or `--fullscreen`
+    pub fullscreen: Option<bool>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// `--gpu <n>`
+    pub gpu: Option<usize>,
+    pub validation: bool,
+    /// Runs without a window or renderer
+    pub headless: bool,
+    /// `--assets <dir>`
+    pub assets: Option<PathBuf>,
+    /// `--set <key>=<value>`, applied through `Config::set`
+    pub overrides: Vec<String>,
+    /// Arguments the engine does not know, and everything after `--`, left for the game
+    pub rest: Vec<String>,
+}
+
+impl EngineArgs {
+    /// Parses the process arguments, skipping the program name
+    pub fn from_env() -> io::Result<EngineArgs> {
+        EngineArgs::parse(std::env::args().skip(1))
+    }
+
+    /// Accepts both `--flag value` and `--flag=value`
+    pub fn parse<I, S>(args: I) -> io::Result<EngineArgs>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>
+    {
+        let mut parsed = EngineArgs::default();
+        let mut args = args.into_iter().map(Into::into);
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.rest.extend(args.by_ref());
+                break
+            }
+
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None)
+            };
+
+            let mut value = || inline_value.clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| invalid(format!("`{}` expects a value", flag)));
+
+            match flag.as_str() {
+                "--windowed" => parsed.fullscreen = Some(false),
+                "--fullscreen" => parsed.fullscreen = Some(true),
+                "--width" => parsed.width = Some(parse_number(&flag, &value()?)?),
+                "--height" => parsed.height = Some(parse_number(&flag, &value()?)?),
+                "--gpu" => parsed.gpu = Some(parse_number(&flag, &value()?)?),
+                "--validation" => parsed.validation = true,
+                "--headless" => parsed.headless = true,
+                "--assets" => parsed.assets = Some(PathBuf::from(value()?)),
+                "--set" => parsed.overrides.push(value()?),
+                _ => parsed.rest.push(arg)
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Writes the options into the config, `--set` overrides last so they win
+    pub fn apply(&self, config: &mut Config) -> io::Result<()> {
+        if let Some(fullscreen) = self.fullscreen {
+            config.window.fullscreen = fullscreen;
+        }
+        if let Some(width) = self.width {
+            config.window.width = width;
+        }
+        if let Some(height) = self.height {
+            config.window.height = height;
+        }
+        if let Some(gpu) = self.gpu {
+            config.device.gpu = Some(gpu);
+        }
+        if self.validation {
+            config.device.validation = true;
+        }
+        if let Some(assets) = &self.assets {
+            config.assets.root = assets.clone();
+        }
+
+        config.apply_overrides(self.overrides.iter().map(String::as_str))
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> io::Result<T> {
+    value.parse().map_err(|_| invalid(format!("`{}` expects a number, got `{}`", flag, value)))
+}
\ No newline at end of file
@@ -0,0 +1,329 @@
+use std::{
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+pub mod args;
+
+use crate::{
+    input::actions::ActionMap,
+    renderer::settings::RenderSettings,
+    timing::DEFAULT_MAX_FIXED_STEPS,
+    window::DisplayMode
+};
+
+/// Default file name, looked up in the working directory
+pub const CONFIG_FILE: &str = "aspen.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Ron,
+}
+
+impl ConfigFormat {
+    /// `.ron` files are read as RON, everything else as TOML
+    pub fn from_path(path: &Path) -> ConfigFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ron") => ConfigFormat::Ron,
+            _ => ConfigFormat::Toml
+        }
+    }
+}
+
+/// Engine settings loaded at startup, missing entries fall back to their defaults
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub render: RenderSettings,
+    pub device: DeviceConfig,
+    pub timing: TimingConfig,
+    pub tasks: TaskConfig,
+    pub log: LogConfig,
+    pub assets: AssetConfig,
+    pub audio: AudioConfig,
+    pub locale: LocaleConfig,
+    /// Action bindings, kept up to date by `Context::save_config`
+    pub input: ActionMap,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub resizable: bool,
+    /// Native title bar and borders, turn off to draw a custom title bar with `AspenWindow::set_caption_areas`
+    pub decorations: bool,
+    /// Borderless fullscreen on the current monitor, or exclusive with `video_mode` set
+    pub fullscreen: bool,
+    /// Resolution and refresh rate for exclusive fullscreen, the monitor's closest mode is used
+    pub video_mode: Option<DisplayMode>,
+    /// Creates the window with a transparent background through winit. The frame only shows the desktop through
+    /// once a GPU backend presents with composite alpha, which the renderer does not do yet.
+    pub transparent: bool,
+    pub always_on_top: bool,
+    /// Passes mouse input through to the windows below, for overlays
+    pub click_through: bool,
+}
+
+/// Read by the graphics backend when it creates its device
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceConfig {
+    /// Index of the GPU to use, `None` picks the best available one
+    pub gpu: Option<usize>,
+    /// Enables the graphics API's validation layers
+    pub validation: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimingConfig {
+    /// Fixed updates per second
+    pub fixed_rate: u16,
+    /// Most fixed updates run in one frame, the time beyond them is dropped after a hitch
+    pub max_fixed_steps: u64,
+    pub target_fps: Option<f64>,
+}
+
+/// Thread counts for the task pool, 0 picks one from the number of cores
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskConfig {
+    pub compute_threads: usize,
+    pub io_threads: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// The engine does not log on its own yet, the level is stored for the game's logger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub level: LogLevel,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetConfig {
+    pub root: PathBuf,
+    /// Searched after the root, in order
+    pub search_paths: Vec<PathBuf>,
+    /// Where imported assets are cached in their processed form
+    pub cache: PathBuf,
+}
+
+/// Volumes in 0..1, stored for the game until the engine has an audio module
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+}
+
+/// Languages are loaded from `<assets root>/<dir>/<language>.toml`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocaleConfig {
+    pub language: String,
+    /// Strings missing from the current language are taken from this one
+    pub fallback: String,
+    pub dir: PathBuf,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Config> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        Config::parse(&text, ConfigFormat::from_path(path))
+    }
+
+    /// Loads the file if it exists, otherwise returns the defaults
+    pub fn load_or_default(path: impl AsRef<Path>) -> io::Result<Config> {
+        match Config::load(path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            result => result
+        }
+    }
+
+    pub fn parse(text: &str, format: ConfigFormat) -> io::Result<Config> {
+        let invalid = |err: String| io::Error::new(io::ErrorKind::InvalidData, err);
+        match format {
+            ConfigFormat::Toml => toml::from_str(text).map_err(|err| invalid(err.to_string())),
+            ConfigFormat::Ron => ron::from_str(text).map_err(|err| invalid(err.to_string()))
+        }
+    }
+
+    pub fn to_text(&self, format: ConfigFormat) -> io::Result<String> {
+        match format {
+            ConfigFormat::Toml => toml::to_string_pretty(self).map_err(io::Error::other),
+            ConfigFormat::Ron => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(io::Error::other)
+        }
+    }
+
+    /// Writes the config back in the format matching the file extension
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_text(ConfigFormat::from_path(path))?)
+    }
+
+    /// Sets a single value by its dotted path, e.g. `window.width` and `1280`.
+    /// The value is parsed as TOML, falling back to a plain string.
+    pub fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let unknown = || invalid(format!("unknown config key `{}`", key));
+
+        let mut root = toml::Value::try_from(&*self).map_err(io::Error::other)?;
+        let parsed = format!("v = {}", value).parse::<toml::Table>().ok()
+            .and_then(|mut table| table.remove("v"))
+            .unwrap_or_else(|| toml::Value::String(value.to_string()));
+
+        let mut parts = key.split('.').peekable();
+        let mut current = &mut root;
+        while let Some(part) = parts.next() {
+            let table = current.as_table_mut().ok_or_else(|| invalid(format!("`{}` is not a section", key)))?;
+
+            if parts.peek().is_none() {
+                table.insert(part.to_string(), parsed);
+                break
+            }
+
+            current = table.get_mut(part).ok_or_else(unknown)?;
+        }
+
+        let config: Config = root.try_into().map_err(|err| invalid(format!("invalid value for `{}`: {}", key, err)))?;
+
+        // Unknown fields are skipped when deserializing, so a misspelled key only shows up as missing here.
+        // Unset options are left out of the table until they get a value, which is why this checks after setting it.
+        let written = toml::Value::try_from(&config).map_err(io::Error::other)?;
+        key.split('.').try_fold(&written, |value, part| value.get(part)).ok_or_else(unknown)?;
+
+        *self = config;
+        Ok(())
+    }
+
+    /// Applies `key=value` overrides in order, such as ones taken from the command line
+    pub fn apply_overrides<'a>(&mut self, overrides: impl IntoIterator<Item = &'a str>) -> io::Result<()> {
+        for entry in overrides {
+            let (key, value) = entry.split_once('=')
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("expected key=value, got `{}`", entry)))?;
+            self.set(key.trim(), value.trim())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "Aspen Engine".to_string(),
+            width: 1280,
+            height: 720,
+            resizable: true,
+            decorations: true,
+            fullscreen: false,
+            video_mode: None,
+            transparent: false,
+            always_on_top: false,
+            click_through: false,
+        }
+    }
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Self {
+            fixed_rate: 100,
+            max_fixed_steps: DEFAULT_MAX_FIXED_STEPS,
+            target_fps: None,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+        }
+    }
+}
+
+impl Default for AssetConfig {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("assets"),
+            search_paths: Vec::new(),
+            cache: PathBuf::from(".aspen-cache"),
+        }
+    }
+}
+
+impl Default for LocaleConfig {
+    fn default() -> Self {
+        Self {
+            language: "en".to_string(),
+            fallback: "en".to_string(),
+            dir: PathBuf::from("locale"),
+        }
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 1.0,
+            effects: 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_updates_known_keys() {
+        let mut config = Config::default();
+        config.set("window.width", "1280").unwrap();
+        config.set("window.title", "Demo").unwrap();
+        config.set("timing.target_fps", "144").unwrap();
+
+        assert_eq!(config.window.width, 1280);
+        assert_eq!(config.window.title, "Demo");
+        assert_eq!(config.timing.target_fps, Some(144.0));
+    }
+
+    #[test]
+    fn set_rejects_unknown_keys() {
+        let mut config = Config::default();
+        for key in ["window.widht", "windwo.width", "window.width.value"] {
+            let err = config.set(key, "1280").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", key);
+        }
+        assert_eq!(config, Config::default());
+    }
+}
\ No newline at end of file
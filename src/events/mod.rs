@@ -0,0 +1,76 @@
+use std::{
+    any::{
+        Any,
+        TypeId
+    },
+    collections::HashMap
+};
+
+trait Queue: Any {
+    fn flush(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+struct EventQueue<T> {
+    readable: Vec<T>,
+    pending: Vec<T>,
+}
+
+impl<T: 'static> Queue for EventQueue<T> {
+    fn flush(&mut self) {
+        self.readable = std::mem::take(&mut self.pending);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Typed events passed between engine systems and game code.
+/// Events sent during a frame can be read for the whole next frame, so every reader sees them exactly once
+/// no matter where in the frame it runs.
+#[derive(Default)]
+pub struct EventBus {
+    queues: HashMap<TypeId, Box<dyn Queue>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send<T: 'static>(&mut self, event: T) {
+        self.queue_mut::<T>().pending.push(event);
+    }
+
+    /// Events of this type sent during the previous frame
+    pub fn read<T: 'static>(&self) -> &[T] {
+        self.queues.get(&TypeId::of::<T>())
+            .and_then(|queue| queue.as_any().downcast_ref::<EventQueue<T>>())
+            .map(|queue| queue.readable.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Makes the events sent so far readable and drops the ones read during the last frame
+    pub(crate) fn flush(&mut self) {
+        for queue in self.queues.values_mut() {
+            queue.flush();
+        }
+    }
+
+    fn queue_mut<T: 'static>(&mut self) -> &mut EventQueue<T> {
+        self.queues.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(EventQueue::<T> {
+                readable: Vec::new(),
+                pending: Vec::new(),
+            }))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,491 @@
+use std::f32::consts::{
+    PI,
+    TAU
+};
+
+use crate::{
+    camera::{
+        Camera,
+        Projection
+    },
+    math::{
+        Quat,
+        Ray,
+        Vec2,
+        Vec3,
+        Vec4
+    }
+};
+
+/// Line segments per rotation ring
+const RING_SEGMENTS: usize = 48;
+/// Size of the boxes at the end of scale handles, relative to the handle length
+const SCALE_BOX: f32 = 0.06;
+
+const AXIS_COLORS: [Vec4; 3] = [
+    Vec4::new(0.9, 0.2, 0.2, 1.0),
+    Vec4::new(0.2, 0.8, 0.2, 1.0),
+    Vec4::new(0.2, 0.4, 0.9, 1.0)
+];
+const VIEW_COLOR: Vec4 = Vec4::new(0.9, 0.9, 0.9, 1.0);
+const HIGHLIGHT_COLOR: Vec4 = Vec4::new(1.0, 0.85, 0.1, 1.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// Axes the handles follow. Scaling always uses the local axes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoSpace {
+    World,
+    Local,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoHandle {
+    X,
+    Y,
+    Z,
+    /// Center handle, moves in the view plane or scales uniformly
+    View,
+}
+
+impl GizmoHandle {
+    const AXES: [GizmoHandle; 3] = [GizmoHandle::X, GizmoHandle::Y, GizmoHandle::Z];
+
+    fn index(self) -> Option<usize> {
+        match self {
+            GizmoHandle::X => Some(0),
+            GizmoHandle::Y => Some(1),
+            GizmoHandle::Z => Some(2),
+            GizmoHandle::View => None
+        }
+    }
+}
+
+/// Change since the previous update, apply as `position += translation`,
+/// `rotation = delta.rotation * rotation` and `scale *= delta.scale`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GizmoDelta {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl GizmoDelta {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+}
+
+/// A gizmo line in world space. Draw after the scene without depth testing so handles stay visible.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GizmoLine {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub color: Vec4,
+}
+
+struct Drag {
+    handle: GizmoHandle,
+    origin: Vec3,
+    /// Axis or plane normal fixed when the drag started
+    axis: Vec3,
+    start_point: Vec3,
+    /// Axis position, angle or distance the drag started at
+    start: f32,
+    last_angle: f32,
+    /// Unsnapped total since the drag started
+    total: f32,
+    /// Snapped total already handed out as deltas
+    applied: f32,
+    applied_offset: Vec3,
+}
+
+/// Translate, rotate and scale handles for editors and debug tools.
+/// Handles keep the same size on screen and are picked with the cursor ray.
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    /// Handle length in pixels
+    pub size: f32,
+    /// How far in pixels the cursor can be from a handle to grab it
+    pub pick_radius: f32,
+    /// Step in world units, radians or scale factor the drag snaps to
+    pub snap: Option<f32>,
+    hovered: Option<GizmoHandle>,
+    drag: Option<Drag>,
+}
+
+impl Gizmo {
+    pub fn new(mode: GizmoMode) -> Self {
+        Self {
+            mode,
+            space: GizmoSpace::World,
+            size: 100.0,
+            pick_radius: 6.0,
+            snap: None,
+            hovered: None,
+            drag: None,
+        }
+    }
+
+    /// Handle under the cursor, or the one being dragged
+    pub fn hovered(&self) -> Option<GizmoHandle> {
+        self.drag.as_ref().map(|drag| drag.handle).or(self.hovered)
+    }
+
+    /// Whether a handle is held, so clicks are not passed on to scene picking
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Hit tests the cursor and follows drags, call once per frame with the edited object's transform.
+    /// `pressed` is whether the button that grabs handles is held. Returns a delta while a drag moves the object.
+    pub fn update(
+        &mut self,
+        camera: &Camera,
+        viewport_size: Vec2,
+        cursor: Vec2,
+        pressed: bool,
+        position: Vec3,
+        rotation: Quat
+    ) -> Option<GizmoDelta> {
+        let (ray, _) = camera.viewport_to_ray(cursor, viewport_size);
+        let scale = world_per_pixel(camera, position, viewport_size.y);
+
+        if !pressed {
+            self.drag = None;
+        }
+
+        let Some(drag) = &mut self.drag else {
+            self.hovered = self.pick(&ray, scale, position, rotation);
+            if pressed {
+                if let Some(handle) = self.hovered {
+                    self.drag = self.begin_drag(camera, &ray, handle, position, rotation);
+                }
+            }
+            return None
+        };
+
+        let delta = match self.mode {
+            GizmoMode::Translate => drag_translate(drag, camera, &ray, self.snap),
+            GizmoMode::Rotate => drag_rotate(drag, &ray, self.snap),
+            GizmoMode::Scale => drag_scale(drag, camera, &ray, self.snap)
+        }?;
+
+        match delta == GizmoDelta::IDENTITY {
+            true => None,
+            false => Some(delta)
+        }
+    }
+
+    /// Lines to draw the handles with, highlighting the hovered one
+    pub fn lines(&self, camera: &Camera, viewport_size: Vec2, position: Vec3, rotation: Quat) -> Vec<GizmoLine> {
+        let length = self.size * world_per_pixel(camera, position, viewport_size.y);
+        let axes = self.axes(rotation);
+        let hovered = self.hovered();
+        let color = |handle: GizmoHandle, base: Vec4| match hovered == Some(handle) {
+            true => HIGHLIGHT_COLOR,
+            false => base
+        };
+
+        let mut lines = Vec::new();
+        for (index, handle) in GizmoHandle::AXES.into_iter().enumerate() {
+            let axis = axes[index];
+            let color = color(handle, AXIS_COLORS[index]);
+            match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    let end = position + axis * length;
+                    lines.push(GizmoLine { start: position, end, color });
+
+                    if self.mode == GizmoMode::Scale {
+                        let half = length * SCALE_BOX;
+                        let (side, up) = axis.any_orthonormal_pair();
+                        push_square(&mut lines, end, side * half, up * half, color);
+                    }
+                },
+                GizmoMode::Rotate => {
+                    let (u, v) = axis.any_orthonormal_pair();
+                    push_ring(&mut lines, position, u * length, v * length, color);
+                }
+            }
+        }
+
+        // Square facing the camera for the view handle
+        if self.mode != GizmoMode::Rotate {
+            let half = length * SCALE_BOX * 1.5;
+            let right = camera.rotation * Vec3::X;
+            let up = camera.rotation * Vec3::Y;
+            push_square(&mut lines, position, right * half, up * half, color(GizmoHandle::View, VIEW_COLOR));
+        }
+
+        lines
+    }
+
+    fn axes(&self, rotation: Quat) -> [Vec3; 3] {
+        let rotation = match (self.mode, self.space) {
+            (GizmoMode::Scale, _) | (_, GizmoSpace::Local) => rotation,
+            (_, GizmoSpace::World) => Quat::IDENTITY
+        };
+        [rotation * Vec3::X, rotation * Vec3::Y, rotation * Vec3::Z]
+    }
+
+    fn pick(&self, ray: &Ray, scale: f32, position: Vec3, rotation: Quat) -> Option<GizmoHandle> {
+        let length = self.size * scale;
+        let radius = self.pick_radius * scale;
+        let axes = self.axes(rotation);
+
+        // Nearest handle along the ray within the pick radius
+        let mut best: Option<(f32, GizmoHandle)> = None;
+        let mut consider = |distance: f32, handle: GizmoHandle| {
+            if best.is_none_or(|(best, _)| distance < best) {
+                best = Some((distance, handle));
+            }
+        };
+
+        if self.mode != GizmoMode::Rotate {
+            let (along, distance) = distance_to_point(ray, position);
+            if distance <= radius * 2.0 {
+                consider(along, GizmoHandle::View);
+            }
+        }
+
+        for (index, handle) in GizmoHandle::AXES.into_iter().enumerate() {
+            let axis = axes[index];
+            match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    let Some(on_axis) = closest_on_line(ray, position, axis) else {
+                        continue
+                    };
+                    let (along, distance) = distance_to_point(ray, position + axis * on_axis.clamp(0.0, length));
+                    if distance <= radius {
+                        consider(along, handle);
+                    }
+                },
+                GizmoMode::Rotate => {
+                    let Some(along) = intersect_plane(ray, position, axis) else {
+                        continue
+                    };
+                    if (ray.at(along).distance(position) - length).abs() <= radius {
+                        consider(along, handle);
+                    }
+                }
+            }
+        }
+
+        best.map(|(_, handle)| handle)
+    }
+
+    fn begin_drag(&self, camera: &Camera, ray: &Ray, handle: GizmoHandle, position: Vec3, rotation: Quat) -> Option<Drag> {
+        let axis = match handle.index() {
+            Some(index) => self.axes(rotation)[index],
+            None => camera.forward()
+        };
+
+        let mut drag = Drag {
+            handle,
+            origin: position,
+            axis,
+            start_point: position,
+            start: 0.0,
+            last_angle: 0.0,
+            total: 0.0,
+            applied: 0.0,
+            applied_offset: Vec3::ZERO,
+        };
+
+        match (self.mode, handle) {
+            (GizmoMode::Translate, GizmoHandle::View) => {
+                drag.start_point = ray.at(intersect_plane(ray, position, axis)?);
+            },
+            (GizmoMode::Scale, GizmoHandle::View) => {
+                drag.start = ray.at(intersect_plane(ray, position, axis)?).distance(position).max(f32::EPSILON);
+                drag.applied = 1.0;
+            },
+            (GizmoMode::Translate, _) => {
+                drag.start = closest_on_line(ray, position, axis)?;
+            },
+            (GizmoMode::Scale, _) => {
+                drag.start = closest_on_line(ray, position, axis)?;
+                drag.applied = 1.0;
+                if drag.start.abs() <= f32::EPSILON {
+                    return None
+                }
+            },
+            (GizmoMode::Rotate, _) => {
+                drag.last_angle = plane_angle(ray, position, axis)?;
+            }
+        }
+
+        Some(drag)
+    }
+}
+
+fn drag_translate(drag: &mut Drag, camera: &Camera, ray: &Ray, snap: Option<f32>) -> Option<GizmoDelta> {
+    let translation = match drag.handle {
+        GizmoHandle::View => {
+            let offset = ray.at(intersect_plane(ray, drag.origin, drag.axis)?) - drag.start_point;
+            let offset = match snap {
+                // Snapped along the camera's axes, so the step matches what is seen on screen
+                Some(step) => {
+                    let right = camera.rotation * Vec3::X;
+                    let up = camera.rotation * Vec3::Y;
+                    right * snap_value(offset.dot(right), step) + up * snap_value(offset.dot(up), step)
+                },
+                None => offset
+            };
+            let translation = offset - drag.applied_offset;
+            drag.applied_offset = offset;
+            translation
+        },
+        _ => {
+            let total = closest_on_line(ray, drag.origin, drag.axis)? - drag.start;
+            let total = snap.map_or(total, |step| snap_value(total, step));
+            let translation = drag.axis * (total - drag.applied);
+            drag.applied = total;
+            translation
+        }
+    };
+
+    Some(GizmoDelta {
+        translation,
+        ..GizmoDelta::IDENTITY
+    })
+}
+
+fn drag_rotate(drag: &mut Drag, ray: &Ray, snap: Option<f32>) -> Option<GizmoDelta> {
+    let angle = plane_angle(ray, drag.origin, drag.axis)?;
+
+    // Unwrapped, so turning past half a circle keeps going instead of flipping back
+    let mut step = angle - drag.last_angle;
+    if step > PI {
+        step -= TAU;
+    } else if step < -PI {
+        step += TAU;
+    }
+    drag.last_angle = angle;
+    drag.total += step;
+
+    let total = snap.map_or(drag.total, |step| snap_value(drag.total, step));
+    let rotation = Quat::from_axis_angle(drag.axis, total - drag.applied);
+    drag.applied = total;
+
+    Some(GizmoDelta {
+        rotation,
+        ..GizmoDelta::IDENTITY
+    })
+}
+
+fn drag_scale(drag: &mut Drag, camera: &Camera, ray: &Ray, snap: Option<f32>) -> Option<GizmoDelta> {
+    let factor = match drag.handle {
+        GizmoHandle::View => ray.at(intersect_plane(ray, drag.origin, camera.forward())?).distance(drag.origin) / drag.start,
+        _ => closest_on_line(ray, drag.origin, drag.axis)? / drag.start
+    };
+    let factor = snap.map_or(factor, |step| snap_value(factor, step)).max(f32::EPSILON);
+    let change = factor / drag.applied;
+    drag.applied = factor;
+
+    let scale = match drag.handle.index() {
+        Some(index) => {
+            let mut scale = Vec3::ONE;
+            scale[index] = change;
+            scale
+        },
+        None => Vec3::splat(change)
+    };
+
+    Some(GizmoDelta {
+        scale,
+        ..GizmoDelta::IDENTITY
+    })
+}
+
+/// World units one pixel covers at `point`, so handles keep their size on screen
+fn world_per_pixel(camera: &Camera, point: Vec3, viewport_height: f32) -> f32 {
+    let viewport_height = viewport_height.max(1.0);
+    match camera.projection {
+        Projection::Perspective { fov_y, near, .. } => {
+            let depth = (point - camera.position).dot(camera.forward()).max(near);
+            2.0 * depth * (fov_y * 0.5).tan() / viewport_height
+        },
+        Projection::Orthographic { height, .. } => height / viewport_height
+    }
+}
+
+/// Position along the line through `origin` closest to the ray, `None` if they are parallel
+fn closest_on_line(ray: &Ray, origin: Vec3, axis: Vec3) -> Option<f32> {
+    let b = axis.dot(ray.direction);
+    let denominator = 1.0 - b * b;
+    if denominator.abs() < 1e-6 {
+        return None
+    }
+
+    let offset = origin - ray.origin;
+    let d = axis.dot(offset);
+    let e = ray.direction.dot(offset);
+    Some((b * e - d) / denominator)
+}
+
+/// Distance along the ray to the point nearest `point`, and how far apart they are
+fn distance_to_point(ray: &Ray, point: Vec3) -> (f32, f32) {
+    let along = (point - ray.origin).dot(ray.direction).max(0.0);
+    (along, ray.at(along).distance(point))
+}
+
+fn intersect_plane(ray: &Ray, origin: Vec3, normal: Vec3) -> Option<f32> {
+    let facing = ray.direction.dot(normal);
+    if facing.abs() < 1e-6 {
+        return None
+    }
+
+    let along = (origin - ray.origin).dot(normal) / facing;
+    match along >= 0.0 {
+        true => Some(along),
+        false => None
+    }
+}
+
+/// Angle of the ray's hit on the plane around `origin`, counter clockwise around `axis`
+fn plane_angle(ray: &Ray, origin: Vec3, axis: Vec3) -> Option<f32> {
+    let offset = ray.at(intersect_plane(ray, origin, axis)?) - origin;
+    let (u, v) = axis.any_orthonormal_pair();
+    Some(offset.dot(v).atan2(offset.dot(u)))
+}
+
+fn snap_value(value: f32, step: f32) -> f32 {
+    match step > 0.0 {
+        true => (value / step).round() * step,
+        false => value
+    }
+}
+
+fn push_square(lines: &mut Vec<GizmoLine>, center: Vec3, side: Vec3, up: Vec3, color: Vec4) {
+    let corners = [center - side - up, center + side - up, center + side + up, center - side + up];
+    for index in 0..4 {
+        lines.push(GizmoLine {
+            start: corners[index],
+            end: corners[(index + 1) % 4],
+            color,
+        });
+    }
+}
+
+fn push_ring(lines: &mut Vec<GizmoLine>, center: Vec3, u: Vec3, v: Vec3, color: Vec4) {
+    let point = |index: usize| {
+        let (sin, cos) = (index as f32 / RING_SEGMENTS as f32 * TAU).sin_cos();
+        center + u * cos + v * sin
+    };
+    for index in 0..RING_SEGMENTS {
+        lines.push(GizmoLine {
+            start: point(index),
+            end: point(index + 1),
+            color,
+        });
+    }
+}
\ No newline at end of file
@@ -0,0 +1,310 @@
+use std::{
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf
+    },
+    time::{
+        Duration,
+        Instant,
+        SystemTime
+    }
+};
+
+use libloading::Library;
+use serde::{
+    de::DeserializeOwned,
+    Serialize
+};
+
+use crate::{
+    application::Context,
+    events::EventBus,
+    interface::Client,
+    reflect::TypeRegistry
+};
+
+/// Symbol the game library exports through `hot_logic!`
+const CREATE_SYMBOL: &[u8] = b"aspen_hot_create";
+/// Time the artifact has to stay unchanged before it is loaded, so half written builds are skipped
+const SETTLE_TIME: Duration = Duration::from_millis(250);
+
+/// Game logic living in a cdylib that is reloaded while the game runs.
+/// The state is serialized with RON before a reload and deserialized by the new build,
+/// so `#[serde(default)]` fields carry over when fields are added or removed.
+/// The library has to be built with the same compiler and engine version as the executable.
+///
+/// A reload clears the timers, tweens, events, asset server and type registry, since they hold callbacks, vtables
+/// and `TypeId`s of the old build that the new one cannot use. Register them again in `reloaded`.
+/// Old builds stay loaded until the process exits, so anything else still pointing into them remains valid.
+pub trait HotLogic: Serialize + DeserializeOwned + Default + 'static {
+    fn fixed_update(&mut self, ctx: &mut Context<HotReload>, delta: f64);
+    fn update(&mut self, ctx: &mut Context<HotReload>, delta: f64);
+    fn render(&mut self, _ctx: &mut Context<HotReload>, _alpha: f64) {}
+    fn suspended(&mut self, _ctx: &mut Context<HotReload>) {}
+    fn resumed(&mut self, _ctx: &mut Context<HotReload>) {}
+    fn file_hovered(&mut self, _ctx: &mut Context<HotReload>, _path: PathBuf) {}
+    fn file_hover_cancelled(&mut self, _ctx: &mut Context<HotReload>) {}
+    fn file_dropped(&mut self, _ctx: &mut Context<HotReload>, _path: PathBuf) {}
+
+    /// Called on the new build after its state was restored, the place to schedule timers and tweens
+    /// and register asset loaders and types again
+    fn reloaded(&mut self, _ctx: &mut Context<HotReload>) {}
+}
+
+/// Object safe form of `HotLogic` passed across the library boundary
+#[doc(hidden)]
+pub trait DynLogic {
+    fn fixed_update(&mut self, ctx: &mut Context<HotReload>, delta: f64);
+    fn update(&mut self, ctx: &mut Context<HotReload>, delta: f64);
+    fn render(&mut self, ctx: &mut Context<HotReload>, alpha: f64);
+    fn suspended(&mut self, ctx: &mut Context<HotReload>);
+    fn resumed(&mut self, ctx: &mut Context<HotReload>);
+    fn file_hovered(&mut self, ctx: &mut Context<HotReload>, path: PathBuf);
+    fn file_hover_cancelled(&mut self, ctx: &mut Context<HotReload>);
+    fn file_dropped(&mut self, ctx: &mut Context<HotReload>, path: PathBuf);
+    fn reloaded(&mut self, ctx: &mut Context<HotReload>);
+    fn save(&self) -> Option<String>;
+}
+
+impl<T: HotLogic> DynLogic for T {
+    fn fixed_update(&mut self, ctx: &mut Context<HotReload>, delta: f64) {
+        HotLogic::fixed_update(self, ctx, delta)
+    }
+
+    fn update(&mut self, ctx: &mut Context<HotReload>, delta: f64) {
+        HotLogic::update(self, ctx, delta)
+    }
+
+    fn render(&mut self, ctx: &mut Context<HotReload>, alpha: f64) {
+        HotLogic::render(self, ctx, alpha)
+    }
+
+    fn suspended(&mut self, ctx: &mut Context<HotReload>) {
+        HotLogic::suspended(self, ctx)
+    }
+
+    fn resumed(&mut self, ctx: &mut Context<HotReload>) {
+        HotLogic::resumed(self, ctx)
+    }
+
+    fn file_hovered(&mut self, ctx: &mut Context<HotReload>, path: PathBuf) {
+        HotLogic::file_hovered(self, ctx, path)
+    }
+
+    fn file_hover_cancelled(&mut self, ctx: &mut Context<HotReload>) {
+        HotLogic::file_hover_cancelled(self, ctx)
+    }
+
+    fn file_dropped(&mut self, ctx: &mut Context<HotReload>, path: PathBuf) {
+        HotLogic::file_dropped(self, ctx, path)
+    }
+
+    fn reloaded(&mut self, ctx: &mut Context<HotReload>) {
+        HotLogic::reloaded(self, ctx)
+    }
+
+    fn save(&self) -> Option<String> {
+        ron::to_string(self).ok()
+    }
+}
+
+/// Builds the logic for a freshly loaded library, falling back to the default state if the saved one no longer fits
+#[doc(hidden)]
+pub fn create<T: HotLogic>(state: Option<&str>) -> Box<dyn DynLogic> {
+    let logic: T = state
+        .and_then(|state| ron::from_str(state).ok())
+        .unwrap_or_default();
+    Box::new(logic)
+}
+
+/// Exports a `HotLogic` type from the game's cdylib so `HotReload` can load it
+#[macro_export]
+macro_rules! hot_logic {
+    ($logic:ty) => {
+        #[no_mangle]
+        pub fn aspen_hot_create(state: Option<&str>) -> Box<dyn $crate::hot_reload::DynLogic> {
+            $crate::hot_reload::create::<$logic>(state)
+        }
+    };
+}
+
+type CreateFn = fn(Option<&str>) -> Box<dyn DynLogic>;
+
+/// Sent to the event bus whenever the watched library was loaded again
+#[derive(Debug, Clone, PartialEq)]
+pub enum HotReloadEvent {
+    Reloaded { generation: u32 },
+    /// The previous build keeps running
+    Failed(String),
+}
+
+/// Client that forwards to game logic loaded from a dynamic library and reloads it when the library is rebuilt
+pub struct HotReload {
+    path: PathBuf,
+    logic: Box<dyn DynLogic>,
+    /// Every build loaded so far, never unloaded since engine state may still point into them
+    libraries: Vec<Library>,
+    loaded_path: PathBuf,
+    modified: Option<SystemTime>,
+    changed: Option<(SystemTime, Instant)>,
+    generation: u32,
+    needs_reload_hook: bool,
+}
+
+impl HotReload {
+    /// Loads the game library at `path`, usually `target/debug/lib<game>.so` or the platform's equivalent
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let modified = modified(&path);
+        let (library, loaded_path, create) = load(&path, 0)?;
+
+        Ok(Self {
+            path,
+            logic: create(None),
+            libraries: vec![library],
+            loaded_path,
+            modified,
+            changed: None,
+            generation: 0,
+            needs_reload_hook: false,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of reloads so far
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Loads the library again right away, carrying the state over.
+    /// Clears the state the old build registered with the context, see `HotLogic`.
+    pub fn reload(&mut self, ctx: &mut Context<HotReload>) -> io::Result<()> {
+        let generation = self.generation + 1;
+        let (library, loaded_path, create) = load(&self.path, generation)?;
+
+        ctx.timers().clear();
+        ctx.tweens().clear();
+        ctx.events = EventBus::new();
+        ctx.assets = None;
+        ctx.types = TypeRegistry::new();
+
+        let state = self.logic.save();
+        self.logic = create(state.as_deref());
+        self.libraries.push(library);
+        let _ = fs::remove_file(std::mem::replace(&mut self.loaded_path, loaded_path));
+
+        self.generation = generation;
+        self.needs_reload_hook = true;
+        Ok(())
+    }
+
+    /// Reloads once the library changed on disk and settled
+    fn poll(&mut self, ctx: &mut Context<HotReload>) {
+        let current = modified(&self.path);
+        if current == self.modified {
+            self.changed = None;
+            return
+        }
+        let Some(current) = current else {
+            return
+        };
+
+        match self.changed {
+            Some((time, since)) if time == current => {
+                if since.elapsed() < SETTLE_TIME {
+                    return
+                }
+            },
+            _ => {
+                self.changed = Some((current, Instant::now()));
+                return
+            }
+        }
+
+        self.changed = None;
+        self.modified = Some(current);
+        match self.reload(ctx) {
+            Ok(()) => ctx.events_mut().send(HotReloadEvent::Reloaded { generation: self.generation }),
+            Err(error) => ctx.events_mut().send(HotReloadEvent::Failed(error.to_string()))
+        }
+    }
+}
+
+impl Drop for HotReload {
+    fn drop(&mut self) {
+        // The context outlives the client and may still hold code of the libraries
+        std::mem::forget(std::mem::take(&mut self.libraries));
+        let _ = fs::remove_file(&self.loaded_path);
+    }
+}
+
+impl Client for HotReload {
+    fn fixed_update(&mut self, ctx: &mut Context<Self>, delta: f64) {
+        self.logic.fixed_update(ctx, delta)
+    }
+
+    fn update(&mut self, ctx: &mut Context<Self>, delta: f64) {
+        self.poll(ctx);
+        if self.needs_reload_hook {
+            self.needs_reload_hook = false;
+            self.logic.reloaded(ctx);
+        }
+        self.logic.update(ctx, delta)
+    }
+
+    fn render(&mut self, ctx: &mut Context<Self>, alpha: f64) {
+        self.logic.render(ctx, alpha)
+    }
+
+    fn suspended(&mut self, ctx: &mut Context<Self>) {
+        self.logic.suspended(ctx)
+    }
+
+    fn resumed(&mut self, ctx: &mut Context<Self>) {
+        self.logic.resumed(ctx)
+    }
+
+    fn file_hovered(&mut self, ctx: &mut Context<Self>, path: PathBuf) {
+        self.logic.file_hovered(ctx, path)
+    }
+
+    fn file_hover_cancelled(&mut self, ctx: &mut Context<Self>) {
+        self.logic.file_hover_cancelled(ctx)
+    }
+
+    fn file_dropped(&mut self, ctx: &mut Context<Self>, path: PathBuf) {
+        self.logic.file_dropped(ctx, path)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Loads a copy of the library so the build can overwrite the original and the loader does not hand back the old one
+fn load(path: &Path, generation: u32) -> io::Result<(Library, PathBuf, CreateFn)> {
+    let file_name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "library path has no file name"))?
+        .to_string_lossy();
+    let copy = std::env::temp_dir().join(format!("{}-{}-{}", std::process::id(), generation, file_name));
+    fs::copy(path, &copy)?;
+
+    // SAFETY: the library is expected to be a game crate exporting `hot_logic!`, built against this engine
+    let loaded = unsafe { Library::new(&copy) }.and_then(|library| {
+        let create = unsafe { library.get::<CreateFn>(CREATE_SYMBOL) }.map(|symbol| *symbol)?;
+        Ok((library, create))
+    });
+
+    match loaded {
+        Ok((library, create)) => Ok((library, copy, create)),
+        Err(error) => {
+            let _ = fs::remove_file(&copy);
+            Err(io::Error::other(error))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,557 @@
+use std::collections::BTreeMap;
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::math::Vec2;
+
+use super::{
+    Input,
+    InputEvent,
+    KeyCode,
+    MouseButton,
+    TimedInput
+};
+
+/// A physical input an action can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// Buttons combined into a joystick like axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AxisBinding {
+    /// Drives x, -1 while `negative` is held and 1 while `positive` is held
+    Pair { negative: Binding, positive: Binding },
+    /// WASD style, with y pointing up
+    Quad { up: Binding, down: Binding, left: Binding, right: Binding },
+}
+
+/// Shapes the length of an axis value, applied after ramping
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ResponseCurve {
+    Linear,
+    /// Eases in and out
+    Smooth,
+    /// Values above 1 give finer control near the center
+    Power(f32),
+}
+
+/// A named axis read as one value regardless of which of its bindings drive it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Axis {
+    pub bindings: Vec<AxisBinding>,
+    pub curve: ResponseCurve,
+    /// Units per second the value moves toward the held direction so keys feel analog, 0 snaps instantly
+    pub ramp: f32,
+    /// Limits the length to 1 so diagonals are not faster than straight movement
+    pub normalize: bool,
+    #[serde(skip)]
+    value: Vec2,
+    /// Same as `value`, but driven by the fixed step input
+    #[serde(skip)]
+    fixed_value: Vec2,
+}
+
+/// A binding used by more than one action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub binding: Binding,
+    pub actions: Vec<String>,
+}
+
+/// Result of a finished capture, the binding has already been applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captured {
+    pub action: String,
+    pub binding: Binding,
+    /// Other actions that use the same binding
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Capture {
+    action: String,
+    slot: usize,
+}
+
+/// Named actions and the inputs bound to them, stored in the config's `input` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionMap {
+    bindings: BTreeMap<String, Vec<Binding>>,
+    axes: BTreeMap<String, Axis>,
+    #[serde(skip)]
+    capture: Option<Capture>,
+    #[serde(skip)]
+    captured: Option<Captured>,
+}
+
+impl Binding {
+    pub fn down(&self, input: &Input) -> bool {
+        match *self {
+            Binding::Key(key) => input.key_down(key),
+            Binding::Mouse(button) => input.mouse_down(button)
+        }
+    }
+
+    pub fn pressed(&self, input: &Input) -> bool {
+        match *self {
+            Binding::Key(key) => input.key_pressed(key),
+            Binding::Mouse(button) => input.mouse_pressed(button)
+        }
+    }
+
+    pub fn released(&self, input: &Input) -> bool {
+        match *self {
+            Binding::Key(key) => input.key_released(key),
+            Binding::Mouse(button) => input.mouse_released(button)
+        }
+    }
+}
+
+impl AxisBinding {
+    /// Direction currently held, each component in -1..1
+    pub fn value(&self, input: &Input) -> Vec2 {
+        let pair = |negative: &Binding, positive: &Binding| positive.down(input) as i32 as f32 - negative.down(input) as i32 as f32;
+        match self {
+            AxisBinding::Pair { negative, positive } => Vec2::new(pair(negative, positive), 0.0),
+            AxisBinding::Quad { up, down, left, right } => Vec2::new(pair(left, right), pair(down, up))
+        }
+    }
+
+    /// The buttons driving the axis
+    pub fn buttons(&self) -> Vec<Binding> {
+        match *self {
+            AxisBinding::Pair { negative, positive } => vec![negative, positive],
+            AxisBinding::Quad { up, down, left, right } => vec![up, down, left, right]
+        }
+    }
+}
+
+impl ResponseCurve {
+    pub fn apply(&self, value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+        match *self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Smooth => value * value * (3.0 - 2.0 * value),
+            ResponseCurve::Power(exponent) => value.powf(exponent.max(f32::EPSILON))
+        }
+    }
+}
+
+impl Axis {
+    pub fn new(bindings: Vec<AxisBinding>) -> Self {
+        Self {
+            bindings,
+            ..Self::default()
+        }
+    }
+
+    /// Current value with the response curve applied
+    pub fn value(&self) -> Vec2 {
+        self.shaped(self.value)
+    }
+
+    /// Value as of the current fixed step, for `fixed_update`
+    pub fn fixed_value(&self) -> Vec2 {
+        self.shaped(self.fixed_value)
+    }
+
+    fn shaped(&self, value: Vec2) -> Vec2 {
+        let length = value.length();
+        match length > 0.0 {
+            true => value * (self.curve.apply(length) / length),
+            false => Vec2::ZERO
+        }
+    }
+
+    fn update(&mut self, input: &Input, delta: f64) {
+        self.value = self.ramped(self.value, input, delta);
+    }
+
+    fn update_fixed(&mut self, input: &Input, delta: f64) {
+        self.fixed_value = self.ramped(self.fixed_value, input, delta);
+    }
+
+    fn ramped(&self, value: Vec2, input: &Input, delta: f64) -> Vec2 {
+        let mut target = self.bindings.iter()
+            .map(|binding| binding.value(input))
+            .sum::<Vec2>()
+            .clamp(Vec2::NEG_ONE, Vec2::ONE);
+        if self.normalize {
+            target = target.clamp_length_max(1.0);
+        }
+
+        match self.ramp > 0.0 {
+            true => {
+                let step = self.ramp * delta as f32;
+                let offset = target - value;
+                value + offset.clamp_length_max(step)
+            },
+            false => target
+        }
+    }
+}
+
+impl Default for Axis {
+    fn default() -> Self {
+        Self {
+            bindings: Vec::new(),
+            curve: ResponseCurve::Linear,
+            ramp: 0.0,
+            normalize: true,
+            value: Vec2::ZERO,
+            fixed_value: Vec2::ZERO,
+        }
+    }
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding to the action, creating the action if needed
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.bindings.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(action) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+
+    pub fn set_bindings(&mut self, action: &str, bindings: Vec<Binding>) {
+        self.bindings.insert(action.to_string(), bindings);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.bindings.get(action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+
+    /// Keeps the actions, only removing what is bound to them
+    pub fn clear_bindings(&mut self) {
+        self.bindings.values_mut().for_each(Vec::clear);
+        self.axes.values_mut().for_each(|axis| axis.bindings.clear());
+    }
+
+    /// Any binding of the action is held
+    pub fn down(&self, input: &Input, action: &str) -> bool {
+        self.bindings(action).iter().any(|binding| binding.down(input))
+    }
+
+    pub fn pressed(&self, input: &Input, action: &str) -> bool {
+        self.bindings(action).iter().any(|binding| binding.pressed(input))
+    }
+
+    pub fn released(&self, input: &Input, action: &str) -> bool {
+        self.bindings(action).iter().any(|binding| binding.released(input))
+    }
+
+    /// Adds a binding to the axis, creating the axis with default settings if needed
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        let axis = self.axes.entry(axis.to_string()).or_default();
+        if !axis.bindings.contains(&binding) {
+            axis.bindings.push(binding);
+        }
+    }
+
+    pub fn set_axis(&mut self, name: &str, axis: Axis) {
+        self.axes.insert(name.to_string(), axis);
+    }
+
+    /// The axis' bindings and settings
+    pub fn axis_settings(&self, name: &str) -> Option<&Axis> {
+        self.axes.get(name)
+    }
+
+    pub fn axis_settings_mut(&mut self, name: &str) -> Option<&mut Axis> {
+        self.axes.get_mut(name)
+    }
+
+    pub fn axes(&self) -> impl Iterator<Item = &str> {
+        self.axes.keys().map(String::as_str)
+    }
+
+    /// Value of a one dimensional axis in -1..1, 0 if it does not exist
+    pub fn axis(&self, name: &str) -> f32 {
+        self.axis_2d(name).x
+    }
+
+    /// Value of a two dimensional axis with y pointing up, zero if it does not exist
+    pub fn axis_2d(&self, name: &str) -> Vec2 {
+        self.axes.get(name).map_or(Vec2::ZERO, Axis::value)
+    }
+
+    /// `axis` as of the current fixed step, read this in `fixed_update`
+    pub fn fixed_axis(&self, name: &str) -> f32 {
+        self.fixed_axis_2d(name).x
+    }
+
+    pub fn fixed_axis_2d(&self, name: &str) -> Vec2 {
+        self.axes.get(name).map_or(Vec2::ZERO, Axis::fixed_value)
+    }
+
+    /// Run by the application after each frame's input is applied
+    pub(crate) fn update_axes(&mut self, input: &Input, delta: f64) {
+        for axis in self.axes.values_mut() {
+            axis.update(input, delta);
+        }
+    }
+
+    /// Run by the application after each fixed step's input is latched
+    pub(crate) fn update_fixed_axes(&mut self, fixed_input: &Input, fixed_delta: f64) {
+        for axis in self.axes.values_mut() {
+            axis.update_fixed(fixed_input, fixed_delta);
+        }
+    }
+
+    /// Actions and axes other than `action` that use the binding
+    pub fn conflicts_with(&self, action: &str, binding: Binding) -> Vec<String> {
+        self.users()
+            .filter(|(name, bindings)| *name != action && bindings.contains(&binding))
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    /// Every binding shared by several actions or axes
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut users: Vec<(Binding, Vec<String>)> = Vec::new();
+        for (action, bindings) in self.users() {
+            for binding in bindings.iter() {
+                match users.iter_mut().find(|(bound, _)| bound == binding) {
+                    // An axis may use a button twice, that is not a conflict
+                    Some((_, actions)) if actions.last().is_some_and(|last| last == action) => (),
+                    Some((_, actions)) => actions.push(action.to_string()),
+                    None => users.push((*binding, vec![action.to_string()]))
+                }
+            }
+        }
+
+        users.into_iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .map(|(binding, actions)| Conflict { binding, actions })
+            .collect()
+    }
+
+    /// Every action and axis with the buttons it uses
+    fn users(&self) -> impl Iterator<Item = (&str, Vec<Binding>)> {
+        let actions = self.bindings.iter().map(|(name, bindings)| (name.as_str(), bindings.clone()));
+        let axes = self.axes.iter().map(|(name, axis)| {
+            (name.as_str(), axis.bindings.iter().flat_map(AxisBinding::buttons).collect())
+        });
+        actions.chain(axes)
+    }
+
+    /// Binds the next key or mouse button pressed to the action, replacing the binding at `slot`
+    /// or adding one if the action has fewer bindings. Finished captures are read with `take_captured`.
+    /// Pressing Escape cancels the capture without binding anything.
+    pub fn start_capture(&mut self, action: &str, slot: usize) {
+        self.capture = Some(Capture {
+            action: action.to_string(),
+            slot,
+        });
+        self.captured = None;
+    }
+
+    pub fn cancel_capture(&mut self) {
+        self.capture = None;
+    }
+
+    /// The action waiting for input, if any
+    pub fn capturing(&self) -> Option<&str> {
+        self.capture.as_ref().map(|capture| capture.action.as_str())
+    }
+
+    pub fn take_captured(&mut self) -> Option<Captured> {
+        self.captured.take()
+    }
+
+    /// Run by the application after each frame's input is applied
+    /// The first new press of the frame's events is taken, so Shift+W binds whichever went down first
+    pub(crate) fn update_capture(&mut self, input: &Input, events: &[TimedInput]) {
+        let Some(capture) = &self.capture else {
+            return
+        };
+
+        if input.key_pressed(KeyCode::Escape) {
+            self.capture = None;
+            return
+        }
+
+        // Key repeats arrive as presses too, only the ones that were not held before count
+        let first = events.iter().find_map(|timed| match timed.event {
+            InputEvent::Key { key, pressed: true } if input.key_pressed(key) => Some(Binding::Key(key)),
+            InputEvent::MouseButton { button, pressed: true } if input.mouse_pressed(button) => Some(Binding::Mouse(button)),
+            _ => None
+        });
+        let Some(binding) = first else {
+            return
+        };
+
+        let Capture { action, slot } = capture.clone();
+        self.capture = None;
+
+        // The slot is resolved before duplicates are removed, removing first would shift it
+        let bindings = self.bindings.entry(action.clone()).or_default();
+        let slot = match bindings.get_mut(slot) {
+            Some(bound) => {
+                *bound = binding;
+                slot
+            },
+            None => match bindings.iter().position(|bound| *bound == binding) {
+                Some(existing) => existing,
+                None => {
+                    bindings.push(binding);
+                    bindings.len() - 1
+                }
+            }
+        };
+
+        let mut index = 0;
+        bindings.retain(|bound| {
+            let keep = index == slot || *bound != binding;
+            index += 1;
+            keep
+        });
+
+        self.captured = Some(Captured {
+            conflicts: self.conflicts_with(&action, binding),
+            action,
+            binding,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the capture on a frame with these events
+    fn capture(map: &mut ActionMap, events: &[InputEvent]) {
+        let mut input = Input::new();
+        let events: Vec<TimedInput> = events.iter()
+            .map(|event| {
+                input.apply(event);
+                TimedInput {
+                    time: 0.0,
+                    event: event.clone(),
+                }
+            })
+            .collect();
+        map.update_capture(&input, &events);
+    }
+
+    fn key(key: KeyCode) -> InputEvent {
+        InputEvent::Key { key, pressed: true }
+    }
+
+    #[test]
+    fn capture_replaces_the_slot() {
+        let mut map = ActionMap::new();
+        map.set_bindings("jump", vec![Binding::Key(KeyCode::Space), Binding::Key(KeyCode::KeyW)]);
+
+        map.start_capture("jump", 1);
+        capture(&mut map, &[]);
+        assert_eq!(map.capturing(), Some("jump"));
+
+        capture(&mut map, &[key(KeyCode::ArrowUp)]);
+        assert_eq!(map.bindings("jump"), &[Binding::Key(KeyCode::Space), Binding::Key(KeyCode::ArrowUp)]);
+        assert_eq!(map.capturing(), None);
+        assert_eq!(map.take_captured().map(|captured| captured.binding), Some(Binding::Key(KeyCode::ArrowUp)));
+    }
+
+    #[test]
+    fn capture_removes_the_duplicate_from_other_slots() {
+        let mut map = ActionMap::new();
+        map.set_bindings("fire", vec![Binding::Key(KeyCode::KeyF), Binding::Key(KeyCode::KeyG), Binding::Key(KeyCode::KeyH)]);
+
+        map.start_capture("fire", 2);
+        capture(&mut map, &[key(KeyCode::KeyF)]);
+        assert_eq!(map.bindings("fire"), &[Binding::Key(KeyCode::KeyG), Binding::Key(KeyCode::KeyF)]);
+
+        map.start_capture("fire", 5);
+        capture(&mut map, &[key(KeyCode::KeyG)]);
+        assert_eq!(map.bindings("fire"), &[Binding::Key(KeyCode::KeyG), Binding::Key(KeyCode::KeyF)]);
+
+        map.start_capture("fire", 5);
+        capture(&mut map, &[InputEvent::MouseButton { button: MouseButton::Left, pressed: true }]);
+        assert_eq!(map.bindings("fire")[2], Binding::Mouse(MouseButton::Left));
+    }
+
+    #[test]
+    fn capture_takes_the_first_press_of_the_frame() {
+        for (first, second) in [(KeyCode::ShiftLeft, KeyCode::KeyW), (KeyCode::KeyW, KeyCode::ShiftLeft)] {
+            let mut map = ActionMap::new();
+            map.start_capture("sprint", 0);
+            capture(&mut map, &[key(first), key(second)]);
+            assert_eq!(map.bindings("sprint"), &[Binding::Key(first)]);
+        }
+
+        let mut map = ActionMap::new();
+        map.start_capture("fire", 0);
+        capture(&mut map, &[InputEvent::MouseButton { button: MouseButton::Right, pressed: true }, key(KeyCode::KeyF)]);
+        assert_eq!(map.bindings("fire"), &[Binding::Mouse(MouseButton::Right)]);
+    }
+
+    #[test]
+    fn escape_cancels_the_capture() {
+        let mut map = ActionMap::new();
+        map.bind("pause", Binding::Key(KeyCode::KeyP));
+
+        map.start_capture("pause", 0);
+        capture(&mut map, &[key(KeyCode::Escape)]);
+        assert_eq!(map.capturing(), None);
+        assert_eq!(map.take_captured(), None);
+        assert_eq!(map.bindings("pause"), &[Binding::Key(KeyCode::KeyP)]);
+    }
+
+    #[test]
+    fn capture_reports_conflicts() {
+        let mut map = ActionMap::new();
+        map.bind("left", Binding::Key(KeyCode::KeyA));
+
+        map.start_capture("dash", 0);
+        capture(&mut map, &[key(KeyCode::KeyA)]);
+        assert_eq!(map.take_captured().unwrap().conflicts, vec!["left".to_string()]);
+        assert_eq!(map.conflicts().len(), 1);
+    }
+
+    #[test]
+    fn axis_buttons_count_as_conflicts() {
+        let mut map = ActionMap::new();
+        map.bind_axis("move", AxisBinding::Quad {
+            up: Binding::Key(KeyCode::KeyW),
+            down: Binding::Key(KeyCode::KeyS),
+            left: Binding::Key(KeyCode::KeyA),
+            right: Binding::Key(KeyCode::KeyD),
+        });
+        map.bind_axis("move", AxisBinding::Pair {
+            negative: Binding::Key(KeyCode::KeyA),
+            positive: Binding::Key(KeyCode::KeyD),
+        });
+        assert!(map.conflicts().is_empty());
+
+        map.start_capture("jump", 0);
+        capture(&mut map, &[key(KeyCode::KeyW)]);
+        assert_eq!(map.take_captured().unwrap().conflicts, vec!["move".to_string()]);
+        assert_eq!(map.conflicts(), vec![Conflict {
+            binding: Binding::Key(KeyCode::KeyW),
+            actions: vec!["jump".to_string(), "move".to_string()],
+        }]);
+    }
+}
\ No newline at end of file
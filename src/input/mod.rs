@@ -0,0 +1,300 @@
+pub mod actions;
+pub mod touch;
+
+use std::collections::HashSet;
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+use winit::{
+    event::{
+        ElementState,
+        Ime,
+        KeyEvent,
+        MouseScrollDelta,
+        WindowEvent
+    },
+    keyboard::PhysicalKey
+};
+
+use crate::math::Vec2;
+
+use touch::TouchPhase;
+
+pub use touch::Touch;
+pub use winit::{
+    event::MouseButton,
+    keyboard::KeyCode
+};
+
+/// Pixel scroll deltas are converted to lines using this many pixels per line
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Input the engine tracks, in a form that can be recorded and replayed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key { key: KeyCode, pressed: bool },
+    MouseButton { button: MouseButton, pressed: bool },
+    /// Cursor position in physical pixels from the top left of the window
+    CursorMoved { position: Vec2 },
+    /// Scroll amount in lines
+    MouseWheel { delta: Vec2 },
+    /// Text typed on the keyboard or committed by the IME, without control characters
+    Text { text: String },
+    /// Text being composed in the IME, empty once composition ends.
+    /// The cursor is a byte range into the text, `None` hides it.
+    ImePreedit { text: String, cursor: Option<(usize, usize)> },
+    ImeEnabled { enabled: bool },
+    /// A finger touching, moving on or leaving the screen, position in physical pixels
+    Touch { id: u64, phase: TouchPhase, position: Vec2 },
+    /// Unaccelerated mouse movement straight from the device, only sent in raw mouse mode
+    RawMouseMotion { delta: Vec2 },
+    /// The window lost focus, everything held is released
+    FocusLost,
+}
+
+/// An input event and when it arrived
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimedInput {
+    /// Real seconds since the application started, on the clock of `TimingStruct::now`
+    pub time: f64,
+    pub event: InputEvent,
+}
+
+impl InputEvent {
+    /// Converts a window event, pushing nothing for events the engine does not track.
+    /// A key press can produce both a key and a text event.
+    pub(crate) fn push_window_event(event: &WindowEvent, time: f64, events: &mut Vec<TimedInput>) {
+        let mut push = |event: InputEvent| events.push(TimedInput { time, event });
+        match event {
+            WindowEvent::KeyboardInput { event: KeyEvent { physical_key, state, repeat, text, .. }, .. } => {
+                if let (PhysicalKey::Code(key), false) = (physical_key, repeat) {
+                    push(InputEvent::Key {
+                        key: *key,
+                        pressed: *state == ElementState::Pressed,
+                    });
+                }
+
+                // Repeats still type text, but the key itself stays held
+                if let (Some(text), ElementState::Pressed) = (text, state) {
+                    let text: String = text.chars().filter(|c| !c.is_control()).collect();
+                    if !text.is_empty() {
+                        push(InputEvent::Text { text });
+                    }
+                }
+            },
+            WindowEvent::MouseInput { state, button, .. } => {
+                push(InputEvent::MouseButton {
+                    button: *button,
+                    pressed: *state == ElementState::Pressed,
+                });
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                push(InputEvent::CursorMoved {
+                    position: Vec2::new(position.x as f32, position.y as f32),
+                });
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x, *y),
+                    MouseScrollDelta::PixelDelta(position) => Vec2::new(position.x as f32, position.y as f32) / PIXELS_PER_LINE
+                };
+
+                push(InputEvent::MouseWheel {
+                    delta,
+                });
+            },
+            WindowEvent::Ime(ime) => {
+                push(match ime {
+                    Ime::Enabled => InputEvent::ImeEnabled { enabled: true },
+                    Ime::Disabled => InputEvent::ImeEnabled { enabled: false },
+                    Ime::Preedit(text, cursor) => InputEvent::ImePreedit { text: text.clone(), cursor: *cursor },
+                    Ime::Commit(text) => InputEvent::Text { text: text.clone() }
+                });
+            },
+            WindowEvent::Touch(touch) => {
+                push(InputEvent::Touch {
+                    id: touch.id,
+                    phase: touch.phase,
+                    position: Vec2::new(touch.location.x as f32, touch.location.y as f32),
+                });
+            },
+            WindowEvent::Focused(false) => push(InputEvent::FocusLost),
+            _ => ()
+        }
+    }
+}
+
+/// Keyboard and mouse state for the current frame
+#[derive(Debug, Clone, Default)]
+pub struct Input {
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    cursor_position: Vec2,
+    cursor_delta: Vec2,
+    scroll_delta: Vec2,
+    raw_mouse_delta: Vec2,
+    text: String,
+    ime_enabled: bool,
+    preedit: String,
+    preedit_cursor: Option<(usize, usize)>,
+    touch: Touch,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Pressed since the previous frame
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// Released since the previous frame
+    pub fn key_released(&self, key: KeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn mouse_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn mouse_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Keys pressed since the previous frame, in no particular order
+    pub fn pressed_keys(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.keys_pressed.iter().copied()
+    }
+
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = MouseButton> + '_ {
+        self.buttons_pressed.iter().copied()
+    }
+
+    pub fn cursor_position(&self) -> Vec2 {
+        self.cursor_position
+    }
+
+    /// Cursor movement since the previous frame
+    pub fn cursor_delta(&self) -> Vec2 {
+        self.cursor_delta
+    }
+
+    pub fn scroll_delta(&self) -> Vec2 {
+        self.scroll_delta
+    }
+
+    /// Device mouse movement since the previous frame, not limited by the screen edges.
+    /// Stays zero unless raw mouse mode is enabled on the context.
+    pub fn raw_mouse_delta(&self) -> Vec2 {
+        self.raw_mouse_delta
+    }
+
+    /// Text entered this frame, for text fields. Editing keys such as backspace are read as keys.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn ime_enabled(&self) -> bool {
+        self.ime_enabled
+    }
+
+    /// Text being composed in the IME, shown inline at the text cursor until it is committed
+    pub fn ime_preedit(&self) -> &str {
+        &self.preedit
+    }
+
+    /// Byte range of the cursor inside the preedit text, `None` if it should be hidden
+    pub fn ime_preedit_cursor(&self) -> Option<(usize, usize)> {
+        self.preedit_cursor
+    }
+
+    pub fn touch(&self) -> &Touch {
+        &self.touch
+    }
+
+    /// Clears the per frame state before this frame's events are applied, `delta` is the frame's unscaled delta
+    pub(crate) fn begin_frame(&mut self, delta: f64) {
+        self.touch.begin_frame(delta);
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.cursor_delta = Vec2::ZERO;
+        self.scroll_delta = Vec2::ZERO;
+        self.raw_mouse_delta = Vec2::ZERO;
+        self.text.clear();
+    }
+
+    pub(crate) fn apply(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Text { ref text } => {
+                self.text.push_str(text);
+            },
+            InputEvent::ImePreedit { ref text, cursor } => {
+                self.preedit.clone_from(text);
+                self.preedit_cursor = cursor;
+            },
+            InputEvent::Touch { id, phase, position } => {
+                self.touch.apply(id, phase, position);
+            },
+            InputEvent::ImeEnabled { enabled } => {
+                self.ime_enabled = enabled;
+                self.preedit.clear();
+                self.preedit_cursor = None;
+            },
+            InputEvent::Key { key, pressed: true } => {
+                if self.keys_down.insert(key) {
+                    self.keys_pressed.insert(key);
+                }
+            },
+            InputEvent::Key { key, pressed: false } => {
+                if self.keys_down.remove(&key) {
+                    self.keys_released.insert(key);
+                }
+            },
+            InputEvent::MouseButton { button, pressed: true } => {
+                if self.buttons_down.insert(button) {
+                    self.buttons_pressed.insert(button);
+                }
+            },
+            InputEvent::MouseButton { button, pressed: false } => {
+                if self.buttons_down.remove(&button) {
+                    self.buttons_released.insert(button);
+                }
+            },
+            InputEvent::CursorMoved { position } => {
+                self.cursor_delta += position - self.cursor_position;
+                self.cursor_position = position;
+            },
+            InputEvent::MouseWheel { delta } => {
+                self.scroll_delta += delta;
+            },
+            InputEvent::RawMouseMotion { delta } => {
+                self.raw_mouse_delta += delta;
+            },
+            InputEvent::FocusLost => {
+                self.keys_released.extend(self.keys_down.drain());
+                self.buttons_released.extend(self.buttons_down.drain());
+                self.preedit.clear();
+                self.preedit_cursor = None;
+                self.touch.cancel_all();
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,159 @@
+use crate::math::Vec2;
+
+pub use winit::event::TouchPhase;
+
+/// Longest press in seconds that still counts as a tap
+const TAP_MAX_TIME: f64 = 0.3;
+/// Movement in physical pixels after which a touch counts as a drag instead of a tap
+const DRAG_THRESHOLD: f32 = 10.0;
+
+/// A finger on the screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchPoint {
+    pub id: u64,
+    /// `Ended` and `Cancelled` touches are kept for the frame they were lifted in
+    pub phase: TouchPhase,
+    /// Physical pixels from the top left of the window
+    pub position: Vec2,
+    pub previous_position: Vec2,
+    pub start_position: Vec2,
+    /// Seconds since the touch started
+    pub held_time: f64,
+    /// Moved further than the drag threshold at some point
+    pub dragging: bool,
+}
+
+/// Two finger gesture state for the current frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pinch {
+    pub center: Vec2,
+    pub center_delta: Vec2,
+    pub distance: f32,
+    /// Ratio of the finger distance to the previous frame's, above 1 when spreading
+    pub scale: f32,
+}
+
+/// Multi-touch tracking and simple gestures
+#[derive(Debug, Clone, Default)]
+pub struct Touch {
+    points: Vec<TouchPoint>,
+    taps: Vec<Vec2>,
+}
+
+impl TouchPoint {
+    pub fn delta(&self) -> Vec2 {
+        self.position - self.previous_position
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self.phase, TouchPhase::Started | TouchPhase::Moved)
+    }
+}
+
+impl Touch {
+    pub fn touches(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.points.iter()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&TouchPoint> {
+        self.points.iter().find(|point| point.id == id)
+    }
+
+    /// Number of fingers currently on the screen
+    pub fn count(&self) -> usize {
+        self.active().count()
+    }
+
+    /// Positions of taps finished this frame
+    pub fn taps(&self) -> &[Vec2] {
+        &self.taps
+    }
+
+    /// Movement of a single dragging finger this frame
+    pub fn drag_delta(&self) -> Option<Vec2> {
+        let mut active = self.active();
+        match (active.next(), active.next()) {
+            (Some(point), None) if point.dragging => Some(point.delta()),
+            _ => None
+        }
+    }
+
+    /// Set while exactly two fingers are on the screen
+    pub fn pinch(&self) -> Option<Pinch> {
+        let mut active = self.active();
+        let (a, b) = match (active.next(), active.next(), active.next()) {
+            (Some(a), Some(b), None) => (a, b),
+            _ => return None
+        };
+
+        let center = (a.position + b.position) * 0.5;
+        let previous_center = (a.previous_position + b.previous_position) * 0.5;
+        let distance = a.position.distance(b.position);
+        let previous_distance = a.previous_position.distance(b.previous_position);
+
+        Some(Pinch {
+            center,
+            center_delta: center - previous_center,
+            distance,
+            scale: match previous_distance > f32::EPSILON {
+                true => distance / previous_distance,
+                false => 1.0
+            },
+        })
+    }
+
+    fn active(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.points.iter().filter(|point| point.is_active())
+    }
+
+    pub(crate) fn begin_frame(&mut self, delta: f64) {
+        self.points.retain(|point| point.is_active());
+        self.taps.clear();
+
+        for point in self.points.iter_mut() {
+            point.previous_position = point.position;
+            point.held_time += delta;
+            if point.phase == TouchPhase::Started {
+                point.phase = TouchPhase::Moved;
+            }
+        }
+    }
+
+    pub(crate) fn apply(&mut self, id: u64, phase: TouchPhase, position: Vec2) {
+        let index = self.points.iter().position(|point| point.id == id && point.is_active());
+
+        let point = match (index, phase) {
+            (None, TouchPhase::Started) => {
+                self.points.push(TouchPoint {
+                    id,
+                    phase,
+                    position,
+                    previous_position: position,
+                    start_position: position,
+                    held_time: 0.0,
+                    dragging: false,
+                });
+                return
+            },
+            (Some(index), _) => &mut self.points[index],
+            (None, _) => return
+        };
+
+        point.position = position;
+        point.dragging |= position.distance(point.start_position) > DRAG_THRESHOLD;
+        point.phase = match phase {
+            TouchPhase::Started => TouchPhase::Moved,
+            phase => phase
+        };
+
+        if phase == TouchPhase::Ended && !point.dragging && point.held_time <= TAP_MAX_TIME {
+            self.taps.push(position);
+        }
+    }
+
+    pub(crate) fn cancel_all(&mut self) {
+        for point in self.points.iter_mut().filter(|point| point.is_active()) {
+            point.phase = TouchPhase::Cancelled;
+        }
+    }
+}
\ No newline at end of file
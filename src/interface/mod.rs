@@ -1,4 +1,26 @@
-pub trait Client {
-    fn fixed_update(&mut self, delta: f64);
-    fn update(&mut self, delta: f64);
+use std::path::PathBuf;
+
+use crate::application::Context;
+
+pub trait Client: Sized {
+    fn fixed_update(&mut self, ctx: &mut Context<Self>, delta: f64);
+    fn update(&mut self, ctx: &mut Context<Self>, delta: f64);
+
+    /// Called once per frame after update, `alpha` is the blend factor between the last two fixed steps
+    fn render(&mut self, _ctx: &mut Context<Self>, _alpha: f64) {}
+
+    /// The application is going to the background, no frames run until it is resumed
+    fn suspended(&mut self, _ctx: &mut Context<Self>) {}
+
+    /// The application is active again, also called once at startup after the window is created
+    fn resumed(&mut self, _ctx: &mut Context<Self>) {}
+
+    /// A file is being dragged over the window, called once per file
+    fn file_hovered(&mut self, _ctx: &mut Context<Self>, _path: PathBuf) {}
+
+    /// The hovered files left the window without being dropped
+    fn file_hover_cancelled(&mut self, _ctx: &mut Context<Self>) {}
+
+    /// A file was dropped on the window, called once per file
+    fn file_dropped(&mut self, _ctx: &mut Context<Self>, _path: PathBuf) {}
 }
\ No newline at end of file
@@ -1,7 +1,29 @@
+pub mod animation;
 pub mod application;
+pub mod asset;
+pub mod camera;
+pub mod config;
+pub mod events;
+pub mod gizmo;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+pub mod input;
 pub mod interface;
+pub mod locale;
+pub mod math;
+pub mod net;
+pub mod profiler;
+pub mod random;
+pub mod reflect;
 pub mod renderer;
+pub mod replay;
+pub mod save;
+pub mod spatial;
+pub mod tasks;
+pub mod tilemap;
 pub mod timing;
+pub mod tween;
+pub mod window;
 
 /*
 use glutin::{config::{Config, ConfigTemplateBuilder}, context::ContextAttributesBuilder, display::GetGlDisplay};
@@ -0,0 +1,265 @@
+use std::{
+    collections::HashMap,
+    fmt::{
+        Display,
+        Write
+    },
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf
+    },
+    sync::{
+        Arc,
+        RwLock
+    }
+};
+
+use serde::Deserialize;
+
+use crate::config::LocaleConfig;
+
+/// Current and fallback table
+type Tables = (Arc<StringTable>, Option<Arc<StringTable>>);
+
+/// Tables used by `tr!`, replaced whenever a `Localization` switches language
+static ACTIVE: RwLock<Option<Tables>> = RwLock::new(None);
+
+/// Sent to the event bus after the language changed, so UI can rebuild its text
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageChanged {
+    pub language: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TableFile {
+    meta: TableMeta,
+    strings: toml::Table,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TableMeta {
+    name: Option<String>,
+    fonts: Vec<PathBuf>,
+}
+
+/// Strings of one language, read from a TOML file:
+///
+/// ```toml
+/// [meta]
+/// name = "Deutsch"
+/// fonts = ["fonts/NotoSans-Regular.ttf"]
+///
+/// [strings]
+/// greeting = "Hallo, {name}!"
+/// menu.play = "Spielen"
+/// ```
+///
+/// Nested tables become dotted keys, `{{` and `}}` escape braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringTable {
+    pub language: String,
+    /// Display name for language menus, the language code if the file has none
+    pub name: String,
+    /// Fonts the text renderer should fall back to for this language's script, relative to the asset root
+    pub fonts: Vec<PathBuf>,
+    strings: HashMap<String, String>,
+}
+
+impl StringTable {
+    pub fn load(language: &str, path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(language, &fs::read_to_string(path)?)
+    }
+
+    pub fn parse(language: &str, text: &str) -> io::Result<Self> {
+        let file: TableFile = toml::from_str(text)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+        let mut strings = HashMap::new();
+        flatten("", file.strings, &mut strings)?;
+
+        Ok(Self {
+            language: language.to_string(),
+            name: file.meta.name.unwrap_or_else(|| language.to_string()),
+            fonts: file.meta.fonts,
+            strings,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.strings.keys().map(String::as_str)
+    }
+}
+
+/// The loaded language and its fallback
+pub struct Localization {
+    dir: PathBuf,
+    current: Arc<StringTable>,
+    fallback: Option<Arc<StringTable>>,
+}
+
+impl Localization {
+    /// Loads the configured language from `dir`, usually `<assets root>/<config dir>`
+    pub fn new(dir: impl Into<PathBuf>, config: &LocaleConfig) -> io::Result<Self> {
+        let dir = dir.into();
+        let current = Arc::new(StringTable::load(&config.language, language_path(&dir, &config.language))?);
+        let fallback = match config.fallback.as_str() {
+            "" => None,
+            language if language == config.language => Some(current.clone()),
+            language => Some(Arc::new(StringTable::load(language, language_path(&dir, language))?))
+        };
+
+        let localization = Self {
+            dir,
+            current,
+            fallback,
+        };
+        localization.activate();
+        Ok(localization)
+    }
+
+    pub fn language(&self) -> &str {
+        &self.current.language
+    }
+
+    pub fn table(&self) -> &StringTable {
+        &self.current
+    }
+
+    /// Loads another language, keeping the current one if that fails
+    pub fn set_language(&mut self, language: &str) -> io::Result<()> {
+        let table = match &self.fallback {
+            Some(fallback) if fallback.language == language => fallback.clone(),
+            _ => Arc::new(StringTable::load(language, language_path(&self.dir, language))?)
+        };
+
+        self.current = table;
+        self.activate();
+        Ok(())
+    }
+
+    /// Language codes with a table in the locale directory
+    pub fn languages(&self) -> io::Result<Vec<String>> {
+        let mut languages = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "toml") {
+                if let Some(language) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    languages.push(language.to_string());
+                }
+            }
+        }
+        languages.sort();
+        Ok(languages)
+    }
+
+    /// Fallback fonts for the current language, followed by the fallback language's
+    pub fn fonts(&self) -> impl Iterator<Item = &PathBuf> {
+        let fallback = self.fallback.iter()
+            .filter(|table| !Arc::ptr_eq(table, &self.current))
+            .flat_map(|table| table.fonts.iter());
+        self.current.fonts.iter().chain(fallback)
+    }
+
+    /// The string for `key` with `{name}` placeholders filled in from `args`.
+    /// Missing strings come from the fallback language, and the key itself is returned as a last resort.
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        lookup(&self.current, self.fallback.as_deref(), key, args)
+    }
+
+    fn activate(&self) {
+        *ACTIVE.write().unwrap() = Some((self.current.clone(), self.fallback.clone()));
+    }
+}
+
+/// Looks up `key` in the most recently loaded language, used by `tr!`
+pub fn translate(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    match &*ACTIVE.read().unwrap() {
+        Some((current, fallback)) => lookup(current, fallback.as_deref(), key, args),
+        None => key.to_string()
+    }
+}
+
+/// Translates a key in the current language: `tr!("greeting", name = player.name)`
+#[macro_export]
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::locale::translate($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::locale::translate($key, &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+])
+    };
+}
+
+fn language_path(dir: &Path, language: &str) -> PathBuf {
+    dir.join(format!("{}.toml", language))
+}
+
+fn lookup(current: &StringTable, fallback: Option<&StringTable>, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let Some(text) = current.get(key).or_else(|| fallback.and_then(|table| table.get(key))) else {
+        return key.to_string()
+    };
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['{', '}']) {
+        out.push_str(&rest[..start]);
+        let brace = rest.as_bytes()[start];
+        rest = &rest[start + 1..];
+
+        // Doubled braces are literal
+        if rest.as_bytes().first() == Some(&brace) {
+            out.push(brace as char);
+            rest = &rest[1..];
+            continue
+        }
+        if brace == b'}' {
+            out.push('}');
+            continue
+        }
+
+        let Some(end) = rest.find('}') else {
+            out.push('{');
+            continue
+        };
+        let name = &rest[..end];
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => {
+                let _ = write!(out, "{}", value);
+            },
+            None => {
+                out.push('{');
+                out.push_str(name);
+                out.push('}');
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn flatten(prefix: &str, table: toml::Table, strings: &mut HashMap<String, String>) -> io::Result<()> {
+    for (key, value) in table {
+        let key = match prefix.is_empty() {
+            true => key,
+            false => format!("{}.{}", prefix, key)
+        };
+
+        match value {
+            toml::Value::String(text) => {
+                strings.insert(key, text);
+            },
+            toml::Value::Table(table) => flatten(&key, table, strings)?,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("string {} is not text", key)))
+        }
+    }
+    Ok(())
+}
\ No newline at end of file
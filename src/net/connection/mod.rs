@@ -0,0 +1,284 @@
+use std::collections::{
+    BTreeMap,
+    VecDeque
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use super::{
+    Channel,
+    NetConfig
+};
+
+/// Payload budget per packet, below the common internet MTU so packets are not fragmented
+const PACKET_BUDGET: usize = 1100;
+/// Per message overhead assumed when filling a packet
+const MESSAGE_OVERHEAD: usize = 16;
+/// Reliable messages further ahead of the next expected one are dropped, the sender resends them later
+const RECEIVE_WINDOW: u32 = 1024;
+/// Payload bytes held back waiting for a missing earlier message, so a peer cannot fill memory with gaps
+const MAX_BUFFERED_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Packet {
+    pub(crate) protocol_id: u64,
+    pub(crate) body: PacketBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum PacketBody {
+    ConnectRequest,
+    /// Sent back for a `ConnectRequest`, the client has to echo the token so the server knows the address is real
+    Challenge { token: u64 },
+    ChallengeResponse { token: u64 },
+    ConnectAccept,
+    ConnectDeny { reason: String },
+    /// `ack` is the next reliable sequence the sender expects, everything before it arrived
+    Data { ack: u32, reliable: Vec<(u32, Vec<u8>)>, unreliable: Vec<Vec<u8>> },
+    Heartbeat { ack: u32 },
+    Disconnect,
+}
+
+struct Unacked {
+    sequence: u32,
+    payload: Vec<u8>,
+    last_sent: Option<f64>,
+}
+
+/// Reliability and liveness state of one connection, shared by both socket sides
+pub(crate) struct Connection {
+    next_sequence: u32,
+    unacked: VecDeque<Unacked>,
+    unreliable: Vec<Vec<u8>>,
+    next_expected: u32,
+    out_of_order: BTreeMap<u32, Vec<u8>>,
+    out_of_order_bytes: usize,
+    /// The peer has to be told about newly received reliable messages
+    ack_pending: bool,
+    pub(crate) last_received: f64,
+    last_sent: f64,
+    pub(crate) rtt: f64,
+}
+
+impl Connection {
+    pub(crate) fn new(now: f64) -> Self {
+        Self {
+            next_sequence: 0,
+            unacked: VecDeque::new(),
+            unreliable: Vec::new(),
+            next_expected: 0,
+            out_of_order: BTreeMap::new(),
+            out_of_order_bytes: 0,
+            ack_pending: false,
+            last_received: now,
+            last_sent: now,
+            rtt: 0.1,
+        }
+    }
+
+    pub(crate) fn queue(&mut self, channel: Channel, payload: Vec<u8>) {
+        match channel {
+            Channel::Reliable => {
+                self.unacked.push_back(Unacked {
+                    sequence: self.next_sequence,
+                    payload,
+                    last_sent: None,
+                });
+                self.next_sequence = self.next_sequence.wrapping_add(1);
+            },
+            Channel::Unreliable => self.unreliable.push(payload)
+        }
+    }
+
+    /// Reliable messages still waiting for an ack
+    pub(crate) fn pending_reliable(&self) -> usize {
+        self.unacked.len()
+    }
+
+    /// Handles a data or heartbeat packet, returning the payloads now deliverable in order
+    pub(crate) fn receive(&mut self, now: f64, body: PacketBody) -> Vec<Vec<u8>> {
+        self.last_received = now;
+
+        let (ack, reliable, unreliable) = match body {
+            PacketBody::Data { ack, reliable, unreliable } => (ack, reliable, unreliable),
+            PacketBody::Heartbeat { ack } => (ack, Vec::new(), Vec::new()),
+            _ => return Vec::new()
+        };
+
+        self.acknowledge(now, ack);
+
+        let mut delivered = unreliable;
+        for (sequence, payload) in reliable {
+            self.ack_pending = true;
+            let ahead = sequence.wrapping_sub(self.next_expected);
+            if sequence_before(sequence, self.next_expected) || ahead >= RECEIVE_WINDOW || self.out_of_order.contains_key(&sequence) {
+                continue
+            }
+            // The next expected message is always taken, it frees the buffer instead of growing it
+            if ahead > 0 && self.out_of_order_bytes + payload.len() > MAX_BUFFERED_BYTES {
+                continue
+            }
+            self.out_of_order_bytes += payload.len();
+            self.out_of_order.insert(sequence, payload);
+        }
+
+        while let Some(payload) = self.out_of_order.remove(&self.next_expected) {
+            self.out_of_order_bytes -= payload.len();
+            delivered.push(payload);
+            self.next_expected = self.next_expected.wrapping_add(1);
+        }
+
+        delivered
+    }
+
+    fn acknowledge(&mut self, now: f64, ack: u32) {
+        while let Some(front) = self.unacked.front() {
+            if !sequence_before(front.sequence, ack) {
+                break
+            }
+
+            if let Some(sent) = front.last_sent {
+                self.rtt += ((now - sent) - self.rtt) * 0.1;
+            }
+            self.unacked.pop_front();
+        }
+    }
+
+    /// Packets due this tick: queued and timed out messages, or a heartbeat to keep the connection alive
+    pub(crate) fn flush(&mut self, now: f64, config: &NetConfig) -> Vec<PacketBody> {
+        let resend_after = (self.rtt * 1.5).max(config.min_resend_time);
+        let ack = self.next_expected;
+        let mut packets = Vec::new();
+        let mut reliable = Vec::new();
+        let mut unreliable = Vec::new();
+        let mut size = 0;
+
+        let due = self.unacked.iter_mut()
+            .filter(|message| message.last_sent.is_none_or(|sent| now - sent >= resend_after));
+        for message in due {
+            if size > 0 && size + message.payload.len() > PACKET_BUDGET {
+                packets.push(data(ack, std::mem::take(&mut reliable), Vec::new()));
+                size = 0;
+            }
+            size += message.payload.len() + MESSAGE_OVERHEAD;
+            message.last_sent = Some(now);
+            reliable.push((message.sequence, message.payload.clone()));
+        }
+
+        for payload in std::mem::take(&mut self.unreliable) {
+            if size > 0 && size + payload.len() > PACKET_BUDGET {
+                packets.push(data(ack, std::mem::take(&mut reliable), std::mem::take(&mut unreliable)));
+                size = 0;
+            }
+            size += payload.len() + MESSAGE_OVERHEAD;
+            unreliable.push(payload);
+        }
+
+        if !reliable.is_empty() || !unreliable.is_empty() {
+            packets.push(data(ack, reliable, unreliable));
+        }
+
+        if packets.is_empty() && (self.ack_pending || now - self.last_sent >= config.heartbeat_interval) {
+            packets.push(PacketBody::Heartbeat { ack });
+        }
+
+        if !packets.is_empty() {
+            self.last_sent = now;
+            self.ack_pending = false;
+        }
+
+        packets
+    }
+}
+
+fn data(ack: u32, reliable: Vec<(u32, Vec<u8>)>, unreliable: Vec<Vec<u8>>) -> PacketBody {
+    PacketBody::Data {
+        ack,
+        reliable,
+        unreliable,
+    }
+}
+
+/// Wrapping comparison so sequences keep working after overflowing
+fn sequence_before(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reliable(messages: &[(u32, &[u8])]) -> PacketBody {
+        data(0, messages.iter().map(|(sequence, payload)| (*sequence, payload.to_vec())).collect(), Vec::new())
+    }
+
+    #[test]
+    fn delivers_reliable_messages_in_order_once() {
+        let mut connection = Connection::new(0.0);
+        assert!(connection.receive(0.0, reliable(&[(1, b"b"), (2, b"c")])).is_empty());
+
+        let delivered = connection.receive(0.0, reliable(&[(0, b"a"), (1, b"b")]));
+        assert_eq!(delivered, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+        assert!(connection.receive(0.0, reliable(&[(2, b"c")])).is_empty());
+        assert_eq!(connection.out_of_order_bytes, 0);
+    }
+
+    #[test]
+    fn drops_messages_beyond_the_receive_window() {
+        let mut connection = Connection::new(0.0);
+        let far = [(RECEIVE_WINDOW, &b"x"[..]), (u32::MAX / 2, &b"y"[..])];
+        connection.receive(0.0, reliable(&far));
+        assert!(connection.out_of_order.is_empty());
+
+        connection.receive(0.0, reliable(&[(RECEIVE_WINDOW - 1, b"z")]));
+        assert_eq!(connection.out_of_order.len(), 1);
+    }
+
+    #[test]
+    fn caps_the_buffered_bytes_but_still_takes_the_next_message() {
+        let mut connection = Connection::new(0.0);
+        let payload = vec![0; MAX_BUFFERED_BYTES / 4];
+        for sequence in 1..10 {
+            connection.receive(0.0, PacketBody::Data {
+                ack: 0,
+                reliable: vec![(sequence, payload.clone())],
+                unreliable: Vec::new(),
+            });
+        }
+        assert!(connection.out_of_order_bytes <= MAX_BUFFERED_BYTES);
+        assert_eq!(connection.out_of_order.len(), 4);
+
+        let delivered = connection.receive(0.0, reliable(&[(0, b"a")]));
+        assert_eq!(delivered.len(), 5);
+        assert_eq!(connection.out_of_order_bytes, 0);
+    }
+
+    #[test]
+    fn acks_clear_sent_messages_and_resend_the_rest() {
+        let config = NetConfig::default();
+        let mut connection = Connection::new(0.0);
+        for message in 0..3u8 {
+            connection.queue(Channel::Reliable, vec![message]);
+        }
+        assert_eq!(connection.flush(0.0, &config).len(), 1);
+
+        connection.receive(0.1, PacketBody::Heartbeat { ack: 2 });
+        assert_eq!(connection.pending_reliable(), 1);
+
+        let resent = connection.flush(10.0, &config);
+        match &resent[..] {
+            [PacketBody::Data { reliable, .. }] => assert_eq!(reliable, &vec![(2, vec![2])]),
+            _ => panic!("expected one data packet")
+        }
+    }
+
+    #[test]
+    fn sequences_compare_across_overflow() {
+        assert!(sequence_before(u32::MAX, 0));
+        assert!(!sequence_before(0, u32::MAX));
+        assert!(sequence_before(1, 2));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,582 @@
+pub mod replication;
+pub mod rollback;
+pub mod session;
+
+mod connection;
+
+use std::{
+    collections::{
+        hash_map::RandomState,
+        HashMap
+    },
+    hash::BuildHasher,
+    io,
+    marker::PhantomData,
+    net::{
+        SocketAddr,
+        ToSocketAddrs,
+        UdpSocket
+    }
+};
+
+use serde::{
+    de::DeserializeOwned,
+    Serialize
+};
+
+use connection::{
+    Connection,
+    Packet,
+    PacketBody
+};
+
+/// Largest datagram accepted, messages have to serialize below this
+const MAX_DATAGRAM: usize = 65_507;
+/// Disconnect packets are sent this many times since any of them may be lost
+const DISCONNECT_REPEATS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Resent until acknowledged, delivered once and in the order sent
+    Reliable,
+    /// Sent once, may be lost, duplicated or arrive out of order relative to reliable messages
+    Unreliable,
+}
+
+/// Shared by both sides, the protocol id has to match for peers to connect
+#[derive(Debug, Clone)]
+pub struct NetConfig {
+    /// Unique per game and protocol version, packets with another id are ignored
+    pub protocol_id: u64,
+    /// Seconds without packets before a connection is dropped
+    pub timeout: f64,
+    /// Seconds without outgoing packets before a heartbeat is sent
+    pub heartbeat_interval: f64,
+    /// Lower bound for the resend delay of unacknowledged reliable messages, in seconds
+    pub min_resend_time: f64,
+    /// Seconds between connection requests while connecting
+    pub connect_retry: f64,
+    pub max_connections: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnectionId(pub u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Nothing was received for longer than the timeout
+    Timeout,
+    /// The other side disconnected
+    Closed,
+    /// The server refused the connection
+    Denied(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetEvent<M> {
+    Connected(ConnectionId),
+    Disconnected(ConnectionId, DisconnectReason),
+    Message(ConnectionId, M),
+}
+
+struct Peer {
+    address: SocketAddr,
+    connection: Connection,
+}
+
+/// Accepts connections from `ClientSocket`s. Call `update` once per fixed update.
+pub struct ServerSocket<M> {
+    socket: UdpSocket,
+    config: NetConfig,
+    time: f64,
+    next_id: u64,
+    peers: HashMap<ConnectionId, Peer>,
+    addresses: HashMap<SocketAddr, ConnectionId>,
+    /// Keys the challenge tokens, so nobody outside the server can compute one for an address
+    secret: RandomState,
+    buffer: Box<[u8]>,
+    _message: PhantomData<M>,
+}
+
+enum ClientState {
+    /// `token` is the server's challenge once it arrived
+    Connecting { last_request: Option<f64>, started: f64, token: Option<u64> },
+    Connected,
+    Disconnected,
+}
+
+/// Connection to a `ServerSocket`. Call `update` once per fixed update.
+pub struct ClientSocket<M> {
+    socket: UdpSocket,
+    config: NetConfig,
+    time: f64,
+    state: ClientState,
+    connection: Connection,
+    buffer: Box<[u8]>,
+    _message: PhantomData<M>,
+}
+
+impl ConnectionId {
+    /// The id events on a `ClientSocket` use for the server
+    pub const SERVER: ConnectionId = ConnectionId(0);
+}
+
+impl<M: Serialize + DeserializeOwned> ServerSocket<M> {
+    pub fn bind(address: impl ToSocketAddrs, config: NetConfig) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            config,
+            time: 0.0,
+            next_id: 1,
+            peers: HashMap::new(),
+            addresses: HashMap::new(),
+            secret: RandomState::new(),
+            buffer: vec![0; MAX_DATAGRAM].into_boxed_slice(),
+            _message: PhantomData,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Receives and sends everything due, `delta` is the time since the last update
+    pub fn update(&mut self, delta: f64) -> io::Result<Vec<NetEvent<M>>> {
+        self.time += delta;
+        let mut events = Vec::new();
+
+        while let Some((packet, address)) = receive(&self.socket, &mut self.buffer, self.config.protocol_id)? {
+            self.handle(packet, address, &mut events)?;
+        }
+
+        let timed_out: Vec<ConnectionId> = self.peers.iter()
+            .filter(|(_, peer)| self.time - peer.connection.last_received > self.config.timeout)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in timed_out {
+            self.remove(id);
+            events.push(NetEvent::Disconnected(id, DisconnectReason::Timeout));
+        }
+
+        for peer in self.peers.values_mut() {
+            for body in peer.connection.flush(self.time, &self.config) {
+                send(&self.socket, peer.address, self.config.protocol_id, body)?;
+            }
+        }
+
+        Ok(events)
+    }
+
+    fn handle(&mut self, packet: PacketBody, address: SocketAddr, events: &mut Vec<NetEvent<M>>) -> io::Result<()> {
+        let known = self.addresses.get(&address).copied();
+
+        match (packet, known) {
+            (PacketBody::ConnectRequest | PacketBody::ChallengeResponse { .. }, Some(_)) => {
+                // The accept was lost, the client is still asking
+                send(&self.socket, address, self.config.protocol_id, PacketBody::ConnectAccept)?;
+            },
+            (PacketBody::ConnectRequest, None) => {
+                // Nothing is stored until the token comes back, so spoofed requests cannot take up slots
+                let token = self.token(address, self.epoch());
+                send(&self.socket, address, self.config.protocol_id, PacketBody::Challenge { token })?;
+            },
+            (PacketBody::ChallengeResponse { token }, None) => {
+                if !self.valid_token(address, token) {
+                    return Ok(())
+                }
+
+                if self.peers.len() >= self.config.max_connections {
+                    let reason = "server is full".to_string();
+                    return send(&self.socket, address, self.config.protocol_id, PacketBody::ConnectDeny { reason })
+                }
+
+                let id = ConnectionId(self.next_id);
+                self.next_id += 1;
+                self.peers.insert(id, Peer {
+                    address,
+                    connection: Connection::new(self.time),
+                });
+                self.addresses.insert(address, id);
+
+                send(&self.socket, address, self.config.protocol_id, PacketBody::ConnectAccept)?;
+                events.push(NetEvent::Connected(id));
+            },
+            (PacketBody::Disconnect, Some(id)) => {
+                self.remove(id);
+                events.push(NetEvent::Disconnected(id, DisconnectReason::Closed));
+            },
+            (body @ (PacketBody::Data { .. } | PacketBody::Heartbeat { .. }), Some(id)) => {
+                let peer = self.peers.get_mut(&id).unwrap();
+                for payload in peer.connection.receive(self.time, body) {
+                    if let Ok(message) = bincode::deserialize(&payload) {
+                        events.push(NetEvent::Message(id, message));
+                    }
+                }
+            },
+            _ => ()
+        }
+
+        Ok(())
+    }
+
+    /// Queues a message, sent on the next update
+    pub fn send(&mut self, id: ConnectionId, channel: Channel, message: &M) -> io::Result<()> {
+        let payload = encode(message)?;
+        let peer = self.peers.get_mut(&id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "unknown connection"))?;
+        peer.connection.queue(channel, payload);
+        Ok(())
+    }
+
+    pub fn broadcast(&mut self, channel: Channel, message: &M) -> io::Result<()> {
+        let payload = encode(message)?;
+        for peer in self.peers.values_mut() {
+            peer.connection.queue(channel, payload.clone());
+        }
+        Ok(())
+    }
+
+    /// Drops the connection right away, messages not sent yet are lost
+    pub fn disconnect(&mut self, id: ConnectionId) -> io::Result<()> {
+        if let Some(peer) = self.remove(id) {
+            for _ in 0..DISCONNECT_REPEATS {
+                send(&self.socket, peer.address, self.config.protocol_id, PacketBody::Disconnect)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn connections(&self) -> impl Iterator<Item = ConnectionId> + '_ {
+        self.peers.keys().copied()
+    }
+
+    pub fn address(&self, id: ConnectionId) -> Option<SocketAddr> {
+        self.peers.get(&id).map(|peer| peer.address)
+    }
+
+    /// Smoothed round trip time in seconds
+    pub fn rtt(&self, id: ConnectionId) -> Option<f64> {
+        self.peers.get(&id).map(|peer| peer.connection.rtt)
+    }
+
+    /// Reliable messages sent to the connection that have not been acknowledged yet
+    pub fn pending_reliable(&self, id: ConnectionId) -> Option<usize> {
+        self.peers.get(&id).map(|peer| peer.connection.pending_reliable())
+    }
+
+    /// Tokens are valid for the epoch they were made in and the one after, at least `timeout` seconds
+    fn epoch(&self) -> u64 {
+        (self.time / self.config.timeout.max(f64::EPSILON)) as u64
+    }
+
+    fn token(&self, address: SocketAddr, epoch: u64) -> u64 {
+        self.secret.hash_one((address, epoch))
+    }
+
+    fn valid_token(&self, address: SocketAddr, token: u64) -> bool {
+        let epoch = self.epoch();
+        token == self.token(address, epoch) || (epoch > 0 && token == self.token(address, epoch - 1))
+    }
+
+    fn remove(&mut self, id: ConnectionId) -> Option<Peer> {
+        let peer = self.peers.remove(&id)?;
+        self.addresses.remove(&peer.address);
+        Some(peer)
+    }
+}
+
+impl<M: Serialize + DeserializeOwned> ClientSocket<M> {
+    /// Starts connecting, the `Connected` event arrives from `update` once the server accepts
+    pub fn connect(server: impl ToSocketAddrs, config: NetConfig) -> io::Result<Self> {
+        let server = server.to_socket_addrs()?.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no server address"))?;
+
+        let local: SocketAddr = match server {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(server)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            config,
+            time: 0.0,
+            state: ClientState::Connecting { last_request: None, started: 0.0, token: None },
+            connection: Connection::new(0.0),
+            buffer: vec![0; MAX_DATAGRAM].into_boxed_slice(),
+            _message: PhantomData,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        matches!(self.state, ClientState::Connected)
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        matches!(self.state, ClientState::Disconnected)
+    }
+
+    pub fn server_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.peer_addr()
+    }
+
+    /// Smoothed round trip time in seconds
+    pub fn rtt(&self) -> f64 {
+        self.connection.rtt
+    }
+
+    /// Receives and sends everything due, `delta` is the time since the last update
+    pub fn update(&mut self, delta: f64) -> io::Result<Vec<NetEvent<M>>> {
+        self.time += delta;
+        let mut events = Vec::new();
+
+        if self.is_disconnected() {
+            return Ok(events)
+        }
+
+        while let Some((packet, _)) = receive(&self.socket, &mut self.buffer, self.config.protocol_id)? {
+            match (packet, &mut self.state) {
+                (PacketBody::Challenge { token: challenge }, ClientState::Connecting { last_request, token, .. }) => {
+                    // Answered on the retry below, which goes out this update
+                    *token = Some(challenge);
+                    *last_request = None;
+                },
+                (PacketBody::ConnectAccept, ClientState::Connecting { .. }) => {
+                    self.state = ClientState::Connected;
+                    self.connection.last_received = self.time;
+                    events.push(NetEvent::Connected(ConnectionId::SERVER));
+                },
+                (PacketBody::ConnectDeny { reason }, ClientState::Connecting { .. }) => {
+                    self.state = ClientState::Disconnected;
+                    events.push(NetEvent::Disconnected(ConnectionId::SERVER, DisconnectReason::Denied(reason)));
+                    return Ok(events)
+                },
+                (PacketBody::Disconnect, ClientState::Connected) => {
+                    self.state = ClientState::Disconnected;
+                    events.push(NetEvent::Disconnected(ConnectionId::SERVER, DisconnectReason::Closed));
+                    return Ok(events)
+                },
+                (body @ (PacketBody::Data { .. } | PacketBody::Heartbeat { .. }), ClientState::Connected) => {
+                    for payload in self.connection.receive(self.time, body) {
+                        if let Ok(message) = bincode::deserialize(&payload) {
+                            events.push(NetEvent::Message(ConnectionId::SERVER, message));
+                        }
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        match &mut self.state {
+            ClientState::Connecting { last_request, started, token } => {
+                if self.time - *started > self.config.timeout {
+                    self.state = ClientState::Disconnected;
+                    events.push(NetEvent::Disconnected(ConnectionId::SERVER, DisconnectReason::Timeout));
+                } else if last_request.is_none_or(|last| self.time - last >= self.config.connect_retry) {
+                    *last_request = Some(self.time);
+                    let body = match *token {
+                        Some(token) => PacketBody::ChallengeResponse { token },
+                        None => PacketBody::ConnectRequest
+                    };
+                    self.send_body(body)?;
+                }
+            },
+            ClientState::Connected => {
+                if self.time - self.connection.last_received > self.config.timeout {
+                    self.state = ClientState::Disconnected;
+                    events.push(NetEvent::Disconnected(ConnectionId::SERVER, DisconnectReason::Timeout));
+                    return Ok(events)
+                }
+
+                for body in self.connection.flush(self.time, &self.config) {
+                    self.send_body(body)?;
+                }
+            },
+            ClientState::Disconnected => ()
+        }
+
+        Ok(events)
+    }
+
+    /// Queues a message, messages queued while connecting are sent once connected
+    pub fn send(&mut self, channel: Channel, message: &M) -> io::Result<()> {
+        if self.is_disconnected() {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "disconnected from the server"))
+        }
+
+        self.connection.queue(channel, encode(message)?);
+        Ok(())
+    }
+
+    pub fn disconnect(&mut self) -> io::Result<()> {
+        if self.is_connected() {
+            for _ in 0..DISCONNECT_REPEATS {
+                self.send_body(PacketBody::Disconnect)?;
+            }
+        }
+
+        self.state = ClientState::Disconnected;
+        Ok(())
+    }
+
+    fn send_body(&self, body: PacketBody) -> io::Result<()> {
+        let packet = Packet {
+            protocol_id: self.config.protocol_id,
+            body,
+        };
+        let bytes = bincode::serialize(&packet).map_err(io::Error::other)?;
+        ignore_unreachable(self.socket.send(&bytes).map(|_| ()))
+    }
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        Self {
+            protocol_id: 0,
+            timeout: 5.0,
+            heartbeat_interval: 0.5,
+            min_resend_time: 0.1,
+            connect_retry: 0.25,
+            max_connections: 32,
+        }
+    }
+}
+
+fn encode<M: Serialize>(message: &M) -> io::Result<Vec<u8>> {
+    let payload = bincode::serialize(message).map_err(io::Error::other)?;
+    match payload.len() < MAX_DATAGRAM / 2 {
+        true => Ok(payload),
+        false => Err(io::Error::new(io::ErrorKind::InvalidInput, "message is too large for a datagram"))
+    }
+}
+
+fn send(socket: &UdpSocket, address: SocketAddr, protocol_id: u64, body: PacketBody) -> io::Result<()> {
+    let packet = Packet {
+        protocol_id,
+        body,
+    };
+    let bytes = bincode::serialize(&packet).map_err(io::Error::other)?;
+    ignore_unreachable(socket.send_to(&bytes, address).map(|_| ()))
+}
+
+/// Next packet with a matching protocol id, `None` once nothing is left to read
+fn receive(socket: &UdpSocket, buffer: &mut [u8], protocol_id: u64) -> io::Result<Option<(PacketBody, SocketAddr)>> {
+    loop {
+        let (length, address) = match socket.recv_from(buffer) {
+            Ok(received) => received,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+            // Reported on some platforms after sending to a closed port, the timeout handles it
+            Err(err) if err.kind() == io::ErrorKind::ConnectionReset || err.kind() == io::ErrorKind::ConnectionRefused => continue,
+            Err(err) => return Err(err)
+        };
+
+        // Malformed packets and other games' traffic are dropped
+        if let Ok(packet) = bincode::deserialize::<Packet>(&buffer[..length]) {
+            if packet.protocol_id == protocol_id {
+                return Ok(Some((packet.body, address)))
+            }
+        }
+    }
+}
+
+fn ignore_unreachable(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::ConnectionRefused || err.kind() == io::ErrorKind::ConnectionReset => Ok(()),
+        result => result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::Duration
+    };
+
+    use super::*;
+
+    fn server() -> ServerSocket<u32> {
+        ServerSocket::bind("127.0.0.1:0", NetConfig::default()).unwrap()
+    }
+
+    fn raw(server: &ServerSocket<u32>) -> UdpSocket {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.connect(server.local_addr().unwrap()).unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        socket
+    }
+
+    fn send_raw(socket: &UdpSocket, body: PacketBody) {
+        let packet = Packet {
+            protocol_id: 0,
+            body,
+        };
+        socket.send(&bincode::serialize(&packet).unwrap()).unwrap();
+    }
+
+    fn receive_raw(socket: &UdpSocket) -> PacketBody {
+        let mut buffer = [0; MAX_DATAGRAM];
+        let length = socket.recv(&mut buffer).unwrap();
+        bincode::deserialize::<Packet>(&buffer[..length]).unwrap().body
+    }
+
+    #[test]
+    fn connects_after_echoing_the_challenge() {
+        let mut server = server();
+        let mut client = ClientSocket::<u32>::connect(server.local_addr().unwrap(), NetConfig::default()).unwrap();
+
+        let mut accepted = Vec::new();
+        for _ in 0..100 {
+            client.update(0.01).unwrap();
+            accepted.extend(server.update(0.01).unwrap());
+            if client.is_connected() {
+                break
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(client.is_connected());
+        assert!(matches!(accepted[..], [NetEvent::Connected(_)]));
+        assert_eq!(server.connections().count(), 1);
+    }
+
+    #[test]
+    fn requests_without_the_token_take_no_slot() {
+        let mut server = server();
+        let socket = raw(&server);
+
+        send_raw(&socket, PacketBody::ConnectRequest);
+        send_raw(&socket, PacketBody::ChallengeResponse { token: 0 });
+        thread::sleep(Duration::from_millis(20));
+        assert!(server.update(0.01).unwrap().is_empty());
+        assert_eq!(server.connections().count(), 0);
+
+        let PacketBody::Challenge { token } = receive_raw(&socket) else {
+            panic!("expected a challenge")
+        };
+        send_raw(&socket, PacketBody::ChallengeResponse { token });
+        thread::sleep(Duration::from_millis(20));
+        assert!(matches!(server.update(0.01).unwrap()[..], [NetEvent::Connected(_)]));
+    }
+
+    #[test]
+    fn tokens_are_bound_to_the_address_and_expire() {
+        let mut server = server();
+        let address: SocketAddr = ([127, 0, 0, 1], 4000).into();
+        let other: SocketAddr = ([127, 0, 0, 1], 4001).into();
+        let token = server.token(address, server.epoch());
+
+        assert!(server.valid_token(address, token));
+        assert!(!server.valid_token(other, token));
+
+        server.time += server.config.timeout;
+        assert!(server.valid_token(address, token));
+        server.time += server.config.timeout;
+        assert!(!server.valid_token(address, token));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,265 @@
+use std::collections::{
+    BTreeMap,
+    HashMap,
+    VecDeque
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::tween::Lerp;
+
+/// Sent snapshots kept per client, older ones can no longer serve as a delta baseline
+const SNAPSHOT_HISTORY: usize = 64;
+
+/// Network id of a replicated entity, assigned by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NetEntity(pub u32);
+
+/// State of every relevant entity at a fixed tick, as a delta against a snapshot the client acknowledged.
+/// Send it unreliably, a lost snapshot is replaced by the next one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot<S> {
+    pub tick: u64,
+    /// `None` when the snapshot holds every entity
+    pub baseline: Option<u64>,
+    /// Entities that are new or changed since the baseline
+    pub changed: Vec<(NetEntity, S)>,
+    /// Entities in the baseline that were destroyed or are no longer relevant to the client
+    pub removed: Vec<NetEntity>,
+}
+
+/// Server side state for one client, builds the snapshots sent to it
+pub struct ClientReplication<S> {
+    sent: VecDeque<(u64, HashMap<NetEntity, S>)>,
+    acked: Option<(u64, HashMap<NetEntity, S>)>,
+}
+
+/// Client side state, rebuilds full snapshots and interpolates between them
+pub struct SnapshotReceiver<S> {
+    received: BTreeMap<u64, HashMap<NetEntity, S>>,
+    /// Ticks the rendered state trails the newest snapshot by, to have two snapshots to blend between
+    pub delay_ticks: f64,
+}
+
+impl<S: Clone + PartialEq> ClientReplication<S> {
+    pub fn new() -> Self {
+        Self {
+            sent: VecDeque::new(),
+            acked: None,
+        }
+    }
+
+    /// Builds the snapshot for `tick` from every entity's state.
+    /// `relevant` decides which entities the client is told about, for interest management.
+    pub fn snapshot<'a, I, F>(&mut self, tick: u64, entities: I, relevant: F) -> Snapshot<S>
+    where
+        S: 'a,
+        I: IntoIterator<Item = (NetEntity, &'a S)>,
+        F: Fn(NetEntity, &S) -> bool
+    {
+        let states: HashMap<NetEntity, S> = entities.into_iter()
+            .filter(|(entity, state)| relevant(*entity, state))
+            .map(|(entity, state)| (entity, state.clone()))
+            .collect();
+
+        // The client only keeps the last `SNAPSHOT_HISTORY` snapshots, so with acks lost for longer
+        // than that the baseline may be gone on its side and only a full snapshot gets through
+        let baseline = self.acked.as_ref()
+            .filter(|(baseline_tick, _)| tick.saturating_sub(*baseline_tick) < SNAPSHOT_HISTORY as u64);
+
+        let snapshot = match baseline {
+            Some((baseline_tick, baseline)) => {
+                let mut changed: Vec<(NetEntity, S)> = states.iter()
+                    .filter(|(entity, state)| baseline.get(entity) != Some(*state))
+                    .map(|(entity, state)| (*entity, state.clone()))
+                    .collect();
+                let mut removed: Vec<NetEntity> = baseline.keys()
+                    .filter(|entity| !states.contains_key(entity))
+                    .copied()
+                    .collect();
+                changed.sort_by_key(|(entity, _)| *entity);
+                removed.sort();
+
+                Snapshot {
+                    tick,
+                    baseline: Some(*baseline_tick),
+                    changed,
+                    removed,
+                }
+            },
+            None => {
+                let mut changed: Vec<(NetEntity, S)> = states.iter()
+                    .map(|(entity, state)| (*entity, state.clone()))
+                    .collect();
+                changed.sort_by_key(|(entity, _)| *entity);
+
+                Snapshot {
+                    tick,
+                    baseline: None,
+                    changed,
+                    removed: Vec::new(),
+                }
+            }
+        };
+
+        self.sent.push_back((tick, states));
+        if self.sent.len() > SNAPSHOT_HISTORY {
+            self.sent.pop_front();
+        }
+
+        snapshot
+    }
+
+    /// The client confirmed it received the snapshot for `tick`, later snapshots are deltas against it
+    pub fn acknowledge(&mut self, tick: u64) {
+        if self.acked.as_ref().is_some_and(|(acked, _)| *acked >= tick) {
+            return
+        }
+
+        while let Some((sent_tick, states)) = self.sent.pop_front() {
+            if sent_tick == tick {
+                self.acked = Some((sent_tick, states));
+                return
+            }
+            if sent_tick > tick {
+                self.sent.push_front((sent_tick, states));
+                return
+            }
+        }
+    }
+}
+
+impl<S: Clone + PartialEq> Default for ClientReplication<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Clone> SnapshotReceiver<S> {
+    pub fn new(delay_ticks: f64) -> Self {
+        Self {
+            received: BTreeMap::new(),
+            delay_ticks,
+        }
+    }
+
+    /// Applies a snapshot, returning the tick to acknowledge to the server.
+    /// `None` if it is older than the newest one or its baseline is no longer known.
+    pub fn receive(&mut self, snapshot: Snapshot<S>) -> Option<u64> {
+        if self.latest_tick().is_some_and(|latest| snapshot.tick <= latest) {
+            return None
+        }
+
+        let mut states = match snapshot.baseline {
+            Some(baseline) => self.received.get(&baseline)?.clone(),
+            None => HashMap::new()
+        };
+        for entity in snapshot.removed.iter() {
+            states.remove(entity);
+        }
+        states.extend(snapshot.changed);
+
+        self.received.insert(snapshot.tick, states);
+        while self.received.len() > SNAPSHOT_HISTORY {
+            self.received.pop_first();
+        }
+
+        Some(snapshot.tick)
+    }
+
+    pub fn latest_tick(&self) -> Option<u64> {
+        self.received.keys().next_back().copied()
+    }
+
+    /// Full state of the newest snapshot
+    pub fn latest(&self) -> Option<&HashMap<NetEntity, S>> {
+        self.received.values().next_back()
+    }
+
+    /// Tick to render at, `delay_ticks` behind the newest snapshot.
+    /// `alpha` is the fraction of the current fixed step that has passed, for smooth rendering between ticks.
+    pub fn render_tick(&self, alpha: f64) -> Option<f64> {
+        self.latest_tick().map(|latest| latest as f64 + alpha - self.delay_ticks)
+    }
+}
+
+impl<S: Lerp> SnapshotReceiver<S> {
+    /// Entity states blended between the snapshots around `tick`.
+    /// Entities missing from the later snapshot are left out, ones missing from the earlier one appear unblended.
+    pub fn interpolated(&self, tick: f64) -> Vec<(NetEntity, S)> {
+        let floor = tick.floor().max(0.0) as u64;
+        let before = self.received.range(..=floor).next_back();
+        let after = self.received.range(floor + 1..).next();
+
+        match (before, after) {
+            (Some((before_tick, from)), Some((after_tick, to))) => {
+                let t = ((tick - *before_tick as f64) / (*after_tick - *before_tick) as f64).clamp(0.0, 1.0) as f32;
+                to.iter()
+                    .map(|(entity, to_state)| {
+                        let state = match from.get(entity) {
+                            Some(from_state) => from_state.lerp(to_state, t),
+                            None => to_state.clone()
+                        };
+                        (*entity, state)
+                    })
+                    .collect()
+            },
+            (Some((_, states)), None) | (None, Some((_, states))) => {
+                states.iter().map(|(entity, state)| (*entity, state.clone())).collect()
+            },
+            (None, None) => Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(tick: u64) -> Vec<(NetEntity, u64)> {
+        vec![(NetEntity(0), tick), (NetEntity(1), 7)]
+    }
+
+    #[test]
+    fn deltas_apply_on_the_acked_baseline() {
+        let mut server = ClientReplication::new();
+        let mut client = SnapshotReceiver::new(2.0);
+
+        for tick in 0..10 {
+            let entities = states(tick);
+            let snapshot = server.snapshot(tick, entities.iter().map(|(entity, state)| (*entity, state)), |_, _| true);
+            if tick > 1 {
+                assert_eq!(snapshot.baseline, Some(tick - 1));
+                assert_eq!(snapshot.changed, vec![(NetEntity(0), tick)]);
+            }
+            let ack = client.receive(snapshot).unwrap();
+            server.acknowledge(ack);
+        }
+
+        let latest = client.latest().unwrap();
+        assert_eq!(latest[&NetEntity(0)], 9);
+        assert_eq!(latest[&NetEntity(1)], 7);
+    }
+
+    #[test]
+    fn recovers_after_acks_are_lost_for_longer_than_the_history() {
+        let mut server = ClientReplication::new();
+        let mut client = SnapshotReceiver::new(2.0);
+
+        for tick in 0..200 {
+            let entities = states(tick);
+            let snapshot = server.snapshot(tick, entities.iter().map(|(entity, state)| (*entity, state)), |_, _| true);
+            let ack = client.receive(snapshot);
+            // Acks of ticks 1..=100 never arrive
+            if let Some(ack) = ack.filter(|ack| *ack == 0 || *ack > 100) {
+                server.acknowledge(ack);
+            }
+        }
+
+        assert_eq!(client.latest_tick(), Some(199));
+        assert_eq!(client.latest().unwrap()[&NetEntity(0)], 199);
+    }
+}
\ No newline at end of file
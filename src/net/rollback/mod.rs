@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+
+/// Outcome of correcting a past tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Correction {
+    /// The prediction was right, nothing to re-simulate
+    Matched,
+    /// The ticks from the corrected one on are re-simulated on the next `advance` or `resimulate`
+    Rollback,
+    /// The tick is older than the kept history or has not been simulated yet, the game has to resync
+    OutOfRange,
+}
+
+struct Frame<S, I> {
+    tick: u64,
+    /// State at the start of the tick, before its input was applied
+    state: S,
+    input: I,
+}
+
+/// Keeps the state and input of the last ticks so mispredicted inputs and authoritative
+/// server states can be corrected by re-simulating from the affected tick.
+/// `I` is the input of every player for one tick, with remote players' inputs predicted until confirmed.
+pub struct Rollback<S, I> {
+    state: S,
+    tick: u64,
+    history: VecDeque<Frame<S, I>>,
+    capacity: usize,
+    rollback_from: Option<u64>,
+}
+
+impl<S: Clone, I: Clone + PartialEq> Rollback<S, I> {
+    /// `capacity` is the number of ticks that can be rolled back
+    pub fn new(state: S, tick: u64, capacity: usize) -> Self {
+        Self {
+            state,
+            tick,
+            history: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            rollback_from: None,
+        }
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// The tick `advance` simulates next
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Oldest tick that can still be corrected
+    pub fn oldest_tick(&self) -> Option<u64> {
+        self.history.front().map(|frame| frame.tick)
+    }
+
+    /// Input a past tick was simulated with, predicted or confirmed
+    pub fn input(&self, tick: u64) -> Option<&I> {
+        self.frame(tick).map(|frame| &frame.input)
+    }
+
+    /// Runs pending corrections, then simulates one tick with `input`. Call once per fixed update.
+    pub fn advance(&mut self, input: I, mut step: impl FnMut(&mut S, &I)) {
+        self.resimulate(&mut step);
+
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(Frame {
+            tick: self.tick,
+            state: self.state.clone(),
+            input,
+        });
+
+        let frame = self.history.back().unwrap();
+        step(&mut self.state, &frame.input);
+        self.tick += 1;
+    }
+
+    /// Replaces the input a past tick was simulated with, usually a remote player's confirmed input
+    pub fn confirm_input(&mut self, tick: u64, input: I) -> Correction {
+        let Some(frame) = self.frame_mut(tick) else {
+            return Correction::OutOfRange
+        };
+
+        if frame.input == input {
+            return Correction::Matched
+        }
+
+        frame.input = input;
+        self.mark(tick);
+        Correction::Rollback
+    }
+
+    /// Replaces the state at the start of `tick` with the server's, re-simulating the ticks after it.
+    /// History before the tick is dropped since the server already confirmed it.
+    pub fn correct_state(&mut self, tick: u64, state: S) -> Correction
+    where
+        S: PartialEq
+    {
+        if tick == self.tick {
+            self.history.clear();
+            self.rollback_from = None;
+            let matched = self.state == state;
+            self.state = state;
+            return match matched {
+                true => Correction::Matched,
+                false => Correction::Rollback
+            }
+        }
+
+        let Some(frame) = self.frame_mut(tick) else {
+            return Correction::OutOfRange
+        };
+
+        let matched = frame.state == state;
+        frame.state = state;
+        while self.history.front().is_some_and(|frame| frame.tick < tick) {
+            self.history.pop_front();
+        }
+        // Corrections before the server's state are covered by it
+        self.rollback_from = self.rollback_from.map(|from| from.max(tick));
+
+        match matched {
+            true => Correction::Matched,
+            false => {
+                self.mark(tick);
+                Correction::Rollback
+            }
+        }
+    }
+
+    /// Restores the earliest corrected tick and simulates forward to the present again,
+    /// returns the number of ticks re-run
+    pub fn resimulate(&mut self, mut step: impl FnMut(&mut S, &I)) -> u64 {
+        let (Some(from), Some(oldest)) = (self.rollback_from.take(), self.oldest_tick()) else {
+            return 0
+        };
+        // Frames that fell out of the history since the correction can no longer be re-run
+        let from = from.max(oldest);
+        let start = (from - oldest) as usize;
+
+        let mut state = self.history[start].state.clone();
+        for frame in self.history.iter_mut().skip(start) {
+            frame.state = state.clone();
+            step(&mut state, &frame.input);
+        }
+
+        self.state = state;
+        self.tick - from
+    }
+
+    fn frame(&self, tick: u64) -> Option<&Frame<S, I>> {
+        let first = self.history.front()?.tick;
+        self.history.get(tick.checked_sub(first)? as usize)
+    }
+
+    fn frame_mut(&mut self, tick: u64) -> Option<&mut Frame<S, I>> {
+        let first = self.history.front()?.tick;
+        self.history.get_mut(tick.checked_sub(first)? as usize)
+    }
+
+    fn mark(&mut self, tick: u64) {
+        self.rollback_from = Some(self.rollback_from.map_or(tick, |from| from.min(tick)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(state: &mut i64, input: &i64) {
+        *state += input;
+    }
+
+    fn simulated(ticks: u64) -> Rollback<i64, i64> {
+        let mut rollback = Rollback::new(0, 0, 16);
+        for _ in 0..ticks {
+            rollback.advance(1, step);
+        }
+        rollback
+    }
+
+    #[test]
+    fn confirmed_input_resimulates_from_its_tick() {
+        let mut rollback = simulated(10);
+        assert_eq!(rollback.confirm_input(5, 1), Correction::Matched);
+        assert_eq!(rollback.confirm_input(5, 3), Correction::Rollback);
+
+        assert_eq!(rollback.resimulate(step), 5);
+        assert_eq!(*rollback.state(), 12);
+        assert_eq!(rollback.input(5), Some(&3));
+    }
+
+    #[test]
+    fn state_correction_after_an_older_input_correction_is_applied() {
+        let mut rollback = simulated(10);
+        rollback.confirm_input(5, 3);
+        assert_eq!(rollback.correct_state(8, 1000), Correction::Rollback);
+
+        assert_eq!(rollback.resimulate(step), 2);
+        assert_eq!(*rollback.state(), 1002);
+        assert_eq!(rollback.oldest_tick(), Some(8));
+    }
+
+    #[test]
+    fn state_correction_at_the_present_replaces_the_state() {
+        let mut rollback = simulated(10);
+        rollback.confirm_input(5, 3);
+        assert_eq!(rollback.correct_state(10, 50), Correction::Rollback);
+
+        assert_eq!(rollback.resimulate(step), 0);
+        assert_eq!(*rollback.state(), 50);
+    }
+
+    #[test]
+    fn corrections_outside_the_history_are_out_of_range() {
+        let mut rollback = simulated(20);
+        assert_eq!(rollback.confirm_input(2, 5), Correction::OutOfRange);
+        assert_eq!(rollback.confirm_input(20, 5), Correction::OutOfRange);
+        assert_eq!(rollback.correct_state(30, 0), Correction::OutOfRange);
+    }
+}
\ No newline at end of file
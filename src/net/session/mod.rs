@@ -0,0 +1,402 @@
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap
+    },
+    io,
+    net::ToSocketAddrs
+};
+
+use serde::{
+    de::DeserializeOwned,
+    Deserialize,
+    Serialize
+};
+
+use crate::events::EventBus;
+
+use super::{
+    Channel,
+    ClientSocket,
+    ConnectionId,
+    DisconnectReason,
+    NetConfig,
+    NetEvent,
+    ServerSocket
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PlayerId(pub u32);
+
+impl PlayerId {
+    /// The hosting player always has this id
+    pub const HOST: PlayerId = PlayerId(0);
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    pub id: PlayerId,
+    pub name: String,
+    pub ready: bool,
+    /// Free form values such as the chosen team or character
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Sent to the event bus by `Session::update`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent<M> {
+    /// The host accepted this player, the player list is complete from here on
+    Joined { local: PlayerId },
+    PlayerJoined(PlayerInfo),
+    PlayerLeft(PlayerId),
+    ReadyChanged { player: PlayerId, ready: bool },
+    MetadataChanged { player: PlayerId, key: String, value: String },
+    Chat { from: PlayerId, text: String },
+    Message { from: PlayerId, message: M },
+    /// The session ended for this player, `update` does nothing afterwards
+    Ended(DisconnectReason),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum SessionMessage<M> {
+    Join { name: String, metadata: BTreeMap<String, String> },
+    Welcome { local: PlayerId, players: Vec<PlayerInfo> },
+    PlayerJoined(PlayerInfo),
+    PlayerLeft(PlayerId),
+    SetReady(bool),
+    ReadyChanged { player: PlayerId, ready: bool },
+    SetMetadata { key: String, value: String },
+    MetadataChanged { player: PlayerId, key: String, value: String },
+    SendChat(String),
+    Chat { from: PlayerId, text: String },
+    Game(M),
+}
+
+enum Role<M> {
+    Host {
+        server: ServerSocket<SessionMessage<M>>,
+        players: HashMap<ConnectionId, PlayerId>,
+        next_id: u32,
+    },
+    Guest {
+        client: ClientSocket<SessionMessage<M>>,
+        join: Option<SessionMessage<M>>,
+    },
+}
+
+/// A lobby and game session between a host and the players that joined it.
+/// The host is authoritative: guests send requests and the host broadcasts the results.
+/// Guests exchange game messages with the host only.
+pub struct Session<M> {
+    role: Role<M>,
+    local: Option<PlayerId>,
+    players: BTreeMap<PlayerId, PlayerInfo>,
+    /// Events caused by the local player, sent out on the next update
+    pending: Vec<SessionEvent<M>>,
+    ended: bool,
+}
+
+impl<M> Session<M>
+where
+    M: Serialize + DeserializeOwned + Clone + 'static
+{
+    /// Opens a session on `address` with the local player as host, guests fill the remaining slots
+    pub fn host(address: impl ToSocketAddrs, mut config: NetConfig, max_players: usize, name: &str) -> io::Result<Self> {
+        config.max_connections = max_players.saturating_sub(1);
+        let server = ServerSocket::bind(address, config)?;
+
+        let host = PlayerInfo {
+            id: PlayerId::HOST,
+            name: name.to_string(),
+            ready: false,
+            metadata: BTreeMap::new(),
+        };
+
+        Ok(Self {
+            role: Role::Host {
+                server,
+                players: HashMap::new(),
+                next_id: 1,
+            },
+            local: Some(PlayerId::HOST),
+            players: BTreeMap::from([(PlayerId::HOST, host)]),
+            pending: vec![SessionEvent::Joined { local: PlayerId::HOST }],
+            ended: false,
+        })
+    }
+
+    /// Connects to a host, `SessionEvent::Joined` follows once the host accepts
+    pub fn join(address: impl ToSocketAddrs, config: NetConfig, name: &str) -> io::Result<Self> {
+        let client = ClientSocket::connect(address, config)?;
+
+        Ok(Self {
+            role: Role::Guest {
+                client,
+                join: Some(SessionMessage::Join {
+                    name: name.to_string(),
+                    metadata: BTreeMap::new(),
+                }),
+            },
+            local: None,
+            players: BTreeMap::new(),
+            pending: Vec::new(),
+            ended: false,
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.role, Role::Host { .. })
+    }
+
+    /// `None` until the host accepted this player
+    pub fn local_player(&self) -> Option<PlayerId> {
+        self.local
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &PlayerInfo> {
+        self.players.values()
+    }
+
+    pub fn player(&self, id: PlayerId) -> Option<&PlayerInfo> {
+        self.players.get(&id)
+    }
+
+    pub fn all_ready(&self) -> bool {
+        self.players.values().all(|player| player.ready)
+    }
+
+    pub fn has_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Sends and receives, pushing `SessionEvent<M>`s to the event bus. Call once per fixed update.
+    pub fn update(&mut self, delta: f64, events: &mut EventBus) -> io::Result<()> {
+        if self.ended {
+            return Ok(())
+        }
+
+        let mut out = std::mem::take(&mut self.pending);
+        match self.is_host() {
+            true => self.update_host(delta, &mut out)?,
+            false => self.update_guest(delta, &mut out)?
+        }
+
+        for event in out {
+            events.send(event);
+        }
+        Ok(())
+    }
+
+    pub fn set_ready(&mut self, ready: bool) -> io::Result<()> {
+        self.request(SessionMessage::SetReady(ready))
+    }
+
+    pub fn set_metadata(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.request(SessionMessage::SetMetadata {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    pub fn chat(&mut self, text: &str) -> io::Result<()> {
+        self.request(SessionMessage::SendChat(text.to_string()))
+    }
+
+    /// Sends a game message. Guests can only send to the host.
+    pub fn send(&mut self, to: PlayerId, channel: Channel, message: M) -> io::Result<()> {
+        match &mut self.role {
+            Role::Host { server, players, .. } => {
+                let connection = players.iter()
+                    .find(|(_, player)| **player == to)
+                    .map(|(connection, _)| *connection)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown player"))?;
+                server.send(connection, channel, &SessionMessage::Game(message))
+            },
+            Role::Guest { client, .. } => {
+                if to != PlayerId::HOST {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "guests can only message the host"))
+                }
+                client.send(channel, &SessionMessage::Game(message))
+            }
+        }
+    }
+
+    /// The host sends to every guest, a guest sends to the host
+    pub fn broadcast(&mut self, channel: Channel, message: M) -> io::Result<()> {
+        match &mut self.role {
+            Role::Host { server, .. } => server.broadcast(channel, &SessionMessage::Game(message)),
+            Role::Guest { client, .. } => client.send(channel, &SessionMessage::Game(message))
+        }
+    }
+
+    /// Closes the session, as a host this disconnects every guest
+    pub fn leave(&mut self) -> io::Result<()> {
+        self.ended = true;
+        match &mut self.role {
+            Role::Host { server, players, .. } => {
+                for connection in players.keys() {
+                    server.disconnect(*connection)?;
+                }
+                players.clear();
+                Ok(())
+            },
+            Role::Guest { client, .. } => client.disconnect()
+        }
+    }
+
+    /// Applies a request from the local player, directly as host or through the host as guest
+    fn request(&mut self, message: SessionMessage<M>) -> io::Result<()> {
+        match &mut self.role {
+            Role::Host { server, .. } => {
+                // Handled like a guest's request so the host sees the same events
+                host_apply(server, &mut self.players, PlayerId::HOST, message, &mut self.pending)
+            },
+            Role::Guest { client, .. } => client.send(Channel::Reliable, &message)
+        }
+    }
+
+    fn update_host(&mut self, delta: f64, out: &mut Vec<SessionEvent<M>>) -> io::Result<()> {
+        let Role::Host { server, players, next_id } = &mut self.role else {
+            return Ok(())
+        };
+
+        for event in server.update(delta)? {
+            match event {
+                // Connections only become players once they sent their join request
+                NetEvent::Connected(_) => (),
+                NetEvent::Disconnected(connection, _) => {
+                    if let Some(id) = players.remove(&connection) {
+                        self.players.remove(&id);
+                        server.broadcast(Channel::Reliable, &SessionMessage::PlayerLeft(id))?;
+                        out.push(SessionEvent::PlayerLeft(id));
+                    }
+                },
+                NetEvent::Message(connection, SessionMessage::Join { name, metadata }) => {
+                    if players.contains_key(&connection) {
+                        continue
+                    }
+
+                    let info = PlayerInfo {
+                        id: PlayerId(*next_id),
+                        name,
+                        ready: false,
+                        metadata,
+                    };
+                    *next_id += 1;
+
+                    for other in players.keys() {
+                        server.send(*other, Channel::Reliable, &SessionMessage::PlayerJoined(info.clone()))?;
+                    }
+                    players.insert(connection, info.id);
+                    self.players.insert(info.id, info.clone());
+
+                    let welcome = SessionMessage::Welcome {
+                        local: info.id,
+                        players: self.players.values().cloned().collect(),
+                    };
+                    server.send(connection, Channel::Reliable, &welcome)?;
+                    out.push(SessionEvent::PlayerJoined(info));
+                },
+                NetEvent::Message(connection, message) => {
+                    if let Some(from) = players.get(&connection).copied() {
+                        host_apply(server, &mut self.players, from, message, out)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_guest(&mut self, delta: f64, out: &mut Vec<SessionEvent<M>>) -> io::Result<()> {
+        let Role::Guest { client, join } = &mut self.role else {
+            return Ok(())
+        };
+
+        for event in client.update(delta)? {
+            let message = match event {
+                NetEvent::Connected(_) => {
+                    if let Some(join) = join.take() {
+                        client.send(Channel::Reliable, &join)?;
+                    }
+                    continue
+                },
+                NetEvent::Disconnected(_, reason) => {
+                    self.ended = true;
+                    out.push(SessionEvent::Ended(reason));
+                    continue
+                },
+                NetEvent::Message(_, message) => message
+            };
+
+            match message {
+                SessionMessage::Welcome { local, players } => {
+                    self.local = Some(local);
+                    self.players = players.into_iter().map(|player| (player.id, player)).collect();
+                    out.push(SessionEvent::Joined { local });
+                },
+                SessionMessage::PlayerJoined(info) => {
+                    self.players.insert(info.id, info.clone());
+                    out.push(SessionEvent::PlayerJoined(info));
+                },
+                SessionMessage::PlayerLeft(id) => {
+                    self.players.remove(&id);
+                    out.push(SessionEvent::PlayerLeft(id));
+                },
+                SessionMessage::ReadyChanged { player, ready } => {
+                    if let Some(info) = self.players.get_mut(&player) {
+                        info.ready = ready;
+                    }
+                    out.push(SessionEvent::ReadyChanged { player, ready });
+                },
+                SessionMessage::MetadataChanged { player, key, value } => {
+                    if let Some(info) = self.players.get_mut(&player) {
+                        info.metadata.insert(key.clone(), value.clone());
+                    }
+                    out.push(SessionEvent::MetadataChanged { player, key, value });
+                },
+                SessionMessage::Chat { from, text } => out.push(SessionEvent::Chat { from, text }),
+                SessionMessage::Game(message) => out.push(SessionEvent::Message { from: PlayerId::HOST, message }),
+                _ => ()
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies a player's request on the host and tells every guest about the result
+fn host_apply<M: Serialize + DeserializeOwned>(
+    server: &mut ServerSocket<SessionMessage<M>>,
+    players: &mut BTreeMap<PlayerId, PlayerInfo>,
+    from: PlayerId,
+    message: SessionMessage<M>,
+    out: &mut Vec<SessionEvent<M>>
+) -> io::Result<()> {
+    match message {
+        SessionMessage::SetReady(ready) => {
+            if let Some(info) = players.get_mut(&from) {
+                info.ready = ready;
+                server.broadcast(Channel::Reliable, &SessionMessage::ReadyChanged { player: from, ready })?;
+                out.push(SessionEvent::ReadyChanged { player: from, ready });
+            }
+        },
+        SessionMessage::SetMetadata { key, value } => {
+            if let Some(info) = players.get_mut(&from) {
+                info.metadata.insert(key.clone(), value.clone());
+                let changed = SessionMessage::MetadataChanged { player: from, key: key.clone(), value: value.clone() };
+                server.broadcast(Channel::Reliable, &changed)?;
+                out.push(SessionEvent::MetadataChanged { player: from, key, value });
+            }
+        },
+        SessionMessage::SendChat(text) => {
+            server.broadcast(Channel::Reliable, &SessionMessage::Chat { from, text: text.clone() })?;
+            out.push(SessionEvent::Chat { from, text });
+        },
+        SessionMessage::Game(message) => out.push(SessionEvent::Message { from, message }),
+        // Everything else only travels from the host to guests
+        _ => ()
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,301 @@
+use std::{
+    cell::Cell,
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write
+    },
+    path::Path,
+    sync::{
+        atomic::{
+            AtomicBool,
+            AtomicU64,
+            Ordering
+        },
+        Mutex,
+        OnceLock
+    },
+    time::Duration
+};
+
+use web_time::Instant;
+
+/// Times the rest of the enclosing scope under the given name
+/// ```ignore
+/// profile_scope!("physics");
+/// ```
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        static PROFILE_SITE: $crate::profiler::ScopeSite = $crate::profiler::ScopeSite::new($name, file!(), line!());
+        let _profile_scope = $crate::profiler::ScopeGuard::at(&PROFILE_SITE);
+    };
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(0);
+static STATE: Mutex<State> = Mutex::new(State::new());
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+thread_local! {
+    static THREAD_ID: u64 = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+    static DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// One timed scope, times are relative to when profiling started
+#[derive(Debug, Clone)]
+pub struct SpanRecord {
+    pub name: &'static str,
+    pub thread: u64,
+    pub depth: u32,
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Every span recorded during one frame
+#[derive(Debug, Clone, Default)]
+pub struct FrameProfile {
+    pub index: u64,
+    pub start: Duration,
+    pub duration: Duration,
+    /// Ordered by start time
+    pub spans: Vec<SpanRecord>,
+}
+
+struct State {
+    frame_index: u64,
+    frame_start: Duration,
+    current: Vec<SpanRecord>,
+    last: Option<FrameProfile>,
+    capture: Option<Vec<FrameProfile>>,
+}
+
+impl State {
+    const fn new() -> Self {
+        Self {
+            frame_index: 0,
+            frame_start: Duration::ZERO,
+            current: Vec::new(),
+            last: None,
+            capture: None,
+        }
+    }
+}
+
+/// Static information about a `profile_scope!` call site
+pub struct ScopeSite {
+    name: &'static str,
+    #[cfg_attr(not(feature = "puffin"), allow(dead_code))]
+    file: &'static str,
+    #[cfg_attr(not(feature = "puffin"), allow(dead_code))]
+    line: u32,
+    #[cfg(feature = "puffin")]
+    puffin_id: OnceLock<puffin::ScopeId>,
+}
+
+impl ScopeSite {
+    pub const fn new(name: &'static str, file: &'static str, line: u32) -> Self {
+        Self {
+            name,
+            file,
+            line,
+            #[cfg(feature = "puffin")]
+            puffin_id: OnceLock::new(),
+        }
+    }
+}
+
+pub struct ScopeGuard {
+    name: &'static str,
+    start: Option<Instant>,
+    #[cfg(feature = "puffin")]
+    _puffin: Option<puffin::ProfilerScope>,
+}
+
+impl ScopeGuard {
+    pub fn new(name: &'static str) -> Self {
+        let start = match is_enabled() {
+            true => {
+                DEPTH.with(|depth| depth.set(depth.get() + 1));
+                Some(Instant::now())
+            },
+            false => None
+        };
+
+        Self {
+            name,
+            start,
+            #[cfg(feature = "puffin")]
+            _puffin: None,
+        }
+    }
+
+    /// Also reports the scope to puffin when the `puffin` feature is enabled
+    pub fn at(site: &'static ScopeSite) -> Self {
+        #[cfg_attr(not(feature = "puffin"), allow(unused_mut))]
+        let mut guard = Self::new(site.name);
+
+        #[cfg(feature = "puffin")]
+        if puffin::are_scopes_on() {
+            let id = site.puffin_id.get_or_init(|| {
+                puffin::ThreadProfiler::call(|profiler| {
+                    profiler.register_named_scope(site.name, "", puffin::short_file_name(site.file), site.line)
+                })
+            });
+            guard._puffin = Some(puffin::ProfilerScope::new(*id, ""));
+        }
+
+        guard
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let Some(start) = self.start else {
+            return
+        };
+
+        let duration = start.elapsed();
+        let depth = DEPTH.with(|depth| {
+            depth.set(depth.get() - 1);
+            depth.get()
+        });
+
+        let record = SpanRecord {
+            name: self.name,
+            thread: THREAD_ID.with(|id| *id),
+            depth,
+            start: start.duration_since(epoch()),
+            duration,
+        };
+
+        lock().current.push(record);
+    }
+}
+
+impl FrameProfile {
+    /// Total time spent in spans with this name
+    pub fn total(&self, name: &str) -> Duration {
+        self.spans.iter()
+            .filter(|span| span.name == name)
+            .map(|span| span.duration)
+            .sum()
+    }
+
+    /// Spans that are direct children of the given top level or nested span
+    pub fn children<'a>(&'a self, parent: &'a SpanRecord) -> impl Iterator<Item = &'a SpanRecord> {
+        let end = parent.start + parent.duration;
+        self.spans.iter().filter(move |span| {
+            span.thread == parent.thread
+                && span.depth == parent.depth + 1
+                && span.start >= parent.start
+                && span.start + span.duration <= end
+        })
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = &SpanRecord> {
+        self.spans.iter().filter(|span| span.depth == 0)
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    epoch();
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    #[cfg(feature = "puffin")]
+    puffin::set_scopes_on(enabled);
+}
+
+/// Closes the current frame, called by the application at the start of every frame
+pub fn new_frame() {
+    #[cfg(feature = "puffin")]
+    puffin::GlobalProfiler::lock().new_frame();
+
+    let now = epoch().elapsed();
+    let mut state = lock();
+
+    let mut spans = std::mem::take(&mut state.current);
+    spans.sort_by_key(|span| span.start);
+
+    let frame = FrameProfile {
+        index: state.frame_index,
+        start: state.frame_start,
+        duration: now.saturating_sub(state.frame_start),
+        spans,
+    };
+
+    if let Some(capture) = state.capture.as_mut() {
+        capture.push(frame.clone());
+    }
+
+    state.last = Some(frame);
+    state.frame_index += 1;
+    state.frame_start = now;
+}
+
+/// Spans of the last completed frame
+pub fn last_frame() -> Option<FrameProfile> {
+    lock().last.clone()
+}
+
+/// Starts keeping every frame until `stop_capture` is called
+pub fn start_capture() {
+    lock().capture = Some(Vec::new());
+}
+
+pub fn stop_capture() -> Vec<FrameProfile> {
+    lock().capture.take().unwrap_or_default()
+}
+
+/// Writes frames in the Chrome trace event format, viewable in chrome://tracing or Perfetto
+pub fn write_chrome_trace(frames: &[FrameProfile], path: impl AsRef<Path>) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write!(out, "{{\"traceEvents\":[")?;
+
+    let mut first = true;
+    for frame in frames {
+        let events = std::iter::once(("frame", 0, frame.start, frame.duration))
+            .chain(frame.spans.iter().map(|span| (span.name, span.thread + 1, span.start, span.duration)));
+
+        for (name, thread, start, duration) in events {
+            if !first {
+                write!(out, ",")?;
+            }
+            first = false;
+
+            write!(
+                out,
+                "{{\"name\":\"{}\",\"ph\":\"X\",\"pid\":0,\"tid\":{},\"ts\":{},\"dur\":{}}}",
+                escape(name),
+                thread,
+                start.as_secs_f64() * 1e6,
+                duration.as_secs_f64() * 1e6
+            )?;
+        }
+    }
+
+    write!(out, "]}}")?;
+    out.flush()
+}
+
+fn escape(name: &str) -> String {
+    name.chars().flat_map(|c| match c {
+        '"' => vec!['\\', '"'],
+        '\\' => vec!['\\', '\\'],
+        c if c.is_control() => vec![' '],
+        c => vec![c]
+    }).collect()
+}
+
+fn epoch() -> Instant {
+    *EPOCH.get_or_init(Instant::now)
+}
+
+fn lock() -> std::sync::MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
\ No newline at end of file
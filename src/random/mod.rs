@@ -0,0 +1,263 @@
+use std::{
+    collections::HashMap,
+    ops::{
+        Range,
+        RangeInclusive
+    }
+};
+
+use glam::{
+    Vec2,
+    Vec3
+};
+
+/// Small, fast generator (xoshiro256++) with output that stays the same across platforms and engine versions,
+/// so seeded gameplay and replays reproduce exactly. Not suitable for cryptography.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut mix = seed;
+        Self {
+            state: std::array::from_fn(|_| splitmix64(&mut mix)),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s0.wrapping_add(*s3).rotate_left(23).wrapping_add(*s0);
+        let t = *s1 << 17;
+
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+
+        result
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Uniform in 0..1
+    pub fn f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 * (1.0 / (1u64 << 24) as f32)
+    }
+
+    /// Uniform in 0..1
+    pub fn f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// True with the given probability
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.f64() < probability
+    }
+
+    /// Uniform in a range, `rng.range(1..=6)` or `rng.range(-1.0..1.0)`
+    pub fn range<R: SampleRange>(&mut self, range: R) -> R::Output {
+        range.sample(self)
+    }
+
+    /// Random direction of length 1
+    pub fn unit_vec2(&mut self) -> Vec2 {
+        let angle = self.f32() * std::f32::consts::TAU;
+        Vec2::from_angle(angle)
+    }
+
+    /// Random direction of length 1, evenly spread over the sphere
+    pub fn unit_vec3(&mut self) -> Vec3 {
+        let z = self.range(-1.0f32..=1.0);
+        let angle = self.f32() * std::f32::consts::TAU;
+        let radius = (1.0 - z * z).max(0.0).sqrt();
+        Vec3::new(radius * angle.cos(), radius * angle.sin(), z)
+    }
+
+    /// Uniform point inside the unit circle
+    pub fn in_unit_circle(&mut self) -> Vec2 {
+        self.unit_vec2() * self.f32().sqrt()
+    }
+
+    /// Uniform point inside the unit sphere
+    pub fn in_unit_sphere(&mut self) -> Vec3 {
+        self.unit_vec3() * self.f32().cbrt()
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        match items.is_empty() {
+            true => None,
+            false => items.get(self.range(0..items.len()))
+        }
+    }
+
+    /// Index picked with a likelihood proportional to its weight, `None` if no weight is positive
+    pub fn weighted_index(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().filter(|weight| **weight > 0.0).sum();
+        if total <= 0.0 {
+            return None
+        }
+
+        let mut target = self.f32() * total;
+        let mut last = None;
+        for (index, weight) in weights.iter().enumerate().filter(|(_, weight)| **weight > 0.0) {
+            if target < *weight {
+                return Some(index)
+            }
+            target -= weight;
+            last = Some(index);
+        }
+        // Rounding can leave a sliver past the last weight
+        last
+    }
+
+    /// Item picked with a likelihood proportional to its weight
+    pub fn weighted_choice<'a, T>(&mut self, items: &'a [(T, f32)]) -> Option<&'a T> {
+        let weights: Vec<f32> = items.iter().map(|(_, weight)| *weight).collect();
+        self.weighted_index(&weights).map(|index| &items[index].0)
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.range(0..=i);
+            items.swap(i, j);
+        }
+    }
+
+    /// Unbiased integer in 0..bound, `bound` must not be zero
+    fn below(&mut self, bound: u64) -> u64 {
+        // Lemire's method, retrying the few values that would skew the result
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let product = self.next_u64() as u128 * bound as u128;
+            if product as u64 >= threshold {
+                return (product >> 64) as u64
+            }
+        }
+    }
+}
+
+/// Ranges `Rng::range` can sample from
+pub trait SampleRange {
+    type Output;
+
+    fn sample(self, rng: &mut Rng) -> Self::Output;
+}
+
+macro_rules! impl_int_range {
+    ($($int:ty => $unsigned:ty),*) => {$(
+        impl SampleRange for Range<$int> {
+            type Output = $int;
+
+            fn sample(self, rng: &mut Rng) -> $int {
+                assert!(self.start < self.end, "empty range");
+                let span = self.end.wrapping_sub(self.start) as $unsigned as u64;
+                self.start.wrapping_add(rng.below(span) as $int)
+            }
+        }
+
+        impl SampleRange for RangeInclusive<$int> {
+            type Output = $int;
+
+            fn sample(self, rng: &mut Rng) -> $int {
+                let (start, end) = self.into_inner();
+                assert!(start <= end, "empty range");
+                let span = end.wrapping_sub(start) as $unsigned as u64;
+                match span.checked_add(1) {
+                    Some(span) => start.wrapping_add(rng.below(span) as $int),
+                    None => rng.next_u64() as $int
+                }
+            }
+        }
+    )*};
+}
+
+impl_int_range!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize, u8 => u8, u16 => u16, u32 => u32, u64 => u64, usize => usize);
+
+macro_rules! impl_float_range {
+    ($($float:ident),*) => {$(
+        impl SampleRange for Range<$float> {
+            type Output = $float;
+
+            fn sample(self, rng: &mut Rng) -> $float {
+                assert!(self.start < self.end, "empty range");
+                let value = self.start + (self.end - self.start) * rng.$float();
+                // Rounding may land on the excluded end
+                match value < self.end {
+                    true => value,
+                    false => self.start
+                }
+            }
+        }
+
+        impl SampleRange for RangeInclusive<$float> {
+            type Output = $float;
+
+            fn sample(self, rng: &mut Rng) -> $float {
+                let (start, end) = self.into_inner();
+                assert!(start <= end, "empty range");
+                (start + (end - start) * rng.$float()).min(end)
+            }
+        }
+    )*};
+}
+
+impl_float_range!(f32, f64);
+
+/// Engine randomness: a world stream plus named streams per system, all derived from one seed.
+/// Separate streams keep systems from shifting each other's numbers when one of them draws more often.
+#[derive(Debug, Clone)]
+pub struct Random {
+    seed: u64,
+    world: Rng,
+    streams: HashMap<String, Rng>,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            world: Rng::new(seed),
+            streams: HashMap::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restarts every stream from a new seed
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+
+    /// Stream for general gameplay
+    pub fn world(&mut self) -> &mut Rng {
+        &mut self.world
+    }
+
+    /// Stream for one system, created from the seed and the name on first use
+    pub fn stream(&mut self, name: &str) -> &mut Rng {
+        let seed = self.seed;
+        self.streams.entry(name.to_string())
+            .or_insert_with(|| Rng::new(seed ^ fnv1a(name.as_bytes())))
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Stable hash for stream names, unlike the std hasher it never changes between runs or versions
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01B3))
+}
\ No newline at end of file
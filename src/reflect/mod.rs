@@ -0,0 +1,302 @@
+use std::{
+    any::{
+        Any,
+        TypeId
+    },
+    collections::HashMap,
+    io
+};
+
+use serde::{
+    de::DeserializeOwned,
+    Serialize
+};
+
+/// Derives `Reflect` for structs with named fields, `#[reflect(skip)]` hides a field
+pub use aspen_engine_derive::Reflect;
+
+/// Runtime access to a type's fields by name, for tools like scene serializers, inspectors and scripting
+/// that work on game types they were not compiled against. Usually derived.
+pub trait Reflect: Any {
+    fn type_info() -> TypeInfo where Self: Sized;
+    fn field(&self, name: &str) -> Option<&dyn Any>;
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Any>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl dyn Reflect {
+    /// Reads a field, `None` if it does not exist or has a different type
+    pub fn get<T: Any>(&self, name: &str) -> Option<&T> {
+        self.field(name)?.downcast_ref()
+    }
+
+    /// Writes a field, returning false if it does not exist or has a different type
+    pub fn set<T: Any>(&mut self, name: &str, value: T) -> bool {
+        match self.field_mut(name).and_then(|field| field.downcast_mut()) {
+            Some(field) => {
+                *field = value;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    /// Full Rust path of the field's type
+    pub type_name: &'static str,
+    pub type_id: TypeId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeInfo {
+    /// Short name, the type can be looked up by it while no other registered type shares it
+    pub name: &'static str,
+    /// Full Rust path, always unique
+    pub type_name: &'static str,
+    pub type_id: TypeId,
+    /// In declaration order
+    pub fields: Vec<FieldInfo>,
+}
+
+impl TypeInfo {
+    pub fn field(&self, name: &str) -> Option<&FieldInfo> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// A registered type with hooks to create and (de)serialize it without knowing it statically
+pub struct Registration {
+    pub info: TypeInfo,
+    default: fn() -> Box<dyn Reflect>,
+    serialize: fn(&dyn Reflect) -> io::Result<String>,
+    deserialize: fn(&str) -> io::Result<Box<dyn Reflect>>,
+}
+
+impl Registration {
+    /// New instance with default values, also where inspectors read field defaults from
+    pub fn default_value(&self) -> Box<dyn Reflect> {
+        (self.default)()
+    }
+
+    /// RON text of a value of this type
+    pub fn serialize(&self, value: &dyn Reflect) -> io::Result<String> {
+        (self.serialize)(value)
+    }
+
+    pub fn deserialize(&self, text: &str) -> io::Result<Box<dyn Reflect>> {
+        (self.deserialize)(text)
+    }
+}
+
+/// Reflection metadata of the game's types, looked up by name or type id
+#[derive(Default)]
+pub struct TypeRegistry {
+    types: Vec<Registration>,
+    by_type_name: HashMap<&'static str, usize>,
+    /// `None` for short names shared by several types, those are only found by their full path
+    by_name: HashMap<&'static str, Option<usize>>,
+    by_id: HashMap<TypeId, usize>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registering a type again replaces it
+    pub fn register<T: Reflect + Default + Serialize + DeserializeOwned>(&mut self) {
+        let registration = Registration {
+            info: T::type_info(),
+            default: || Box::new(T::default()),
+            serialize: serialize::<T>,
+            deserialize: deserialize::<T>,
+        };
+
+        match self.by_id.get(&registration.info.type_id) {
+            Some(&index) => self.types[index] = registration,
+            None => {
+                let index = self.types.len();
+                self.by_name.entry(registration.info.name)
+                    .and_modify(|alias| *alias = None)
+                    .or_insert(Some(index));
+                self.by_type_name.insert(registration.info.type_name, index);
+                self.by_id.insert(registration.info.type_id, index);
+                self.types.push(registration);
+            }
+        }
+    }
+
+    /// Looks a type up by its full path, or by its short name if no other registered type shares it
+    pub fn get(&self, name: &str) -> Option<&Registration> {
+        let index = match self.by_type_name.get(name) {
+            Some(&index) => index,
+            None => (*self.by_name.get(name)?)?
+        };
+        Some(&self.types[index])
+    }
+
+    /// Short names used by more than one registered type, `get` needs the full path for these
+    pub fn ambiguous_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.by_name.iter()
+            .filter(|(_, alias)| alias.is_none())
+            .map(|(&name, _)| name)
+    }
+
+    pub fn get_by_id(&self, type_id: TypeId) -> Option<&Registration> {
+        self.by_id.get(&type_id).map(|&index| &self.types[index])
+    }
+
+    /// Registration of the value's concrete type
+    pub fn get_for(&self, value: &dyn Reflect) -> Option<&Registration> {
+        self.get_by_id(value.as_any().type_id())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Registration> {
+        self.types.iter()
+    }
+}
+
+fn serialize<T: Reflect + Serialize>(value: &dyn Reflect) -> io::Result<String> {
+    let value = value.downcast_ref::<T>()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "value has a different type than the registration"))?;
+    ron::to_string(value).map_err(io::Error::other)
+}
+
+fn deserialize<T: Reflect + DeserializeOwned>(text: &str) -> io::Result<Box<dyn Reflect>> {
+    let value: T = ron::from_str(text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    Ok(Box::new(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    mod player {
+        use super::*;
+
+        #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+        pub struct Health {
+            pub current: f32,
+        }
+
+        impl Reflect for Health {
+            fn type_info() -> TypeInfo {
+                TypeInfo {
+                    name: "Health",
+                    type_name: std::any::type_name::<Self>(),
+                    type_id: TypeId::of::<Self>(),
+                    fields: vec![FieldInfo {
+                        name: "current",
+                        type_name: std::any::type_name::<f32>(),
+                        type_id: TypeId::of::<f32>(),
+                    }],
+                }
+            }
+
+            fn field(&self, name: &str) -> Option<&dyn Any> {
+                match name {
+                    "current" => Some(&self.current),
+                    _ => None
+                }
+            }
+
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn Any> {
+                match name {
+                    "current" => Some(&mut self.current),
+                    _ => None
+                }
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn Any {
+                self
+            }
+        }
+    }
+
+    mod building {
+        use super::*;
+
+        #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+        pub struct Health {
+            pub armor: u32,
+        }
+
+        impl Reflect for Health {
+            fn type_info() -> TypeInfo {
+                TypeInfo {
+                    name: "Health",
+                    type_name: std::any::type_name::<Self>(),
+                    type_id: TypeId::of::<Self>(),
+                    fields: Vec::new(),
+                }
+            }
+
+            fn field(&self, _name: &str) -> Option<&dyn Any> {
+                None
+            }
+
+            fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Any> {
+                None
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn Any {
+                self
+            }
+        }
+    }
+
+    #[test]
+    fn registered_types_round_trip() {
+        let mut registry = TypeRegistry::new();
+        registry.register::<player::Health>();
+
+        let registration = registry.get("Health").unwrap();
+        let mut value = registration.default_value();
+        assert!(value.set("current", 5.0f32));
+        assert!(!value.set("current", 5u32));
+
+        let text = registration.serialize(value.as_ref()).unwrap();
+        let restored = registration.deserialize(&text).unwrap();
+        assert_eq!(restored.downcast_ref::<player::Health>(), Some(&player::Health { current: 5.0 }));
+        assert!(registry.get_for(restored.as_ref()).is_some());
+    }
+
+    #[test]
+    fn shared_short_names_need_the_full_path() {
+        let mut registry = TypeRegistry::new();
+        registry.register::<player::Health>();
+        registry.register::<building::Health>();
+        registry.register::<player::Health>();
+
+        assert!(registry.get("Health").is_none());
+        assert_eq!(registry.ambiguous_names().collect::<Vec<_>>(), vec!["Health"]);
+
+        let player = registry.get(std::any::type_name::<player::Health>()).unwrap();
+        let building = registry.get(std::any::type_name::<building::Health>()).unwrap();
+        assert_eq!(player.info.type_id, TypeId::of::<player::Health>());
+        assert_eq!(building.info.type_id, TypeId::of::<building::Health>());
+        assert_eq!(registry.iter().count(), 2);
+    }
+}
\ No newline at end of file
@@ -1,12 +1,69 @@
+pub mod queue;
 pub mod rendergraph;
+pub mod settings;
+
+mod resolution;
+
+use resolution::ResolutionController;
+use settings::{
+    RenderSettings,
+    SettingsChanges
+};
+
+use crate::math::UVec2;
 
 pub struct Renderer {
-    
+    settings: RenderSettings,
+    pending_changes: SettingsChanges,
+    resolution: ResolutionController,
 }
 
 impl Renderer {
     pub fn new() -> Self {
+        let settings = RenderSettings::default();
         Self {
+            resolution: ResolutionController::new(&settings),
+            settings,
+            pending_changes: SettingsChanges::default(),
         }
     }
+
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    /// Takes effect at the start of the next frame, rebuilding only what the change affects
+    pub fn set_settings(&mut self, settings: RenderSettings) {
+        let settings = settings.validated();
+        self.pending_changes.merge(settings.changes_from(&self.settings));
+        self.settings = settings;
+        self.resolution.reset(&self.settings);
+    }
+
+    /// Scale the scene is rendered at this frame, below `resolution_scale` while dynamic resolution lowers it.
+    /// Render targets are sized for `resolution_scale` and only a viewport of them is used, so changes are cheap.
+    pub fn render_scale(&self) -> f32 {
+        self.resolution.scale()
+    }
+
+    /// Size of the scene's render area for a window of the given size, before upscaling
+    pub fn render_size(&self, window_size: UVec2) -> UVec2 {
+        (window_size.as_vec2() * self.render_scale()).round().as_uvec2().max(UVec2::ONE)
+    }
+
+    /// Reports how long the last frame took to produce, driving dynamic resolution
+    pub(crate) fn frame_finished(&mut self, frame_time: f64) {
+        self.resolution.frame_finished(&self.settings, frame_time);
+    }
+
+    /// Resources that need rebuilding since the last call, consumed by the backend before drawing
+    pub fn take_pending_changes(&mut self) -> SettingsChanges {
+        std::mem::take(&mut self.pending_changes)
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
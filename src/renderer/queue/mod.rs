@@ -0,0 +1,141 @@
+/// Bits of quantized depth in a sort key
+const DEPTH_BITS: u32 = 23;
+const DEPTH_MAX: u64 = (1 << DEPTH_BITS) - 1;
+const TRANSPARENT_BIT: u64 = 1 << 55;
+
+/// 64 bit draw order, smaller keys draw first.
+///
+/// Bits from the top: 8 bits of layer, 1 bit set for transparent draws, then
+/// - opaque: 16 bits pipeline, 16 bits material, 23 bits depth front to back, so state changes are grouped
+/// - transparent: 23 bits depth back to front, 16 bits pipeline, 16 bits material, so blending is correct
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SortKey(pub u64);
+
+impl SortKey {
+    /// `depth` is the view distance normalized to 0..1, e.g. divided by the far plane
+    pub fn opaque(layer: u8, pipeline: u16, material: u16, depth: f32) -> Self {
+        SortKey(
+            (layer as u64) << 56
+                | (pipeline as u64) << 39
+                | (material as u64) << 23
+                | quantize(depth)
+        )
+    }
+
+    pub fn transparent(layer: u8, pipeline: u16, material: u16, depth: f32) -> Self {
+        SortKey(
+            (layer as u64) << 56
+                | TRANSPARENT_BIT
+                | (DEPTH_MAX - quantize(depth)) << 32
+                | (pipeline as u64) << 16
+                | material as u64
+        )
+    }
+
+    pub fn layer(self) -> u8 {
+        (self.0 >> 56) as u8
+    }
+
+    pub fn is_transparent(self) -> bool {
+        self.0 & TRANSPARENT_BIT != 0
+    }
+
+    pub fn pipeline(self) -> u16 {
+        match self.is_transparent() {
+            true => (self.0 >> 16) as u16,
+            false => (self.0 >> 39) as u16
+        }
+    }
+
+    pub fn material(self) -> u16 {
+        match self.is_transparent() {
+            true => self.0 as u16,
+            false => (self.0 >> 23) as u16
+        }
+    }
+}
+
+fn quantize(depth: f32) -> u64 {
+    (depth.clamp(0.0, 1.0) as f64 * DEPTH_MAX as f64) as u64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Submission<T> {
+    pub key: SortKey,
+    pub draw: T,
+}
+
+/// Draws collected during the frame and sorted before recording.
+/// Equal keys keep their submission order, so the result is deterministic.
+#[derive(Debug, Clone)]
+pub struct RenderQueue<T> {
+    submissions: Vec<Submission<T>>,
+    sorted: bool,
+}
+
+impl<T> RenderQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            submissions: Vec::new(),
+            sorted: true,
+        }
+    }
+
+    pub fn submit(&mut self, key: SortKey, draw: T) {
+        self.sorted &= self.submissions.last().is_none_or(|last| last.key <= key);
+        self.submissions.push(Submission { key, draw });
+    }
+
+    pub fn extend(&mut self, submissions: impl IntoIterator<Item = (SortKey, T)>) {
+        for (key, draw) in submissions {
+            self.submit(key, draw);
+        }
+    }
+
+    pub fn sort(&mut self) {
+        if !self.sorted {
+            self.submissions.sort_by_key(|submission| submission.key);
+            self.sorted = true;
+        }
+    }
+
+    /// Submissions in draw order
+    pub fn sorted(&mut self) -> &[Submission<T>] {
+        self.sort();
+        &self.submissions
+    }
+
+    /// Runs of sorted submissions sharing a pipeline, each run needs one pipeline bind
+    pub fn pipeline_runs(&mut self) -> impl Iterator<Item = &[Submission<T>]> {
+        self.sort();
+        self.submissions.chunk_by(|a, b| a.key.pipeline() == b.key.pipeline())
+    }
+
+    /// Submissions in one layer, in draw order
+    pub fn layer(&mut self, layer: u8) -> &[Submission<T>] {
+        self.sort();
+        let start = self.submissions.partition_point(|submission| submission.key.layer() < layer);
+        let end = self.submissions.partition_point(|submission| submission.key.layer() <= layer);
+        &self.submissions[start..end]
+    }
+
+    pub fn len(&self) -> usize {
+        self.submissions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.submissions.is_empty()
+    }
+
+    /// Empties the queue for the next frame, keeping its allocation
+    pub fn clear(&mut self) {
+        self.submissions.clear();
+        self.sorted = true;
+    }
+}
+
+impl<T> Default for RenderQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
\ No newline at end of file
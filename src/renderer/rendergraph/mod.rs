@@ -1,9 +1,365 @@
+use std::ops::{
+    BitOr,
+    BitOrAssign
+};
+
+/// Pipeline stages a resource is accessed in, combinable with `|`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Stages(u32);
+
+impl Stages {
+    pub const NONE: Stages = Stages(0);
+    pub const TRANSFER: Stages = Stages(1 << 0);
+    pub const VERTEX_INPUT: Stages = Stages(1 << 1);
+    pub const VERTEX_SHADER: Stages = Stages(1 << 2);
+    pub const FRAGMENT_SHADER: Stages = Stages(1 << 3);
+    pub const DEPTH_TESTS: Stages = Stages(1 << 4);
+    pub const COLOR_OUTPUT: Stages = Stages(1 << 5);
+    pub const COMPUTE_SHADER: Stages = Stages(1 << 6);
+    pub const DRAW_INDIRECT: Stages = Stages(1 << 7);
+    pub const PRESENT: Stages = Stages(1 << 8);
+
+    pub fn contains(self, other: Stages) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Stages {
+    type Output = Stages;
+
+    fn bitor(self, rhs: Stages) -> Stages {
+        Stages(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Stages {
+    fn bitor_assign(&mut self, rhs: Stages) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Backend independent image layouts, mapped to the API's layouts by the backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageLayout {
+    Undefined,
+    General,
+    ColorAttachment,
+    DepthAttachment,
+    DepthReadOnly,
+    ShaderReadOnly,
+    TransferSrc,
+    TransferDst,
+    Present,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    Image,
+    Buffer,
+}
+
+/// How a pass uses a resource, shader stages are given where the usage could be in several
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    ColorAttachment,
+    DepthAttachment,
+    DepthRead,
+    Sampled(Stages),
+    StorageRead(Stages),
+    StorageWrite(Stages),
+    Uniform(Stages),
+    VertexBuffer,
+    IndexBuffer,
+    IndirectBuffer,
+    TransferSrc,
+    TransferDst,
+    Present,
+}
+
+/// A single access, what the resource has to be synchronized to before a pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Access {
+    pub stages: Stages,
+    pub write: bool,
+    /// `None` for buffers
+    pub layout: Option<ImageLayout>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResourceId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PassId(u32);
+
+/// Synchronization the backend records before a pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Barrier {
+    pub resource: ResourceId,
+    /// Stages of earlier accesses that have to finish, empty for the first use of a resource
+    pub src_stages: Stages,
+    /// Whether the earlier accesses wrote, making their writes visible is needed
+    pub src_write: bool,
+    pub dst: Access,
+    /// Old and new layout for images whose layout changes
+    pub layout_transition: Option<(ImageLayout, ImageLayout)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarrierIssue {
+    /// The pass needs a barrier on the resource that was not written by hand
+    Missing { pass: PassId, resource: ResourceId },
+    /// A hand written barrier that no hazard or layout change requires
+    Redundant { pass: PassId, resource: ResourceId },
+}
+
+struct ResourceDesc {
+    name: String,
+    kind: ResourceKind,
+    initial_layout: ImageLayout,
+}
+
+struct PassDesc {
+    name: String,
+    usages: Vec<(ResourceId, Usage)>,
+}
+
+pub struct CompiledPass {
+    pub id: PassId,
+    pub name: String,
+    pub barriers: Vec<Barrier>,
+}
+
+/// Passes in submission order with the barriers to record before each
+pub struct CompiledGraph {
+    pub passes: Vec<CompiledPass>,
+    /// Layout each image is left in after the last pass, indexed like the graph's resources
+    pub final_layouts: Vec<Option<ImageLayout>>,
+}
+
+/// Passes and the resources they use. Barriers and layout transitions are inferred from the
+/// declared usages, so passes never synchronize by hand.
+#[derive(Default)]
 pub struct RenderGraph {
+    resources: Vec<ResourceDesc>,
+    passes: Vec<PassDesc>,
+}
 
+#[derive(Default, Clone, Copy)]
+struct ResourceState {
+    layout: Option<ImageLayout>,
+    write_stages: Stages,
+    /// Reads since the last write, a following write has to wait for them
+    read_stages: Stages,
+    /// Stages the last write has already been made visible to
+    visible_stages: Stages,
+}
+
+impl Usage {
+    pub fn access(&self, kind: ResourceKind) -> Access {
+        let (stages, write, layout) = match *self {
+            Usage::ColorAttachment => (Stages::COLOR_OUTPUT, true, ImageLayout::ColorAttachment),
+            Usage::DepthAttachment => (Stages::DEPTH_TESTS, true, ImageLayout::DepthAttachment),
+            Usage::DepthRead => (Stages::DEPTH_TESTS, false, ImageLayout::DepthReadOnly),
+            Usage::Sampled(stages) => (stages, false, ImageLayout::ShaderReadOnly),
+            Usage::StorageRead(stages) => (stages, false, ImageLayout::General),
+            Usage::StorageWrite(stages) => (stages, true, ImageLayout::General),
+            Usage::Uniform(stages) => (stages, false, ImageLayout::General),
+            Usage::VertexBuffer | Usage::IndexBuffer => (Stages::VERTEX_INPUT, false, ImageLayout::General),
+            Usage::IndirectBuffer => (Stages::DRAW_INDIRECT, false, ImageLayout::General),
+            Usage::TransferSrc => (Stages::TRANSFER, false, ImageLayout::TransferSrc),
+            Usage::TransferDst => (Stages::TRANSFER, true, ImageLayout::TransferDst),
+            Usage::Present => (Stages::PRESENT, false, ImageLayout::Present)
+        };
+
+        Access {
+            stages,
+            write,
+            layout: match kind {
+                ResourceKind::Image => Some(layout),
+                ResourceKind::Buffer => None
+            },
+        }
+    }
 }
 
 impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An image created by the graph, its previous contents are undefined
+    pub fn add_image(&mut self, name: &str) -> ResourceId {
+        self.add_resource(name, ResourceKind::Image, ImageLayout::Undefined)
+    }
+
+    /// An image that lives outside the graph, such as the swapchain image, currently in `layout`
+    pub fn import_image(&mut self, name: &str, layout: ImageLayout) -> ResourceId {
+        self.add_resource(name, ResourceKind::Image, layout)
+    }
+
+    pub fn add_buffer(&mut self, name: &str) -> ResourceId {
+        self.add_resource(name, ResourceKind::Buffer, ImageLayout::Undefined)
+    }
+
+    /// Passes run in the order they are added
+    pub fn add_pass(&mut self, name: &str, usages: &[(ResourceId, Usage)]) -> PassId {
+        self.passes.push(PassDesc {
+            name: name.to_string(),
+            usages: usages.to_vec(),
+        });
+        PassId(self.passes.len() as u32 - 1)
+    }
+
+    pub fn resource_name(&self, resource: ResourceId) -> &str {
+        &self.resources[resource.0 as usize].name
+    }
+
+    pub fn pass_name(&self, pass: PassId) -> &str {
+        &self.passes[pass.0 as usize].name
+    }
+
+    pub fn compile(&self) -> CompiledGraph {
+        let mut states: Vec<ResourceState> = self.resources.iter()
+            .map(|resource| ResourceState {
+                layout: match resource.kind {
+                    ResourceKind::Image => Some(resource.initial_layout),
+                    ResourceKind::Buffer => None
+                },
+                ..Default::default()
+            })
+            .collect();
+
+        let passes = self.passes.iter().enumerate().map(|(index, pass)| {
+            let barriers = self.pass_accesses(pass).into_iter()
+                .filter_map(|(resource, access)| transition(&mut states[resource.0 as usize], resource, access))
+                .collect();
 
+            CompiledPass {
+                id: PassId(index as u32),
+                name: pass.name.clone(),
+                barriers,
+            }
+        }).collect();
+
+        CompiledGraph {
+            passes,
+            final_layouts: states.iter().map(|state| state.layout).collect(),
+        }
+    }
+
+    /// Compares hand written barriers, given as the passes and resources they are recorded for,
+    /// against the inferred ones
+    pub fn validate(&self, manual: &[(PassId, ResourceId)]) -> Vec<BarrierIssue> {
+        let compiled = self.compile();
+        let mut issues = Vec::new();
+
+        for pass in compiled.passes.iter() {
+            for barrier in pass.barriers.iter() {
+                if !manual.contains(&(pass.id, barrier.resource)) {
+                    issues.push(BarrierIssue::Missing { pass: pass.id, resource: barrier.resource });
+                }
+            }
+        }
+
+        for &(pass, resource) in manual.iter() {
+            let needed = compiled.passes.get(pass.0 as usize)
+                .is_some_and(|compiled| compiled.barriers.iter().any(|barrier| barrier.resource == resource));
+            if !needed {
+                issues.push(BarrierIssue::Redundant { pass, resource });
+            }
+        }
+
+        issues
+    }
+
+    fn add_resource(&mut self, name: &str, kind: ResourceKind, initial_layout: ImageLayout) -> ResourceId {
+        self.resources.push(ResourceDesc {
+            name: name.to_string(),
+            kind,
+            initial_layout,
+        });
+        ResourceId(self.resources.len() as u32 - 1)
+    }
+
+    /// One access per resource, usages of the same resource within a pass are merged.
+    /// Mixing layouts within a pass falls back to the general layout.
+    fn pass_accesses(&self, pass: &PassDesc) -> Vec<(ResourceId, Access)> {
+        let mut accesses: Vec<(ResourceId, Access)> = Vec::new();
+        for &(resource, usage) in pass.usages.iter() {
+            let access = usage.access(self.resources[resource.0 as usize].kind);
+            match accesses.iter_mut().find(|(existing, _)| *existing == resource) {
+                Some((_, merged)) => {
+                    merged.stages |= access.stages;
+                    merged.write |= access.write;
+                    if merged.layout != access.layout {
+                        merged.layout = Some(ImageLayout::General);
+                    }
+                },
+                None => accesses.push((resource, access))
+            }
+        }
+
+        accesses
+    }
+}
+
+/// Updates the resource state for the access, returning the barrier it needs if any
+fn transition(state: &mut ResourceState, resource: ResourceId, access: Access) -> Option<Barrier> {
+    let layout_transition = match (state.layout, access.layout) {
+        (Some(old), Some(new)) if old != new => Some((old, new)),
+        _ => None
+    };
+
+    let barrier = match (access.write, layout_transition) {
+        // Writes and layout changes wait for every earlier access
+        (true, _) | (false, Some(_)) => {
+            let src_stages = state.write_stages | state.read_stages;
+            let needed = !src_stages.is_empty() || layout_transition.is_some();
+            needed.then_some(Barrier {
+                resource,
+                src_stages,
+                src_write: !state.write_stages.is_empty(),
+                dst: access,
+                layout_transition,
+            })
+        },
+        // Reads only wait for the last write, once per stage
+        (false, None) => {
+            let unsynced = !state.write_stages.is_empty() && !state.visible_stages.contains(access.stages);
+            unsynced.then_some(Barrier {
+                resource,
+                src_stages: state.write_stages,
+                src_write: true,
+                dst: access,
+                layout_transition: None,
+            })
+        }
+    };
+
+    match (access.write, layout_transition) {
+        (true, _) => {
+            state.write_stages = access.stages;
+            state.read_stages = Stages::NONE;
+            state.visible_stages = Stages::NONE;
+        },
+        // A layout transition rewrites the image, reads in other stages have to wait for it like for a write
+        (false, Some(_)) => {
+            state.write_stages = access.stages;
+            state.read_stages = access.stages;
+            state.visible_stages = access.stages;
+        },
+        (false, None) => {
+            state.read_stages |= access.stages;
+            state.visible_stages |= access.stages;
+        }
+    }
+
+    state.layout = access.layout;
+    barrier
 }
 
 pub struct SubGraph {
@@ -12,4 +368,77 @@ pub struct SubGraph {
 
 impl SubGraph {
     
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn barrier(compiled: &CompiledGraph, pass: PassId, resource: ResourceId) -> Option<Barrier> {
+        compiled.passes[pass.0 as usize].barriers.iter().copied().find(|barrier| barrier.resource == resource)
+    }
+
+    #[test]
+    fn reads_wait_for_the_last_write_once_per_stage() {
+        let mut graph = RenderGraph::new();
+        let buffer = graph.add_buffer("particles");
+        let write = graph.add_pass("simulate", &[(buffer, Usage::StorageWrite(Stages::COMPUTE_SHADER))]);
+        let first = graph.add_pass("draw", &[(buffer, Usage::VertexBuffer)]);
+        let second = graph.add_pass("draw again", &[(buffer, Usage::VertexBuffer)]);
+        let compiled = graph.compile();
+
+        assert!(barrier(&compiled, write, buffer).is_none());
+        let read = barrier(&compiled, first, buffer).unwrap();
+        assert_eq!((read.src_stages, read.src_write), (Stages::COMPUTE_SHADER, true));
+        assert!(barrier(&compiled, second, buffer).is_none());
+    }
+
+    #[test]
+    fn reads_in_other_stages_wait_for_a_layout_transition() {
+        let mut graph = RenderGraph::new();
+        let image = graph.add_image("shadow map");
+        graph.add_pass("render", &[(image, Usage::DepthAttachment)]);
+        let fragment = graph.add_pass("light", &[(image, Usage::Sampled(Stages::FRAGMENT_SHADER))]);
+        let compute = graph.add_pass("blur", &[(image, Usage::Sampled(Stages::COMPUTE_SHADER))]);
+        let fragment_again = graph.add_pass("light again", &[(image, Usage::Sampled(Stages::FRAGMENT_SHADER))]);
+        let compiled = graph.compile();
+
+        let transition = barrier(&compiled, fragment, image).unwrap();
+        assert_eq!(transition.layout_transition, Some((ImageLayout::DepthAttachment, ImageLayout::ShaderReadOnly)));
+
+        let after = barrier(&compiled, compute, image).unwrap();
+        assert_eq!((after.src_stages, after.src_write, after.layout_transition), (Stages::FRAGMENT_SHADER, true, None));
+        assert!(barrier(&compiled, fragment_again, image).is_none());
+    }
+
+    #[test]
+    fn writes_wait_for_reads_after_a_transition() {
+        let mut graph = RenderGraph::new();
+        let image = graph.import_image("swapchain", ImageLayout::Present);
+        graph.add_pass("copy out", &[(image, Usage::TransferSrc)]);
+        let write = graph.add_pass("clear", &[(image, Usage::TransferDst)]);
+        let compiled = graph.compile();
+
+        let barrier = barrier(&compiled, write, image).unwrap();
+        assert_eq!(barrier.src_stages, Stages::TRANSFER);
+        assert_eq!(barrier.layout_transition, Some((ImageLayout::TransferSrc, ImageLayout::TransferDst)));
+        assert_eq!(compiled.final_layouts, vec![Some(ImageLayout::TransferDst)]);
+    }
+
+    #[test]
+    fn validate_reports_missing_and_redundant_barriers() {
+        let mut graph = RenderGraph::new();
+        let image = graph.add_image("color");
+        let draw = graph.add_pass("draw", &[(image, Usage::ColorAttachment)]);
+        let post = graph.add_pass("post", &[(image, Usage::Sampled(Stages::FRAGMENT_SHADER))]);
+
+        let issues = graph.validate(&[(post, image), (draw, ResourceId(0)), (PassId(7), image)]);
+        assert_eq!(issues, vec![BarrierIssue::Redundant { pass: PassId(7), resource: image }]);
+
+        let issues = graph.validate(&[]);
+        assert_eq!(issues, vec![
+            BarrierIssue::Missing { pass: draw, resource: image },
+            BarrierIssue::Missing { pass: post, resource: image }
+        ]);
+    }
 }
\ No newline at end of file
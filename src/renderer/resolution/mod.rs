@@ -0,0 +1,73 @@
+use super::settings::RenderSettings;
+
+/// Weight of the newest frame in the smoothed frame time
+const SMOOTHING: f64 = 0.1;
+/// Frames to wait after a change so its effect shows up in the smoothed time
+const COOLDOWN_FRAMES: u32 = 30;
+/// Scaling down starts above this fraction of the frame budget, scaling up below the lower one
+const OVER_BUDGET: f64 = 1.05;
+const UNDER_BUDGET: f64 = 0.85;
+/// Largest relative change of the scale in one adjustment
+const MAX_STEP: f64 = 0.1;
+
+/// Picks the render scale each frame, following the frame time in dynamic resolution mode
+pub(crate) struct ResolutionController {
+    scale: f32,
+    smoothed_frame_time: Option<f64>,
+    cooldown: u32,
+}
+
+impl ResolutionController {
+    pub(crate) fn new(settings: &RenderSettings) -> Self {
+        Self {
+            scale: settings.resolution_scale,
+            smoothed_frame_time: None,
+            cooldown: 0,
+        }
+    }
+
+    pub(crate) fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Called when settings change, restarting from the configured scale
+    pub(crate) fn reset(&mut self, settings: &RenderSettings) {
+        *self = Self::new(settings);
+    }
+
+    /// Feeds the time the last frame took to produce
+    pub(crate) fn frame_finished(&mut self, settings: &RenderSettings, frame_time: f64) {
+        let Some(dynamic) = settings.dynamic_resolution else {
+            return
+        };
+
+        let smoothed = match self.smoothed_frame_time {
+            Some(smoothed) => smoothed + (frame_time - smoothed) * SMOOTHING,
+            None => frame_time
+        };
+        self.smoothed_frame_time = Some(smoothed);
+
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return
+        }
+
+        let budget = 1.0 / dynamic.target_fps;
+        let load = smoothed / budget;
+        if (UNDER_BUDGET..=OVER_BUDGET).contains(&load) || smoothed <= 0.0 {
+            return
+        }
+
+        // Frame cost roughly follows the pixel count, which grows with the square of the scale
+        let factor = (1.0 / load).sqrt().clamp(1.0 - MAX_STEP, 1.0 + MAX_STEP);
+        let scale = (self.scale as f64 * factor) as f32;
+        let scale = scale.clamp(dynamic.min_scale, settings.resolution_scale);
+
+        if scale == self.scale {
+            return
+        }
+
+        self.scale = scale;
+        self.cooldown = COOLDOWN_FRAMES;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,262 @@
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+/// Used in place of a non-finite `DynamicResolution::target_fps`
+const DEFAULT_TARGET_FPS: f64 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Quality {
+    Off,
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+/// How the scene is scaled up to the window when rendered below native resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Upscaling {
+    Bilinear,
+    /// Edge adaptive upsampling followed by a sharpening pass, in the style of FSR 1
+    Sharpened,
+}
+
+/// Lowers the resolution scale under load to hold a frame rate
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DynamicResolution {
+    pub target_fps: f64,
+    /// The scale never drops below this, `resolution_scale` is the upper limit
+    pub min_scale: f32,
+}
+
+/// User facing graphics options, changeable at runtime through `Renderer::set_settings`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    /// Scale of the internal render resolution relative to the window
+    pub resolution_scale: f32,
+    pub upscaling: Upscaling,
+    /// 0 to 1, only used by `Upscaling::Sharpened`
+    pub sharpness: f32,
+    /// `None` keeps the scale fixed
+    pub dynamic_resolution: Option<DynamicResolution>,
+    pub vsync: bool,
+    /// Preferred swapchain image count, 2 for double and 3 for triple buffering.
+    /// The backend clamps it to what the surface supports.
+    pub swapchain_images: u32,
+    /// Frames the CPU may record ahead of the GPU, 1 gives the lowest input latency and more gives throughput
+    pub max_frame_latency: u32,
+    /// Outputs HDR10 where the surface supports it, the final pass then PQ encodes instead of applying gamma
+    pub hdr: bool,
+    /// Nits that SDR white and UI are shown at in HDR output
+    pub paper_white: f32,
+    /// The display's peak brightness in nits, highlights are tone mapped to it in HDR output
+    pub peak_brightness: f32,
+    /// 1 disables multisampling
+    pub msaa_samples: u32,
+    pub shadow_quality: Quality,
+    /// 1 disables anisotropic filtering
+    pub anisotropy: u32,
+    pub texture_quality: Quality,
+}
+
+/// What has to be rebuilt for a settings change to take effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SettingsChanges {
+    pub swapchain: bool,
+    pub render_targets: bool,
+    pub pipelines: bool,
+    pub shadow_maps: bool,
+    pub samplers: bool,
+    pub textures: bool,
+}
+
+impl RenderSettings {
+    pub fn preset(preset: QualityPreset) -> Self {
+        let mut settings = Self::default();
+        settings.apply_preset(preset);
+        settings
+    }
+
+    /// Sets every quality option from the preset, leaving vsync as it is
+    pub fn apply_preset(&mut self, preset: QualityPreset) {
+        let (resolution_scale, msaa_samples, shadow_quality, anisotropy, texture_quality) = match preset {
+            QualityPreset::Low => (0.75, 1, Quality::Low, 1, Quality::Low),
+            QualityPreset::Medium => (1.0, 2, Quality::Medium, 4, Quality::Medium),
+            QualityPreset::High => (1.0, 4, Quality::High, 8, Quality::High),
+            QualityPreset::Ultra => (1.0, 8, Quality::Ultra, 16, Quality::Ultra)
+        };
+
+        self.resolution_scale = resolution_scale;
+        self.msaa_samples = msaa_samples;
+        self.shadow_quality = shadow_quality;
+        self.anisotropy = anisotropy;
+        self.texture_quality = texture_quality;
+    }
+
+    /// Clamps values into ranges the renderer accepts
+    pub fn validated(mut self) -> Self {
+        let defaults = Self::default();
+        self.resolution_scale = finite_or(self.resolution_scale, defaults.resolution_scale).clamp(0.25, 2.0);
+        self.sharpness = finite_or(self.sharpness, defaults.sharpness).clamp(0.0, 1.0);
+        if let Some(dynamic) = &mut self.dynamic_resolution {
+            dynamic.target_fps = match dynamic.target_fps.is_finite() {
+                true => dynamic.target_fps.max(1.0),
+                false => DEFAULT_TARGET_FPS
+            };
+            dynamic.min_scale = finite_or(dynamic.min_scale, 0.25).clamp(0.25, self.resolution_scale);
+        }
+        self.msaa_samples = match self.msaa_samples {
+            0 | 1 => 1,
+            samples => samples.min(8).next_power_of_two()
+        };
+        self.anisotropy = self.anisotropy.clamp(1, 16);
+        self.swapchain_images = self.swapchain_images.clamp(2, 4);
+        self.max_frame_latency = self.max_frame_latency.clamp(1, 3);
+        self.paper_white = finite_or(self.paper_white, defaults.paper_white).clamp(80.0, 500.0);
+        self.peak_brightness = finite_or(self.peak_brightness, defaults.peak_brightness).clamp(self.paper_white, 10000.0);
+        self
+    }
+
+    pub fn changes_from(&self, old: &RenderSettings) -> SettingsChanges {
+        let scale_changed = self.resolution_scale != old.resolution_scale;
+        let msaa_changed = self.msaa_samples != old.msaa_samples;
+
+        SettingsChanges {
+            swapchain: self.vsync != old.vsync
+                || self.swapchain_images != old.swapchain_images
+                || self.max_frame_latency != old.max_frame_latency
+                || self.hdr != old.hdr,
+            render_targets: scale_changed || msaa_changed,
+            pipelines: msaa_changed || self.upscaling != old.upscaling || self.hdr != old.hdr,
+            shadow_maps: self.shadow_quality != old.shadow_quality,
+            samplers: self.anisotropy != old.anisotropy,
+            textures: self.texture_quality != old.texture_quality,
+        }
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            resolution_scale: 1.0,
+            upscaling: Upscaling::Sharpened,
+            sharpness: 0.5,
+            dynamic_resolution: None,
+            vsync: true,
+            swapchain_images: 3,
+            max_frame_latency: 2,
+            hdr: false,
+            paper_white: 200.0,
+            peak_brightness: 1000.0,
+            msaa_samples: 1,
+            shadow_quality: Quality::Medium,
+            anisotropy: 1,
+            texture_quality: Quality::High,
+        }
+    }
+}
+
+/// NaN and infinity would slip through or panic in `clamp`, they fall back to the default
+fn finite_or(value: f32, default: f32) -> f32 {
+    match value.is_finite() {
+        true => value,
+        false => default
+    }
+}
+
+impl SettingsChanges {
+    pub fn any(&self) -> bool {
+        self.swapchain || self.render_targets || self.pipelines || self.shadow_maps || self.samplers || self.textures
+    }
+
+    pub fn merge(&mut self, other: SettingsChanges) {
+        self.swapchain |= other.swapchain;
+        self.render_targets |= other.render_targets;
+        self.pipelines |= other.pipelines;
+        self.shadow_maps |= other.shadow_maps;
+        self.samplers |= other.samplers;
+        self.textures |= other.textures;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_values_fall_back_to_defaults() {
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let settings = RenderSettings {
+                resolution_scale: value,
+                sharpness: value,
+                dynamic_resolution: Some(DynamicResolution {
+                    target_fps: value as f64,
+                    min_scale: value,
+                }),
+                paper_white: value,
+                peak_brightness: value,
+                ..RenderSettings::default()
+            }.validated();
+
+            let defaults = RenderSettings::default();
+            assert_eq!(settings.resolution_scale, defaults.resolution_scale);
+            assert_eq!(settings.sharpness, defaults.sharpness);
+            assert_eq!(settings.paper_white, defaults.paper_white);
+            assert_eq!(settings.peak_brightness, defaults.peak_brightness);
+            assert_eq!(settings.dynamic_resolution, Some(DynamicResolution {
+                target_fps: DEFAULT_TARGET_FPS,
+                min_scale: 0.25,
+            }));
+        }
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let settings = RenderSettings {
+            resolution_scale: 5.0,
+            msaa_samples: 3,
+            anisotropy: 0,
+            swapchain_images: 9,
+            max_frame_latency: 0,
+            paper_white: 400.0,
+            peak_brightness: 100.0,
+            ..RenderSettings::default()
+        }.validated();
+
+        assert_eq!(settings.resolution_scale, 2.0);
+        assert_eq!(settings.msaa_samples, 4);
+        assert_eq!(settings.anisotropy, 1);
+        assert_eq!(settings.swapchain_images, 4);
+        assert_eq!(settings.max_frame_latency, 1);
+        assert_eq!(settings.peak_brightness, 400.0);
+    }
+
+    #[test]
+    fn swapchain_settings_rebuild_the_swapchain() {
+        let old = RenderSettings::default();
+        for new in [RenderSettings { swapchain_images: 2, ..old }, RenderSettings { max_frame_latency: 1, ..old }] {
+            let changes = new.changes_from(&old);
+            assert!(changes.swapchain);
+            assert!(!changes.render_targets && !changes.pipelines);
+        }
+    }
+
+    #[test]
+    fn hdr_rebuilds_the_swapchain_and_pipelines() {
+        let old = RenderSettings::default();
+        let changes = RenderSettings { hdr: true, ..old }.changes_from(&old);
+        assert!(changes.swapchain && changes.pipelines);
+        assert!(!RenderSettings { paper_white: 300.0, ..old }.changes_from(&old).any());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,107 @@
+use std::{
+    fs::File,
+    io::{
+        self,
+        BufReader,
+        BufWriter
+    },
+    path::Path
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    input::TimedInput,
+    timing::UpdateTimes
+};
+
+/// Bumped whenever the recording layout changes, old recordings are rejected on load
+pub const RECORDING_VERSION: u32 = 5;
+
+/// Everything needed to play a session back with the same fixed step sequence:
+/// the seed, and for every frame its timing and the input applied at its start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+    pub seed: u64,
+    pub frames: Vec<RecordedFrame>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub times: UpdateTimes,
+    pub events: Vec<TimedInput>,
+}
+
+impl Recording {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            version: RECORDING_VERSION,
+            seed,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Number of fixed steps the recording covers
+    pub fn fixed_steps(&self) -> u64 {
+        self.frames.iter().map(|frame| frame.times.fixed_steps).sum()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self).map_err(io::Error::other)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Recording> {
+        let reader = BufReader::new(File::open(path)?);
+        let recording: Recording = bincode::deserialize_from(reader)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        if recording.version != RECORDING_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("recording version {} is not supported, expected {}", recording.version, RECORDING_VERSION)
+            ))
+        }
+
+        Ok(recording)
+    }
+}
+
+pub(crate) enum Replay {
+    Idle,
+    Recording(Recording),
+    Playing { recording: Recording, next: usize },
+}
+
+impl Replay {
+    /// Picks the timing and input for the coming frame, from the recording when playing back,
+    /// and appends the live frame when recording
+    pub(crate) fn next_frame(&mut self, live_times: UpdateTimes, live_events: Vec<TimedInput>) -> (UpdateTimes, Vec<TimedInput>) {
+        match self {
+            Replay::Idle => (live_times, live_events),
+            Replay::Recording(recording) => {
+                recording.frames.push(RecordedFrame {
+                    times: live_times,
+                    events: live_events.clone(),
+                });
+                (live_times, live_events)
+            },
+            Replay::Playing { recording, next } => {
+                match recording.frames.get(*next) {
+                    Some(frame) => {
+                        *next += 1;
+                        (frame.times, frame.events.clone())
+                    },
+                    None => {
+                        *self = Replay::Idle;
+                        (live_times, live_events)
+                    }
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,399 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io,
+    marker::PhantomData,
+    path::{
+        Path,
+        PathBuf
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH
+    }
+};
+
+use serde::{
+    de::{
+        DeserializeOwned,
+        IgnoredAny
+    },
+    Deserialize,
+    Serialize
+};
+
+const BINARY_EXTENSION: &str = "sav";
+const RON_EXTENSION: &str = "ron";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SaveFormat {
+    /// Compact and fast, but unreadable and fragile against changed types without a migration
+    #[default]
+    Binary,
+    /// Human readable, handy while developing
+    Ron,
+}
+
+impl SaveFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            SaveFormat::Binary => BINARY_EXTENSION,
+            SaveFormat::Ron => RON_EXTENSION
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveMeta {
+    /// Version of the save data, compared against `SaveSlots::version` to run migrations
+    pub version: u32,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+    /// Seconds played, from `GameClock::elapsed`
+    pub playtime: f64,
+    /// Shown in save slot lists, such as the level name
+    pub description: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile<D> {
+    meta: SaveMeta,
+    data: D,
+}
+
+#[derive(Clone)]
+enum PayloadData {
+    Binary(Vec<u8>),
+    /// The whole file, kept as text since `ron::Value` loses enum variant names
+    Ron(String),
+}
+
+/// Save data in its stored form, read and rewritten by migrations
+#[derive(Clone)]
+pub struct Payload {
+    data: PayloadData,
+}
+
+impl Payload {
+    pub fn read<T: DeserializeOwned>(&self) -> io::Result<T> {
+        match &self.data {
+            PayloadData::Binary(bytes) => bincode::deserialize(bytes).map_err(invalid_data),
+            PayloadData::Ron(text) => ron::from_str::<SaveFile<T>>(text).map(|file| file.data).map_err(invalid_data)
+        }
+    }
+
+    /// Replaces the data, keeping the format
+    pub fn write<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        self.data = match &self.data {
+            PayloadData::Binary(_) => PayloadData::Binary(bincode::serialize(value).map_err(invalid_data)?),
+            PayloadData::Ron(text) => {
+                let file: SaveFile<IgnoredAny> = ron::from_str(text).map_err(invalid_data)?;
+                PayloadData::Ron(to_ron(&SaveFile {
+                    meta: file.meta,
+                    data: value,
+                })?)
+            }
+        };
+        Ok(())
+    }
+}
+
+type Migration = Box<dyn Fn(&mut Payload) -> io::Result<()> + Send + Sync>;
+
+/// Named save slots holding a `T` each, stored one file per slot.
+/// Old saves are brought up to date by the migrations registered for the versions in between.
+pub struct SaveSlots<T> {
+    dir: PathBuf,
+    version: u32,
+    format: SaveFormat,
+    migrations: BTreeMap<u32, Migration>,
+    _data: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> SaveSlots<T> {
+    /// Slots in the platform's data directory, e.g. `~/.local/share/<game>/saves` on Linux
+    pub fn new(game: &str, version: u32) -> io::Result<Self> {
+        let dir = dirs::data_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no save directory on this platform"))?
+            .join(game)
+            .join("saves");
+        Ok(Self::in_dir(dir, version))
+    }
+
+    pub fn in_dir(dir: impl Into<PathBuf>, version: u32) -> Self {
+        Self {
+            dir: dir.into(),
+            version,
+            format: SaveFormat::default(),
+            migrations: BTreeMap::new(),
+            _data: PhantomData,
+        }
+    }
+
+    /// Format new saves are written in, saves in the other format still load
+    pub fn with_format(mut self, format: SaveFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Registers the step that upgrades data saved at `from` to `from + 1`
+    pub fn with_migration<F>(mut self, from: u32, migration: F) -> Self
+    where
+        F: Fn(&mut Payload) -> io::Result<()> + Send + Sync + 'static
+    {
+        self.migrations.insert(from, Box::new(migration));
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn save(&self, slot: &str, data: &T, playtime: f64, description: &str) -> io::Result<SaveMeta> {
+        validate_slot(slot)?;
+        fs::create_dir_all(&self.dir)?;
+
+        let meta = SaveMeta {
+            version: self.version,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or(0),
+            playtime,
+            description: description.to_string(),
+        };
+
+        let bytes = match self.format {
+            SaveFormat::Binary => {
+                let file = SaveFile {
+                    meta: meta.clone(),
+                    data: bincode::serialize(data).map_err(invalid_data)?,
+                };
+                bincode::serialize(&file).map_err(invalid_data)?
+            },
+            SaveFormat::Ron => {
+                to_ron(&SaveFile {
+                    meta: meta.clone(),
+                    data,
+                })?.into_bytes()
+            }
+        };
+
+        // Written next to the slot first so a crash mid-write never destroys the previous save
+        let path = self.path(slot, self.format);
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, bytes)?;
+        fs::rename(&temp, &path)?;
+
+        let other = match self.format {
+            SaveFormat::Binary => SaveFormat::Ron,
+            SaveFormat::Ron => SaveFormat::Binary
+        };
+        let _ = fs::remove_file(self.path(slot, other));
+
+        Ok(meta)
+    }
+
+    /// Loads a slot, migrating it if it was saved by an older version
+    pub fn load(&self, slot: &str) -> io::Result<(SaveMeta, T)> {
+        let (mut meta, mut payload) = self.read(slot)?;
+
+        if meta.version > self.version {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "save is from a newer version"))
+        }
+        while meta.version < self.version {
+            let migration = self.migrations.get(&meta.version).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("no migration from save version {}", meta.version))
+            })?;
+            migration(&mut payload)?;
+            meta.version += 1;
+        }
+
+        Ok((meta, payload.read()?))
+    }
+
+    /// Metadata of a slot without migrating its data
+    pub fn meta(&self, slot: &str) -> io::Result<SaveMeta> {
+        self.read(slot).map(|(meta, _)| meta)
+    }
+
+    /// Every slot with its metadata, most recent first. Unreadable files are skipped.
+    pub fn slots(&self) -> io::Result<Vec<(String, SaveMeta)>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error)
+        };
+
+        let mut slots = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let is_save = path.extension()
+                .is_some_and(|extension| extension == BINARY_EXTENSION || extension == RON_EXTENSION);
+            let Some(slot) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue
+            };
+
+            if is_save && !slots.iter().any(|(existing, _)| existing == slot) {
+                if let Ok(meta) = self.meta(slot) {
+                    slots.push((slot.to_string(), meta));
+                }
+            }
+        }
+
+        slots.sort_by_key(|(_, meta)| std::cmp::Reverse(meta.timestamp));
+        Ok(slots)
+    }
+
+    pub fn exists(&self, slot: &str) -> bool {
+        self.find(slot).is_some()
+    }
+
+    pub fn delete(&self, slot: &str) -> io::Result<()> {
+        validate_slot(slot)?;
+        for format in [SaveFormat::Binary, SaveFormat::Ron] {
+            match fs::remove_file(self.path(slot, format)) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                _ => ()
+            }
+        }
+        Ok(())
+    }
+
+    fn path(&self, slot: &str, format: SaveFormat) -> PathBuf {
+        self.dir.join(format!("{}.{}", slot, format.extension()))
+    }
+
+    fn find(&self, slot: &str) -> Option<(PathBuf, SaveFormat)> {
+        validate_slot(slot).ok()?;
+        [self.format, SaveFormat::Binary, SaveFormat::Ron].into_iter()
+            .map(|format| (self.path(slot, format), format))
+            .find(|(path, _)| path.is_file())
+    }
+
+    fn read(&self, slot: &str) -> io::Result<(SaveMeta, Payload)> {
+        validate_slot(slot)?;
+        let (path, format) = self.find(slot)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no save in slot {}", slot)))?;
+
+        match format {
+            SaveFormat::Binary => {
+                let file: SaveFile<Vec<u8>> = bincode::deserialize(&fs::read(path)?).map_err(invalid_data)?;
+                Ok((file.meta, Payload { data: PayloadData::Binary(file.data) }))
+            },
+            SaveFormat::Ron => {
+                let text = fs::read_to_string(path)?;
+                let file: SaveFile<IgnoredAny> = ron::from_str(&text).map_err(invalid_data)?;
+                Ok((file.meta, Payload { data: PayloadData::Ron(text) }))
+            }
+        }
+    }
+}
+
+fn validate_slot(slot: &str) -> io::Result<()> {
+    let valid = !slot.is_empty() && slot.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ');
+    match valid {
+        true => Ok(()),
+        false => Err(io::Error::new(io::ErrorKind::InvalidInput, "slot names may only contain letters, digits, spaces, '-' and '_'"))
+    }
+}
+
+fn to_ron<T: Serialize>(value: &T) -> io::Result<String> {
+    ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()).map_err(invalid_data)
+}
+
+fn invalid_data(error: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        A,
+        B(u32),
+        C { amount: f32 },
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Data {
+        name: String,
+        kinds: Vec<Kind>,
+        selected: Option<Kind>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct OldData {
+        name: String,
+    }
+
+    fn data() -> Data {
+        Data {
+            name: "hero".to_string(),
+            kinds: vec![Kind::A, Kind::B(3), Kind::C { amount: 0.5 }],
+            selected: Some(Kind::B(3)),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aspen-save-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn round_trips_in_both_formats() {
+        for format in [SaveFormat::Binary, SaveFormat::Ron] {
+            let dir = temp_dir(&format!("{:?}", format));
+            let slots = SaveSlots::<Data>::in_dir(&dir, 1).with_format(format);
+
+            slots.save("slot 1", &data(), 12.5, "level 1").unwrap();
+            let (meta, loaded) = slots.load("slot 1").unwrap();
+            assert_eq!(loaded, data());
+            assert_eq!(meta.playtime, 12.5);
+            assert_eq!(slots.slots().unwrap().len(), 1);
+
+            slots.delete("slot 1").unwrap();
+            assert!(!slots.exists("slot 1"));
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn migrations_upgrade_old_saves() {
+        for format in [SaveFormat::Binary, SaveFormat::Ron] {
+            let dir = temp_dir(&format!("migrate-{:?}", format));
+            SaveSlots::<OldData>::in_dir(&dir, 1)
+                .with_format(format)
+                .save("old", &OldData { name: "hero".to_string() }, 0.0, "")
+                .unwrap();
+
+            let slots = SaveSlots::<Data>::in_dir(&dir, 2).with_migration(1, |payload| {
+                let old: OldData = payload.read()?;
+                payload.write(&Data {
+                    name: old.name,
+                    kinds: vec![Kind::B(3)],
+                    selected: None,
+                })
+            });
+
+            let (meta, loaded) = slots.load("old").unwrap();
+            assert_eq!(meta.version, 2);
+            assert_eq!(loaded.kinds, vec![Kind::B(3)]);
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_slot_names() {
+        let slots = SaveSlots::<Data>::in_dir(temp_dir("invalid"), 1);
+        assert!(slots.save("../escape", &data(), 0.0, "").is_err());
+        assert!(slots.load("").is_err());
+    }
+}
\ No newline at end of file
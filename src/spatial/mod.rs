@@ -0,0 +1,446 @@
+use std::{
+    collections::{
+        HashMap,
+        HashSet
+    },
+    hash::Hash
+};
+
+use crate::math::{
+    Aabb,
+    IVec3,
+    Ray,
+    Vec3
+};
+
+/// Objects covering more cells than this are kept out of the grid and tested by every query
+const MAX_CELLS_PER_ENTRY: i64 = 4096;
+/// Cell coordinates are clamped to this, bounds reaching it are kept out of the grid as well
+const GRID_LIMIT: f32 = (1 << 20) as f32;
+
+/// Uniform grid over object bounds, keyed by whatever id the game uses for its objects.
+/// Objects spanning several cells are stored in each of them, very large, far away or
+/// non-finite ones in a list that every query checks.
+pub struct SpatialIndex<K> {
+    cell_size: f32,
+    cells: HashMap<IVec3, Vec<K>>,
+    unbounded: Vec<K>,
+    entries: HashMap<K, Entry>,
+    occupied: Option<(IVec3, IVec3)>,
+}
+
+struct Entry {
+    bounds: Aabb,
+    /// First and last cell, `None` for objects in the unbounded list
+    cells: Option<(IVec3, IVec3)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit<K> {
+    pub key: K,
+    pub distance: f32,
+    pub point: Vec3,
+}
+
+impl<K: Copy + Eq + Hash> SpatialIndex<K> {
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "cell size must be positive");
+
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            unbounded: Vec::new(),
+            entries: HashMap::new(),
+            occupied: None,
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.unbounded.clear();
+        self.entries.clear();
+        self.occupied = None;
+    }
+
+    pub fn bounds(&self, key: K) -> Option<Aabb> {
+        self.entries.get(&key).map(|entry| entry.bounds)
+    }
+
+    /// Inserts the object or moves it if it is already present
+    pub fn insert(&mut self, key: K, bounds: Aabb) {
+        let cells = self.cell_span(&bounds);
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.bounds = bounds;
+            if entry.cells == cells {
+                return
+            }
+            let old = entry.cells;
+            self.unlink(key, old);
+        }
+
+        match cells {
+            Some((min_cell, max_cell)) => {
+                for cell in cell_range(min_cell, max_cell) {
+                    self.cells.entry(cell).or_default().push(key);
+                }
+
+                self.occupied = Some(match self.occupied {
+                    Some((lo, hi)) => (lo.min(min_cell), hi.max(max_cell)),
+                    None => (min_cell, max_cell)
+                });
+            },
+            None => self.unbounded.push(key)
+        }
+
+        self.entries.insert(key, Entry {
+            bounds,
+            cells,
+        });
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<Aabb> {
+        let entry = self.entries.remove(&key)?;
+        self.unlink(key, entry.cells);
+
+        if self.cells.is_empty() {
+            self.occupied = None;
+        }
+
+        Some(entry.bounds)
+    }
+
+    /// All objects whose bounds overlap the box
+    pub fn overlap_aabb(&self, aabb: &Aabb) -> Vec<K> {
+        let mut result: Vec<K> = self.unbounded.iter()
+            .copied()
+            .filter(|key| self.entries[key].bounds.intersects(aabb))
+            .collect();
+
+        let Some((lo, hi)) = self.occupied else {
+            return result
+        };
+
+        let min = self.cell_of(aabb.min).max(lo);
+        let max = self.cell_of(aabb.max).min(hi);
+        if min.cmpgt(max).any() {
+            return result
+        }
+
+        let mut seen = HashSet::new();
+        let mut visit = |keys: &Vec<K>| {
+            for &key in keys {
+                if seen.insert(key) && self.entries[&key].bounds.intersects(aabb) {
+                    result.push(key);
+                }
+            }
+        };
+
+        // A query covering more cells than are occupied looks at the occupied ones instead
+        match cell_count(min, max) > self.cells.len() as i64 {
+            true => self.cells.values().for_each(&mut visit),
+            false => cell_range(min, max).filter_map(|cell| self.cells.get(&cell)).for_each(&mut visit)
+        }
+
+        result
+    }
+
+    /// Closest object hit by the ray within `max_distance`
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<RayHit<K>> {
+        self.raycast_filtered(ray, max_distance, |_| true)
+    }
+
+    /// Closest object hit by the ray within `max_distance` that passes the filter
+    pub fn raycast_filtered(&self, ray: &Ray, max_distance: f32, mut filter: impl FnMut(K) -> bool) -> Option<RayHit<K>> {
+        let mut best: Option<RayHit<K>> = None;
+
+        for &key in &self.unbounded {
+            if !filter(key) {
+                continue
+            }
+
+            if let Some(distance) = ray.intersect_aabb(&self.entries[&key].bounds) {
+                if distance <= max_distance && best.is_none_or(|hit| distance < hit.distance) {
+                    best = Some(RayHit {
+                        key,
+                        distance,
+                        point: ray.at(distance),
+                    });
+                }
+            }
+        }
+
+        self.raycast_grid(ray, max_distance, filter, best)
+    }
+
+    /// Walks the grid cells along the ray, starting from the closest hit found so far
+    fn raycast_grid(&self, ray: &Ray, max_distance: f32, mut filter: impl FnMut(K) -> bool, mut best: Option<RayHit<K>>) -> Option<RayHit<K>> {
+        let Some((lo, hi)) = self.occupied else {
+            return best
+        };
+        let occupied_bounds = Aabb::new(
+            lo.as_vec3() * self.cell_size,
+            (hi + IVec3::ONE).as_vec3() * self.cell_size
+        );
+
+        // Walk cells from where the ray enters the occupied region, so empty space is never stepped through
+        let Some(start) = ray.intersect_aabb(&occupied_bounds) else {
+            return best
+        };
+        if start > max_distance || ray.direction == Vec3::ZERO || !ray.direction.is_finite() {
+            return best
+        }
+
+        let origin = ray.at(start);
+        let mut cell = self.cell_of(origin).clamp(lo, hi);
+        let step = ray.direction.signum().as_ivec3();
+        let t_delta = (self.cell_size / ray.direction.abs()).abs();
+
+        let next_boundary = (cell + step.max(IVec3::ZERO)).as_vec3() * self.cell_size;
+        let mut t_max = Vec3::select(
+            ray.direction.cmpeq(Vec3::ZERO),
+            Vec3::INFINITY,
+            (next_boundary - origin) / ray.direction
+        ) + start;
+
+        let mut seen = HashSet::new();
+
+        loop {
+            let cell_exit = t_max.min_element();
+
+            if let Some(keys) = self.cells.get(&cell) {
+                for &key in keys {
+                    if !seen.insert(key) || !filter(key) {
+                        continue
+                    }
+
+                    let Some(distance) = ray.intersect_aabb(&self.entries[&key].bounds) else {
+                        continue
+                    };
+
+                    if distance <= max_distance && best.is_none_or(|hit| distance < hit.distance) {
+                        best = Some(RayHit {
+                            key,
+                            distance,
+                            point: ray.at(distance),
+                        });
+                    }
+                }
+            }
+
+            // Anything in later cells is further away than the current hit
+            if best.is_some_and(|hit| hit.distance <= cell_exit) || cell_exit > max_distance {
+                break
+            }
+
+            let axis = match t_max.x < t_max.y {
+                true if t_max.x < t_max.z => 0,
+                false if t_max.y < t_max.z => 1,
+                _ => 2
+            };
+
+            cell[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
+
+            if cell[axis] < lo[axis] || cell[axis] > hi[axis] {
+                break
+            }
+        }
+
+        best
+    }
+
+    /// Up to `k` objects ordered by distance from the point to their bounds
+    pub fn k_nearest(&self, point: Vec3, k: usize) -> Vec<(K, f32)> {
+        if k == 0 {
+            return Vec::new()
+        }
+
+        let distance = |key: K| self.entries[&key].bounds.distance_squared(point).sqrt();
+
+        // Non-finite bounds have no meaningful distance and are left out
+        let mut found: Vec<(K, f32)> = self.unbounded.iter()
+            .map(|&key| (key, distance(key)))
+            .filter(|(_, distance)| !distance.is_nan())
+            .collect();
+
+        let Some((lo, hi)) = self.occupied else {
+            found.sort_by(|a, b| a.1.total_cmp(&b.1));
+            found.truncate(k);
+            return found
+        };
+
+        let center = self.cell_of(point);
+        let max_radius = (center - lo).abs().max((hi - center).abs()).max_element();
+
+        let mut seen = HashSet::new();
+        let mut visit = |keys: &Vec<K>, found: &mut Vec<(K, f32)>| {
+            for &key in keys {
+                if seen.insert(key) {
+                    found.push((key, distance(key)));
+                }
+            }
+        };
+
+        for radius in 0..=max_radius {
+            // Once a shell holds more cells than are occupied it is cheaper to look at all of them
+            let side = 2 * radius as i64 + 1;
+            if side * side * side > self.cells.len() as i64 {
+                for keys in self.cells.values() {
+                    visit(keys, &mut found);
+                }
+                break
+            }
+
+            for cell in cell_range(center - IVec3::splat(radius), center + IVec3::splat(radius)) {
+                if (cell - center).abs().max_element() != radius {
+                    continue
+                }
+                if let Some(keys) = self.cells.get(&cell) {
+                    visit(keys, &mut found);
+                }
+            }
+
+            // Objects not yet seen are at least `radius` whole cells away
+            if found.len() >= k {
+                found.sort_by(|a, b| a.1.total_cmp(&b.1));
+                if found[k - 1].1 <= radius as f32 * self.cell_size {
+                    break
+                }
+            }
+        }
+
+        found.sort_by(|a, b| a.1.total_cmp(&b.1));
+        found.truncate(k);
+        found
+    }
+
+    fn cell_of(&self, point: Vec3) -> IVec3 {
+        (point / self.cell_size).floor().clamp(Vec3::splat(-GRID_LIMIT), Vec3::splat(GRID_LIMIT)).as_ivec3()
+    }
+
+    /// Cells covered by the bounds, `None` if the object belongs in the unbounded list
+    fn cell_span(&self, bounds: &Aabb) -> Option<(IVec3, IVec3)> {
+        if !bounds.min.is_finite() || !bounds.max.is_finite() {
+            return None
+        }
+
+        let min = self.cell_of(bounds.min);
+        let max = self.cell_of(bounds.max);
+        let limit = GRID_LIMIT as i32;
+        match min.abs().max(max.abs()).max_element() < limit && cell_count(min, max) <= MAX_CELLS_PER_ENTRY {
+            true => Some((min, max)),
+            false => None
+        }
+    }
+
+    fn unlink(&mut self, key: K, cells: Option<(IVec3, IVec3)>) {
+        let Some((min_cell, max_cell)) = cells else {
+            self.unbounded.retain(|other| *other != key);
+            return
+        };
+
+        for cell in cell_range(min_cell, max_cell) {
+            if let Some(keys) = self.cells.get_mut(&cell) {
+                keys.retain(|other| *other != key);
+                if keys.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+}
+
+fn cell_count(min: IVec3, max: IVec3) -> i64 {
+    let size = (max - min + IVec3::ONE).max(IVec3::ZERO).as_i64vec3();
+    size.x * size.y * size.z
+}
+
+fn cell_range(min: IVec3, max: IVec3) -> impl Iterator<Item = IVec3> {
+    (min.z..=max.z).flat_map(move |z| {
+        (min.y..=max.y).flat_map(move |y| {
+            (min.x..=max.x).map(move |x| IVec3::new(x, y, z))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box(center: Vec3) -> Aabb {
+        Aabb::from_center(center, Vec3::splat(0.5))
+    }
+
+    #[test]
+    fn queries_find_grid_entries() {
+        let mut index = SpatialIndex::new(2.0);
+        index.insert(1, unit_box(Vec3::new(0.0, 0.0, 0.0)));
+        index.insert(2, unit_box(Vec3::new(10.0, 0.0, 0.0)));
+        index.insert(3, unit_box(Vec3::new(-6.0, 4.0, 0.0)));
+
+        assert_eq!(index.overlap_aabb(&unit_box(Vec3::new(9.5, 0.0, 0.0))), vec![2]);
+
+        let hit = index.raycast(&Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X), 100.0).unwrap();
+        assert_eq!(hit.key, 1);
+        assert_eq!(hit.distance, 4.5);
+
+        let nearest: Vec<i32> = index.k_nearest(Vec3::new(8.0, 0.0, 0.0), 2).into_iter().map(|(key, _)| key).collect();
+        assert_eq!(nearest, vec![2, 1]);
+
+        index.insert(1, unit_box(Vec3::new(20.0, 0.0, 0.0)));
+        assert!(index.overlap_aabb(&unit_box(Vec3::ZERO)).is_empty());
+        assert_eq!(index.remove(3), Some(unit_box(Vec3::new(-6.0, 4.0, 0.0))));
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn huge_and_far_bounds_stay_out_of_the_grid() {
+        let mut index = SpatialIndex::new(1.0);
+        index.insert(1, Aabb::new(Vec3::splat(-1e30), Vec3::splat(1e30)));
+        index.insert(2, unit_box(Vec3::splat(1e12)));
+        index.insert(3, unit_box(Vec3::ZERO));
+        assert_eq!(index.cells.len(), 8);
+
+        let mut overlapping = index.overlap_aabb(&unit_box(Vec3::ZERO));
+        overlapping.sort();
+        assert_eq!(overlapping, vec![1, 3]);
+        assert_eq!(index.overlap_aabb(&unit_box(Vec3::splat(1e12))), vec![1, 2]);
+
+        let hit = index.raycast(&Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::Z), 10.0).unwrap();
+        assert_eq!((hit.key, hit.distance), (1, 0.0));
+
+        index.remove(1);
+        assert_eq!(index.raycast(&Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::Z), 10.0).map(|hit| hit.key), Some(3));
+        assert_eq!(index.k_nearest(Vec3::splat(2e12), 1)[0].0, 2);
+    }
+
+    #[test]
+    fn non_finite_bounds_and_queries_do_not_hang() {
+        let mut index = SpatialIndex::new(1.0);
+        index.insert(1, Aabb { min: Vec3::NAN, max: Vec3::NAN });
+        index.insert(2, Aabb::new(Vec3::ZERO, Vec3::splat(f32::INFINITY)));
+        index.insert(3, unit_box(Vec3::ZERO));
+
+        assert_eq!(index.overlap_aabb(&Aabb::new(Vec3::splat(f32::NEG_INFINITY), Vec3::splat(f32::INFINITY))).len(), 2);
+        assert!(index.overlap_aabb(&Aabb { min: Vec3::NAN, max: Vec3::NAN }).is_empty());
+        assert!(index.raycast(&Ray { origin: Vec3::ZERO, direction: Vec3::NAN }, f32::INFINITY).is_none());
+        assert!(index.k_nearest(Vec3::ZERO, 3).iter().all(|(key, _)| *key != 1));
+
+        index.insert(1, unit_box(Vec3::ONE));
+        assert_eq!(index.bounds(1), Some(unit_box(Vec3::ONE)));
+        assert_eq!(index.unbounded, vec![2]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,204 @@
+use std::{
+    panic::{
+        self,
+        AssertUnwindSafe
+    },
+    sync::mpsc::{
+        self,
+        Receiver,
+        TryRecvError
+    },
+    thread
+};
+
+use rayon::{
+    prelude::*,
+    ThreadPool,
+    ThreadPoolBuilder
+};
+
+use crate::{
+    config::TaskConfig,
+    profiler::ScopeGuard
+};
+
+pub use rayon::Scope;
+
+/// Work-stealing thread pools for engine and game work.
+/// CPU bound work runs on the compute pool, blocking work like file reads on the separate IO pool
+/// so it never stalls compute tasks.
+pub struct TaskPool {
+    compute: ThreadPool,
+    io: ThreadPool,
+}
+
+/// Handle to the result of a spawned task
+pub struct Task<T> {
+    receiver: Receiver<thread::Result<T>>,
+    result: Option<thread::Result<T>>,
+}
+
+impl TaskPool {
+    /// Thread counts of 0 pick one based on the available cores
+    pub fn new(config: &TaskConfig) -> Self {
+        let cores = thread::available_parallelism().map(|cores| cores.get()).unwrap_or(4);
+
+        let compute_threads = match config.compute_threads {
+            // One core is left for the main thread
+            0 => cores.saturating_sub(1).max(1),
+            threads => threads
+        };
+        let io_threads = match config.io_threads {
+            0 => (cores / 4).clamp(1, 4),
+            threads => threads
+        };
+
+        Self {
+            compute: build_pool("aspen-compute", compute_threads),
+            io: build_pool("aspen-io", io_threads),
+        }
+    }
+
+    pub fn compute_threads(&self) -> usize {
+        self.compute.current_num_threads()
+    }
+
+    pub fn io_threads(&self) -> usize {
+        self.io.current_num_threads()
+    }
+
+    /// Runs `f` on the compute pool, recorded in the profiler under `name`
+    pub fn spawn<T, F>(&self, name: &'static str, f: F) -> Task<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static
+    {
+        spawn_on(&self.compute, name, f)
+    }
+
+    /// Runs `f` on the IO pool, for work that mostly waits
+    pub fn spawn_io<T, F>(&self, name: &'static str, f: F) -> Task<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static
+    {
+        spawn_on(&self.io, name, f)
+    }
+
+    /// Spawns tasks that may borrow from the caller, returning once all of them have finished
+    pub fn scope<'scope, R, F>(&self, f: F) -> R
+    where
+        R: Send,
+        F: FnOnce(&Scope<'scope>) -> R + Send
+    {
+        self.compute.scope(f)
+    }
+
+    /// Calls `f` for every item in parallel and blocks until all are done
+    pub fn for_each<T, F>(&self, name: &'static str, items: &[T], f: F)
+    where
+        T: Sync,
+        F: Fn(&T) + Sync + Send
+    {
+        self.compute.install(|| {
+            items.par_iter().for_each(|item| {
+                let _scope = ScopeGuard::new(name);
+                f(item)
+            })
+        })
+    }
+
+    pub fn for_each_mut<T, F>(&self, name: &'static str, items: &mut [T], f: F)
+    where
+        T: Send,
+        F: Fn(&mut T) + Sync + Send
+    {
+        self.compute.install(|| {
+            items.par_iter_mut().for_each(|item| {
+                let _scope = ScopeGuard::new(name);
+                f(item)
+            })
+        })
+    }
+
+    /// Maps every item in parallel, keeping the input order
+    pub fn map<T, U, F>(&self, name: &'static str, items: &[T], f: F) -> Vec<U>
+    where
+        T: Sync,
+        U: Send,
+        F: Fn(&T) -> U + Sync + Send
+    {
+        self.compute.install(|| {
+            items.par_iter().map(|item| {
+                let _scope = ScopeGuard::new(name);
+                f(item)
+            }).collect()
+        })
+    }
+}
+
+impl<T> Task<T> {
+    /// Whether the result is ready, `take` will not block once this returns true
+    pub fn is_finished(&mut self) -> bool {
+        if self.result.is_none() {
+            match self.receiver.try_recv() {
+                Ok(result) => self.result = Some(result),
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => panic!("task was dropped without finishing")
+            }
+        }
+
+        true
+    }
+
+    /// Returns the result if the task finished, a panic inside the task is resumed here
+    pub fn try_take(&mut self) -> Option<T> {
+        match self.is_finished() {
+            true => Some(unwrap_result(self.result.take().unwrap())),
+            false => None
+        }
+    }
+
+    /// Blocks until the task finishes
+    pub fn wait(mut self) -> T {
+        let result = match self.result.take() {
+            Some(result) => result,
+            None => self.receiver.recv().expect("task was dropped without finishing")
+        };
+
+        unwrap_result(result)
+    }
+}
+
+fn build_pool(name: &'static str, threads: usize) -> ThreadPool {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(move |index| format!("{}-{}", name, index))
+        .build()
+        .expect("thread pool creation failed")
+}
+
+fn spawn_on<T, F>(pool: &ThreadPool, name: &'static str, f: F) -> Task<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static
+{
+    let (sender, receiver) = mpsc::channel();
+    pool.spawn(move || {
+        let _scope = ScopeGuard::new(name);
+        // The receiver may be gone if the task handle was dropped, the result is simply discarded then
+        let _ = sender.send(panic::catch_unwind(AssertUnwindSafe(f)));
+    });
+
+    Task {
+        receiver,
+        result: None,
+    }
+}
+
+fn unwrap_result<T>(result: thread::Result<T>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(payload) => panic::resume_unwind(payload)
+    }
+}
\ No newline at end of file
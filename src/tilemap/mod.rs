@@ -0,0 +1,463 @@
+pub mod tiled;
+
+use std::{
+    collections::HashMap,
+    path::PathBuf
+};
+
+use crate::math::{
+    IVec2,
+    UVec2,
+    Vec2
+};
+
+/// Tiles per chunk side, chunks are the unit of instance rebuilding and culling
+pub const CHUNK_SIZE: i32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile {
+    /// Map wide tile id, atlases claim consecutive ranges starting at their `first_id`
+    pub id: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Swaps the x and y axes before flipping, combined with the flips this gives 90 degree rotations
+    pub flip_diagonal: bool,
+}
+
+impl Tile {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            flip_x: false,
+            flip_y: false,
+            flip_diagonal: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationFrame {
+    /// Tile id within the same atlas
+    pub tile: u32,
+    /// Seconds
+    pub duration: f32,
+}
+
+/// Tile images laid out in a grid on one texture
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileAtlas {
+    pub name: String,
+    pub texture: PathBuf,
+    pub texture_size: UVec2,
+    pub tile_size: UVec2,
+    pub first_id: u32,
+    pub count: u32,
+    pub columns: u32,
+    /// Pixels between tiles
+    pub spacing: u32,
+    /// Pixels around the grid
+    pub margin: u32,
+    /// Animated tiles by their local id
+    pub animations: HashMap<u32, Vec<AnimationFrame>>,
+}
+
+impl TileAtlas {
+    pub fn contains(&self, id: u32) -> bool {
+        id >= self.first_id && id - self.first_id < self.count
+    }
+
+    /// Texture coordinates of a map wide tile id, as (min, max) in 0..1
+    pub fn uv(&self, id: u32) -> (Vec2, Vec2) {
+        let local = id - self.first_id;
+        let columns = self.columns.max(1);
+        let cell = UVec2::new(local % columns, local / columns);
+        let min = UVec2::splat(self.margin) + cell * (self.tile_size + UVec2::splat(self.spacing));
+
+        let size = self.texture_size.max(UVec2::ONE).as_vec2();
+        (min.as_vec2() / size, (min + self.tile_size).as_vec2() / size)
+    }
+
+    /// Map wide id shown at `time` seconds, following the tile's animation if it has one
+    pub fn animated(&self, id: u32, time: f32) -> u32 {
+        let Some(frames) = self.animations.get(&(id - self.first_id)) else {
+            return id
+        };
+        let total: f32 = frames.iter().map(|frame| frame.duration).sum();
+        if total <= 0.0 {
+            return id
+        }
+
+        let mut time = time.rem_euclid(total);
+        for frame in frames {
+            if time < frame.duration {
+                return self.first_id + frame.tile
+            }
+            time -= frame.duration;
+        }
+        self.first_id + frames.last().map_or(0, |frame| frame.tile)
+    }
+
+    /// Time after `time` at which `animated` moves to the tile's next frame, `None` if it never changes
+    pub fn next_change(&self, id: u32, time: f32) -> Option<f32> {
+        let frames = self.animations.get(&(id - self.first_id)).filter(|frames| frames.len() > 1)?;
+        let total: f32 = frames.iter().map(|frame| frame.duration).sum();
+        if total <= 0.0 {
+            return None
+        }
+
+        let phase = time.rem_euclid(total);
+        let mut end = 0.0;
+        for frame in frames {
+            end += frame.duration;
+            if phase < end {
+                return Some(time - phase + end)
+            }
+        }
+        Some(time - phase + total)
+    }
+}
+
+/// Per tile data for an instanced quad draw, one draw per atlas
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileInstance {
+    /// Top left corner in map units
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Corners already swapped for flipped tiles
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+    /// Swap the quad's u and v axes
+    pub transpose: u32,
+    pub atlas: u32,
+}
+
+struct Chunk {
+    tiles: Vec<Option<Tile>>,
+    instances: Vec<TileInstance>,
+    dirty: bool,
+    /// Earliest time one of the chunk's animated tiles changes frame
+    next_change: Option<f32>,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Self {
+            tiles: vec![None; (CHUNK_SIZE * CHUNK_SIZE) as usize],
+            instances: Vec::new(),
+            dirty: true,
+            next_change: None,
+        }
+    }
+}
+
+/// Grid of tiles stored in chunks, so large and sparse maps only pay for the areas in use
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    /// Map units the layer is shifted by
+    pub offset: Vec2,
+    chunks: HashMap<IVec2, Chunk>,
+}
+
+impl TileLayer {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            visible: true,
+            opacity: 1.0,
+            offset: Vec2::ZERO,
+            chunks: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, position: IVec2) -> Option<Tile> {
+        let (chunk, index) = split(position);
+        self.chunks.get(&chunk).and_then(|chunk| chunk.tiles[index])
+    }
+
+    pub fn set(&mut self, position: IVec2, tile: Option<Tile>) {
+        let (chunk_position, index) = split(position);
+        if tile.is_none() && !self.chunks.contains_key(&chunk_position) {
+            return
+        }
+
+        let chunk = self.chunks.entry(chunk_position).or_insert_with(Chunk::new);
+        chunk.tiles[index] = tile;
+        chunk.dirty = true;
+
+        if chunk.tiles.iter().all(Option::is_none) {
+            self.chunks.remove(&chunk_position);
+        }
+    }
+
+    /// Positions of the chunks holding tiles, in chunk coordinates
+    pub fn chunks(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// Every tile with its position
+    pub fn tiles(&self) -> impl Iterator<Item = (IVec2, Tile)> + '_ {
+        self.chunks.iter().flat_map(|(chunk, data)| {
+            data.tiles.iter().enumerate().filter_map(move |(index, tile)| {
+                let local = IVec2::new(index as i32 % CHUNK_SIZE, index as i32 / CHUNK_SIZE);
+                tile.map(|tile| (*chunk * CHUNK_SIZE + local, tile))
+            })
+        })
+    }
+}
+
+/// A placed object from an object layer, such as a spawn point or trigger area
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    /// Tiled's class or type, used to pick the prefab to spawn
+    pub class: String,
+    /// Top left corner in map units
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Degrees clockwise
+    pub rotation: f32,
+    pub tile: Option<Tile>,
+    pub properties: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub visible: bool,
+    pub objects: Vec<MapObject>,
+}
+
+/// Layered 2D tile map with animated tiles, built in code or imported from Tiled
+pub struct Tilemap {
+    /// Size of a grid cell in map units
+    pub tile_size: UVec2,
+    pub atlases: Vec<TileAtlas>,
+    /// Drawn in order, back to front
+    pub layers: Vec<TileLayer>,
+    pub object_layers: Vec<ObjectLayer>,
+    pub properties: HashMap<String, String>,
+    time: f32,
+}
+
+impl Tilemap {
+    pub fn new(tile_size: UVec2) -> Self {
+        Self {
+            tile_size,
+            atlases: Vec::new(),
+            layers: Vec::new(),
+            object_layers: Vec::new(),
+            properties: HashMap::new(),
+            time: 0.0,
+        }
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    pub fn atlas_for(&self, id: u32) -> Option<(usize, &TileAtlas)> {
+        self.atlases.iter().enumerate().find(|(_, atlas)| atlas.contains(id))
+    }
+
+    /// Advances tile animations, chunks whose animated tiles changed frame rebuild their instances
+    pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+        for layer in self.layers.iter_mut() {
+            for chunk in layer.chunks.values_mut() {
+                chunk.dirty |= chunk.next_change.is_some_and(|next| self.time >= next);
+            }
+        }
+    }
+
+    /// Tile grid cell at a map position
+    pub fn tile_at(&self, position: Vec2) -> IVec2 {
+        (position / self.tile_size.max(UVec2::ONE).as_vec2()).floor().as_ivec2()
+    }
+
+    /// Chunks of a layer overlapping the map area between `min` and `max`, for culling to the camera view
+    pub fn visible_chunks(&self, layer: usize, min: Vec2, max: Vec2) -> Vec<IVec2> {
+        let Some(layer) = self.layers.get(layer) else {
+            return Vec::new()
+        };
+        let chunk_size = (self.tile_size.as_ivec2() * CHUNK_SIZE).as_vec2();
+        let first = ((min - layer.offset) / chunk_size).floor().as_ivec2();
+        let last = ((max - layer.offset) / chunk_size).floor().as_ivec2();
+
+        layer.chunks.keys()
+            .filter(|chunk| chunk.cmpge(first).all() && chunk.cmple(last).all())
+            .copied()
+            .collect()
+    }
+
+    /// Instances for one chunk of a layer, rebuilt if its tiles or animation frames changed
+    pub fn chunk_instances(&mut self, layer: usize, chunk: IVec2) -> &[TileInstance] {
+        let Some(layer) = self.layers.get_mut(layer) else {
+            return &[]
+        };
+        let Some(data) = layer.chunks.get_mut(&chunk) else {
+            return &[]
+        };
+
+        if data.dirty {
+            data.instances.clear();
+            data.next_change = None;
+
+            let tile_size = self.tile_size.as_vec2();
+            for (index, tile) in data.tiles.iter().enumerate() {
+                let Some(tile) = tile else {
+                    continue
+                };
+                let Some((atlas_index, atlas)) = self.atlases.iter().enumerate().find(|(_, atlas)| atlas.contains(tile.id)) else {
+                    continue
+                };
+
+                if let Some(next) = atlas.next_change(tile.id, self.time) {
+                    data.next_change = Some(data.next_change.map_or(next, |current| current.min(next)));
+                }
+                let (mut uv_min, mut uv_max) = atlas.uv(atlas.animated(tile.id, self.time));
+                if tile.flip_x {
+                    std::mem::swap(&mut uv_min.x, &mut uv_max.x);
+                }
+                if tile.flip_y {
+                    std::mem::swap(&mut uv_min.y, &mut uv_max.y);
+                }
+
+                // Tiles larger than the grid extend upwards from the bottom of their cell like in Tiled
+                let local = IVec2::new(index as i32 % CHUNK_SIZE, index as i32 / CHUNK_SIZE);
+                let cell = (chunk * CHUNK_SIZE + local).as_vec2() * tile_size;
+                let size = atlas.tile_size.as_vec2();
+                data.instances.push(TileInstance {
+                    position: layer.offset + cell + Vec2::new(0.0, tile_size.y - size.y),
+                    size,
+                    uv_min,
+                    uv_max,
+                    transpose: tile.flip_diagonal as u32,
+                    atlas: atlas_index as u32,
+                });
+            }
+            data.dirty = false;
+        }
+
+        &data.instances
+    }
+
+    /// Objects of every visible object layer that have a class, for spawning prefabs
+    pub fn spawns(&self) -> impl Iterator<Item = &MapObject> {
+        self.object_layers.iter()
+            .filter(|layer| layer.visible)
+            .flat_map(|layer| layer.objects.iter())
+            .filter(|object| !object.class.is_empty())
+    }
+}
+
+type PrefabBuilder<T> = Box<dyn Fn(&MapObject) -> T>;
+
+/// Maps object classes to functions building the game's objects from them
+pub struct Prefabs<T> {
+    builders: HashMap<String, PrefabBuilder<T>>,
+}
+
+impl<T> Prefabs<T> {
+    pub fn new() -> Self {
+        Self {
+            builders: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, class: &str, builder: impl Fn(&MapObject) -> T + 'static) {
+        self.builders.insert(class.to_string(), Box::new(builder));
+    }
+
+    /// Builds every spawn in the map with a registered class, others are skipped
+    pub fn spawn(&self, map: &Tilemap) -> Vec<T> {
+        map.spawns()
+            .filter_map(|object| self.builders.get(&object.class).map(|builder| builder(object)))
+            .collect()
+    }
+}
+
+impl<T> Default for Prefabs<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn split(position: IVec2) -> (IVec2, usize) {
+    let chunk = IVec2::new(position.x.div_euclid(CHUNK_SIZE), position.y.div_euclid(CHUNK_SIZE));
+    let local = position - chunk * CHUNK_SIZE;
+    (chunk, (local.y * CHUNK_SIZE + local.x) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tile 1 alternates between tiles 1 and 2 every half second, tile 3 is static
+    fn map() -> Tilemap {
+        let mut map = Tilemap::new(UVec2::splat(16));
+        map.atlases.push(TileAtlas {
+            name: "tiles".to_string(),
+            texture: PathBuf::new(),
+            texture_size: UVec2::new(64, 16),
+            tile_size: UVec2::splat(16),
+            first_id: 1,
+            count: 4,
+            columns: 4,
+            spacing: 0,
+            margin: 0,
+            animations: HashMap::from([(0, vec![
+                AnimationFrame { tile: 0, duration: 0.5 },
+                AnimationFrame { tile: 1, duration: 0.5 },
+            ])]),
+        });
+
+        let mut layer = TileLayer::new("ground");
+        layer.set(IVec2::new(0, 0), Some(Tile::new(1)));
+        layer.set(IVec2::new(CHUNK_SIZE, 0), Some(Tile::new(3)));
+        map.layers.push(layer);
+        map
+    }
+
+    fn dirty(map: &Tilemap, chunk: IVec2) -> bool {
+        map.layers[0].chunks[&chunk].dirty
+    }
+
+    #[test]
+    fn next_change_is_the_end_of_the_current_frame() {
+        let atlas = &map().atlases[0];
+        assert_eq!(atlas.next_change(1, 0.0), Some(0.5));
+        assert_eq!(atlas.next_change(1, 0.7), Some(1.0));
+        assert_eq!(atlas.next_change(1, 2.25), Some(2.5));
+        assert_eq!(atlas.next_change(3, 0.0), None);
+    }
+
+    #[test]
+    fn animated_chunks_rebuild_only_when_a_frame_changes() {
+        let mut map = map();
+        let animated = IVec2::ZERO;
+        let still = IVec2::new(1, 0);
+        map.chunk_instances(0, animated);
+        map.chunk_instances(0, still);
+
+        map.update(0.2);
+        assert!(!dirty(&map, animated));
+        map.update(0.2);
+        assert!(!dirty(&map, animated));
+
+        map.update(0.2);
+        assert!(dirty(&map, animated));
+        assert!(!dirty(&map, still));
+        assert_eq!(map.chunk_instances(0, animated)[0].uv_min.x, 0.25);
+
+        map.update(0.2);
+        assert!(!dirty(&map, animated));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,544 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{
+        self,
+        Read
+    },
+    path::Path
+};
+
+use flate2::read::{
+    GzDecoder,
+    ZlibDecoder
+};
+use serde_json::Value;
+use xml::reader::{
+    EventReader,
+    XmlEvent
+};
+
+use crate::math::{
+    IVec2,
+    UVec2,
+    Vec2
+};
+
+use super::{
+    AnimationFrame,
+    MapObject,
+    ObjectLayer,
+    Tile,
+    TileAtlas,
+    TileLayer,
+    Tilemap
+};
+
+const FLIP_X: u32 = 0x8000_0000;
+const FLIP_Y: u32 = 0x4000_0000;
+const FLIP_DIAGONAL: u32 = 0x2000_0000;
+/// Hexagonal rotation bit, not supported but masked out of the id
+const ROTATE_HEX: u32 = 0x1000_0000;
+
+/// Loads a map saved by the Tiled editor, as `.tmx` or as `.tmj`/`.json`.
+/// Tile layer data has to be CSV or base64, either uncompressed or compressed with zlib or gzip. Zstandard is not supported.
+/// External tilesets are loaded relative to the map.
+/// Texture paths in the atlases are joined to the directory of the map or tileset file naming them.
+pub fn load(path: impl AsRef<Path>) -> io::Result<Tilemap> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    match is_json(path) {
+        true => parse_json(&text, dir),
+        false => parse_tmx(&text, dir)
+    }
+}
+
+/// `dir` is where external tilesets and images are looked up
+pub fn parse_tmx(text: &str, dir: &Path) -> io::Result<Tilemap> {
+    let root = parse_xml(text)?;
+    if root.name != "map" {
+        return Err(invalid("not a Tiled map"))
+    }
+
+    let mut map = Tilemap::new(UVec2::new(root.attr_or("tilewidth", 0)?, root.attr_or("tileheight", 0)?));
+    map.properties = xml_properties(&root);
+
+    for tileset in root.children_named("tileset") {
+        let first_id = tileset.attr_or("firstgid", 1)?;
+        let atlas = match tileset.attributes.get("source") {
+            Some(source) => load_tileset(&dir.join(source), first_id)?,
+            None => xml_tileset(tileset, first_id, dir)?
+        };
+        map.atlases.push(atlas);
+    }
+
+    xml_layers(&root, Vec2::ZERO, &mut map)?;
+    Ok(map)
+}
+
+/// `dir` is where external tilesets and images are looked up
+pub fn parse_json(text: &str, dir: &Path) -> io::Result<Tilemap> {
+    let root: Value = serde_json::from_str(text).map_err(|err| invalid(&err.to_string()))?;
+
+    let mut map = Tilemap::new(UVec2::new(json_u32(&root, "tilewidth", 0), json_u32(&root, "tileheight", 0)));
+    map.properties = json_properties(&root);
+
+    for tileset in json_array(&root, "tilesets") {
+        let first_id = json_u32(tileset, "firstgid", 1);
+        let atlas = match tileset.get("source").and_then(Value::as_str) {
+            Some(source) => load_tileset(&dir.join(source), first_id)?,
+            None => json_tileset(tileset, first_id, dir)
+        };
+        map.atlases.push(atlas);
+    }
+
+    json_layers(&root, Vec2::ZERO, &mut map)?;
+    Ok(map)
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "json" || extension == "tmj" || extension == "tsj")
+}
+
+fn load_tileset(path: &Path, first_id: u32) -> io::Result<TileAtlas> {
+    let text = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    match is_json(path) {
+        true => {
+            let root: Value = serde_json::from_str(&text).map_err(|err| invalid(&err.to_string()))?;
+            Ok(json_tileset(&root, first_id, dir))
+        },
+        false => xml_tileset(&parse_xml(&text)?, first_id, dir)
+    }
+}
+
+fn decode_tile(gid: u32) -> Option<Tile> {
+    let id = gid & !(FLIP_X | FLIP_Y | FLIP_DIAGONAL | ROTATE_HEX);
+    match id {
+        0 => None,
+        id => Some(Tile {
+            id,
+            flip_x: gid & FLIP_X != 0,
+            flip_y: gid & FLIP_Y != 0,
+            flip_diagonal: gid & FLIP_DIAGONAL != 0,
+        })
+    }
+}
+
+fn fill(layer: &mut TileLayer, origin: IVec2, width: u32, gids: &[u32]) {
+    let width = width.max(1) as usize;
+    for (index, gid) in gids.iter().enumerate() {
+        if let Some(tile) = decode_tile(*gid) {
+            layer.set(origin + IVec2::new((index % width) as i32, (index / width) as i32), Some(tile));
+        }
+    }
+}
+
+fn decode_data(text: &str, encoding: &str, compression: &str) -> io::Result<Vec<u32>> {
+    match encoding {
+        "csv" if !compression.is_empty() => Err(invalid("CSV tile data cannot be compressed")),
+        "csv" => text.split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse().map_err(|_| invalid("invalid CSV tile data")))
+            .collect(),
+        "base64" => Ok(decompress(decode_base64(text)?, compression)?
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect()),
+        other => Err(invalid(&format!("unknown tile data encoding {}", other)))
+    }
+}
+
+fn decompress(bytes: Vec<u8>, compression: &str) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let result = match compression {
+        "" => return Ok(bytes),
+        "zlib" => ZlibDecoder::new(&bytes[..]).read_to_end(&mut output),
+        "gzip" => GzDecoder::new(&bytes[..]).read_to_end(&mut output),
+        other => return Err(invalid(&format!("{} compressed tile data is not supported, save the map with zlib, gzip or no compression", other)))
+    };
+
+    result.map_err(|_| invalid(&format!("invalid {} compressed tile data", compression)))?;
+    Ok(output)
+}
+
+fn decode_base64(text: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(invalid("invalid base64 tile data"))
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+struct Element {
+    name: String,
+    attributes: HashMap<String, String>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attr_or<T: std::str::FromStr>(&self, name: &str, default: T) -> io::Result<T> {
+        match self.attributes.get(name) {
+            Some(value) => value.parse().map_err(|_| invalid(&format!("invalid {} attribute on {}", name, self.name))),
+            None => Ok(default)
+        }
+    }
+
+    fn attr(&self, name: &str) -> &str {
+        self.attributes.get(name).map_or("", String::as_str)
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+fn parse_xml(text: &str) -> io::Result<Element> {
+    let mut stack: Vec<Element> = Vec::new();
+
+    for event in EventReader::from_str(text) {
+        match event.map_err(|err| invalid(&err.to_string()))? {
+            XmlEvent::StartElement { name, attributes, .. } => stack.push(Element {
+                name: name.local_name,
+                attributes: attributes.into_iter()
+                    .map(|attribute| (attribute.name.local_name, attribute.value))
+                    .collect(),
+                children: Vec::new(),
+                text: String::new(),
+            }),
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().ok_or_else(|| invalid("unbalanced XML"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element)
+                }
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            },
+            _ => ()
+        }
+    }
+
+    Err(invalid("empty XML document"))
+}
+
+fn xml_properties(element: &Element) -> HashMap<String, String> {
+    element.child("properties")
+        .map(|properties| {
+            properties.children_named("property")
+                .map(|property| {
+                    // Multiline strings are stored as the element's text
+                    let value = match property.attributes.get("value") {
+                        Some(value) => value.clone(),
+                        None => property.text.clone()
+                    };
+                    (property.attr("name").to_string(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn xml_tileset(element: &Element, first_id: u32, dir: &Path) -> io::Result<TileAtlas> {
+    let image = element.child("image");
+    let mut animations = HashMap::new();
+    for tile in element.children_named("tile") {
+        if let Some(animation) = tile.child("animation") {
+            let frames = animation.children_named("frame")
+                .map(|frame| Ok(AnimationFrame {
+                    tile: frame.attr_or("tileid", 0)?,
+                    duration: frame.attr_or("duration", 0.0f32)? / 1000.0,
+                }))
+                .collect::<io::Result<Vec<_>>>()?;
+            animations.insert(tile.attr_or("id", 0)?, frames);
+        }
+    }
+
+    Ok(TileAtlas {
+        name: element.attr("name").to_string(),
+        texture: dir.join(image.map_or("", |image| image.attr("source"))),
+        texture_size: match image {
+            Some(image) => UVec2::new(image.attr_or("width", 0)?, image.attr_or("height", 0)?),
+            None => UVec2::ZERO
+        },
+        tile_size: UVec2::new(element.attr_or("tilewidth", 0)?, element.attr_or("tileheight", 0)?),
+        first_id,
+        count: element.attr_or("tilecount", 0)?,
+        columns: element.attr_or("columns", 0)?,
+        spacing: element.attr_or("spacing", 0)?,
+        margin: element.attr_or("margin", 0)?,
+        animations,
+    })
+}
+
+fn xml_layers(parent: &Element, offset: Vec2, map: &mut Tilemap) -> io::Result<()> {
+    for element in parent.children.iter() {
+        let offset = offset + Vec2::new(element.attr_or("offsetx", 0.0)?, element.attr_or("offsety", 0.0)?);
+        let visible = element.attr_or("visible", 1)? != 0;
+
+        match element.name.as_str() {
+            "layer" => {
+                let mut layer = TileLayer::new(element.attr("name"));
+                layer.visible = visible;
+                layer.opacity = element.attr_or("opacity", 1.0)?;
+                layer.offset = offset;
+
+                if let Some(data) = element.child("data") {
+                    let encoding = data.attr("encoding");
+                    let compression = data.attr("compression");
+                    let chunks: Vec<&Element> = data.children_named("chunk").collect();
+
+                    match chunks.is_empty() {
+                        true => {
+                            let gids = xml_gids(data, encoding, compression)?;
+                            fill(&mut layer, IVec2::ZERO, element.attr_or("width", 0)?, &gids);
+                        },
+                        false => for chunk in chunks {
+                            let gids = xml_gids(chunk, encoding, compression)?;
+                            let origin = IVec2::new(chunk.attr_or("x", 0)?, chunk.attr_or("y", 0)?);
+                            fill(&mut layer, origin, chunk.attr_or("width", 0)?, &gids);
+                        }
+                    }
+                }
+                map.layers.push(layer);
+            },
+            "objectgroup" => {
+                let objects = element.children_named("object")
+                    .map(|object| xml_object(object, offset))
+                    .collect::<io::Result<Vec<_>>>()?;
+                map.object_layers.push(ObjectLayer {
+                    name: element.attr("name").to_string(),
+                    visible,
+                    objects,
+                });
+            },
+            "group" => xml_layers(element, offset, map)?,
+            _ => ()
+        }
+    }
+    Ok(())
+}
+
+/// Tile ids of a `data` or `chunk` element, encoded or as `tile` children
+fn xml_gids(element: &Element, encoding: &str, compression: &str) -> io::Result<Vec<u32>> {
+    match encoding {
+        "" => element.children_named("tile").map(|tile| tile.attr_or("gid", 0)).collect(),
+        encoding => decode_data(&element.text, encoding, compression)
+    }
+}
+
+fn xml_object(element: &Element, offset: Vec2) -> io::Result<MapObject> {
+    let class = match element.attributes.get("class") {
+        Some(class) => class.clone(),
+        None => element.attr("type").to_string()
+    };
+
+    Ok(MapObject {
+        id: element.attr_or("id", 0)?,
+        name: element.attr("name").to_string(),
+        class,
+        position: offset + Vec2::new(element.attr_or("x", 0.0)?, element.attr_or("y", 0.0)?),
+        size: Vec2::new(element.attr_or("width", 0.0)?, element.attr_or("height", 0.0)?),
+        rotation: element.attr_or("rotation", 0.0)?,
+        tile: decode_tile(element.attr_or("gid", 0)?),
+        properties: xml_properties(element),
+    })
+}
+
+fn json_u32(value: &Value, key: &str, default: u32) -> u32 {
+    value.get(key).and_then(Value::as_u64).map_or(default, |value| value as u32)
+}
+
+fn json_f32(value: &Value, key: &str, default: f32) -> f32 {
+    value.get(key).and_then(Value::as_f64).map_or(default, |value| value as f32)
+}
+
+fn json_str<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or("")
+}
+
+fn json_array<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value.get(key).and_then(Value::as_array).into_iter().flatten()
+}
+
+fn json_properties(value: &Value) -> HashMap<String, String> {
+    json_array(value, "properties")
+        .map(|property| {
+            let value = match property.get("value") {
+                Some(Value::String(text)) => text.clone(),
+                Some(other) => other.to_string(),
+                None => String::new()
+            };
+            (json_str(property, "name").to_string(), value)
+        })
+        .collect()
+}
+
+fn json_tileset(value: &Value, first_id: u32, dir: &Path) -> TileAtlas {
+    let animations = json_array(value, "tiles")
+        .filter(|tile| tile.get("animation").is_some())
+        .map(|tile| {
+            let frames = json_array(tile, "animation")
+                .map(|frame| AnimationFrame {
+                    tile: json_u32(frame, "tileid", 0),
+                    duration: json_f32(frame, "duration", 0.0) / 1000.0,
+                })
+                .collect();
+            (json_u32(tile, "id", 0), frames)
+        })
+        .collect();
+
+    TileAtlas {
+        name: json_str(value, "name").to_string(),
+        texture: dir.join(json_str(value, "image")),
+        texture_size: UVec2::new(json_u32(value, "imagewidth", 0), json_u32(value, "imageheight", 0)),
+        tile_size: UVec2::new(json_u32(value, "tilewidth", 0), json_u32(value, "tileheight", 0)),
+        first_id,
+        count: json_u32(value, "tilecount", 0),
+        columns: json_u32(value, "columns", 0),
+        spacing: json_u32(value, "spacing", 0),
+        margin: json_u32(value, "margin", 0),
+        animations,
+    }
+}
+
+fn json_layers(parent: &Value, offset: Vec2, map: &mut Tilemap) -> io::Result<()> {
+    for value in json_array(parent, "layers") {
+        let offset = offset + Vec2::new(json_f32(value, "offsetx", 0.0), json_f32(value, "offsety", 0.0));
+        let visible = value.get("visible").and_then(Value::as_bool).unwrap_or(true);
+
+        match json_str(value, "type") {
+            "tilelayer" => {
+                let mut layer = TileLayer::new(json_str(value, "name"));
+                layer.visible = visible;
+                layer.opacity = json_f32(value, "opacity", 1.0);
+                layer.offset = offset;
+
+                let encoding = json_str(value, "encoding");
+                let compression = json_str(value, "compression");
+                match value.get("chunks").and_then(Value::as_array) {
+                    Some(chunks) => for chunk in chunks {
+                        let gids = json_gids(chunk, encoding, compression)?;
+                        let origin = IVec2::new(json_f32(chunk, "x", 0.0) as i32, json_f32(chunk, "y", 0.0) as i32);
+                        fill(&mut layer, origin, json_u32(chunk, "width", 0), &gids);
+                    },
+                    None => {
+                        let gids = json_gids(value, encoding, compression)?;
+                        fill(&mut layer, IVec2::ZERO, json_u32(value, "width", 0), &gids);
+                    }
+                }
+                map.layers.push(layer);
+            },
+            "objectgroup" => {
+                let objects = json_array(value, "objects")
+                    .map(|object| {
+                        let class = match object.get("class") {
+                            Some(class) => class.as_str().unwrap_or("").to_string(),
+                            None => json_str(object, "type").to_string()
+                        };
+                        MapObject {
+                            id: json_u32(object, "id", 0),
+                            name: json_str(object, "name").to_string(),
+                            class,
+                            position: offset + Vec2::new(json_f32(object, "x", 0.0), json_f32(object, "y", 0.0)),
+                            size: Vec2::new(json_f32(object, "width", 0.0), json_f32(object, "height", 0.0)),
+                            rotation: json_f32(object, "rotation", 0.0),
+                            tile: decode_tile(json_u32(object, "gid", 0)),
+                            properties: json_properties(object),
+                        }
+                    })
+                    .collect();
+                map.object_layers.push(ObjectLayer {
+                    name: json_str(value, "name").to_string(),
+                    visible,
+                    objects,
+                });
+            },
+            "group" => json_layers(value, offset, map)?,
+            _ => ()
+        }
+    }
+    Ok(())
+}
+
+/// Tile ids of a layer or chunk, as a number array or an encoded string
+fn json_gids(value: &Value, encoding: &str, compression: &str) -> io::Result<Vec<u32>> {
+    match value.get("data") {
+        Some(Value::Array(gids)) => Ok(gids.iter().map(|gid| gid.as_u64().unwrap_or(0) as u32).collect()),
+        Some(Value::String(text)) => decode_data(text, encoding, compression),
+        _ => Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tile ids 1, 2, empty and 3 flipped horizontally in a 2x2 layer
+    fn map(compression: &str, data: &str) -> io::Result<Tilemap> {
+        let text = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<map tilewidth="16" tileheight="16">
+    <layer name="ground" width="2" height="2">
+        <data encoding="base64" compression="{}">{}</data>
+    </layer>
+</map>"#, compression, data);
+        parse_tmx(&text, Path::new(""))
+    }
+
+    fn assert_tiles(map: &Tilemap) {
+        let layer = &map.layers[0];
+        assert_eq!(layer.get(IVec2::new(0, 0)).map(|tile| tile.id), Some(1));
+        assert_eq!(layer.get(IVec2::new(1, 0)).map(|tile| tile.id), Some(2));
+        assert_eq!(layer.get(IVec2::new(0, 1)), None);
+
+        let flipped = layer.get(IVec2::new(1, 1)).unwrap();
+        assert_eq!((flipped.id, flipped.flip_x, flipped.flip_y), (3, true, false));
+    }
+
+    #[test]
+    fn decodes_compressed_base64_layers() {
+        assert_tiles(&map("", "AQAAAAIAAAAAAAAAAwAAgA==").unwrap());
+        assert_tiles(&map("zlib", "eJxjZGBgYGKAAGYGhgYAAMQAhw==").unwrap());
+        assert_tiles(&map("gzip", "H4sIAAAAAAACA2NkYGBgYoAAZgaGBgCVaOVREAAAAA==").unwrap());
+    }
+
+    #[test]
+    fn rejects_unsupported_and_corrupt_data() {
+        let error = map("zstd", "KLUv/QBYIQAA").err().unwrap();
+        assert!(error.to_string().contains("zstd"));
+        assert!(map("zlib", "AQAAAAIAAAAAAAAAAwAAgA==").is_err());
+        assert!(decode_data("1,2", "csv", "gzip").is_err());
+    }
+}
\ No newline at end of file
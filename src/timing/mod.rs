@@ -1,10 +1,27 @@
-use std::time::Instant;
+pub mod pacing;
+pub mod timers;
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+use std::time::Duration;
+use web_time::Instant;
+
+use pacing::FrameStats;
 
 pub struct TimingStruct {
     pub begin_time: Instant,
     pub prev_time: Instant,
     pub current_time: Instant,
-    pub cumulative: f64,
+    /// Real time, for UI, input timestamps and anything that must not slow down or pause
+    pub wall: WallClock,
+    /// Scaled time gameplay and timers run on
+    pub game: GameClock,
+    /// Fixed simulation steps
+    pub fixed: FixedClock,
+    /// Frame time statistics and hitch detection over the recent frames
+    pub stats: FrameStats,
 }
 
 impl TimingStruct {
@@ -13,7 +30,10 @@ impl TimingStruct {
             begin_time: Instant::now(),
             prev_time: Instant::now(),
             current_time: Instant::now(),
-            cumulative: 0.0
+            wall: WallClock::default(),
+            game: GameClock::new(),
+            fixed: FixedClock::new(),
+            stats: FrameStats::new(),
         }
     }
 
@@ -21,23 +41,347 @@ impl TimingStruct {
     pub fn update(&mut self, fixed_rate: u16) -> UpdateTimes {
         self.prev_time = self.current_time;
         self.current_time = Instant::now();
-        let delta = self.current_time.duration_since(self.prev_time).as_secs_f64();
-        self.cumulative += delta;
+        let unscaled_delta = self.current_time.duration_since(self.prev_time).as_secs_f64();
+        self.wall.elapsed = self.current_time.duration_since(self.begin_time).as_secs_f64() - unscaled_delta;
+        self.stats.record(unscaled_delta);
+        self.advance(unscaled_delta, fixed_rate)
+    }
+
+    /// Real seconds since `begin_time`, the clock input events are timestamped with
+    pub fn now(&self) -> f64 {
+        self.begin_time.elapsed().as_secs_f64()
+    }
+
+    /// Forgets the time passed since the last update, so the next delta only covers time after this call
+    pub fn skip_elapsed(&mut self) {
+        self.current_time = Instant::now();
+        self.stats.skip_next();
+    }
+
+    /// Like `update`, but with a given delta instead of the time since the last update.
+    /// Advances the wall clock and the fixed accumulator, the game clock and fixed ticks
+    /// advance when the frame runs, so replays drive them with the recorded times.
+    pub fn advance(&mut self, unscaled_delta: f64, fixed_rate: u16) -> UpdateTimes {
+        self.wall.advance(unscaled_delta);
+        let delta = self.game.scaled(unscaled_delta);
 
         let fixed_delta = 1.0 / fixed_rate as f64;
-        let fixed_steps = (self.cumulative / fixed_delta) as u64;
-        self.cumulative %= fixed_delta;
+        let fixed_steps = self.fixed.accumulate(
+            match self.fixed.scaled {
+                true => delta,
+                false => unscaled_delta
+            },
+            fixed_delta
+        );
 
         UpdateTimes {
             delta,
+            unscaled_delta,
             fixed_delta,
-            fixed_steps
+            fixed_steps,
+            alpha: self.fixed.alpha(),
+            time: self.wall.elapsed,
+        }
+    }
+}
+
+impl Default for TimingStruct {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Real time since the application started, unaffected by time scale and pause
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WallClock {
+    elapsed: f64,
+    delta: f64,
+    frames: u64,
+}
+
+impl WallClock {
+    /// Seconds since the start, as of the current frame
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Seconds the current frame covers
+    pub fn delta(&self) -> f64 {
+        self.delta
+    }
+
+    /// Frames run so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    fn advance(&mut self, delta: f64) {
+        self.elapsed += delta;
+        self.delta = delta;
+        self.frames += 1;
+    }
+}
+
+/// Fixed steps run per frame at most, unless changed through `FixedClock::max_steps` or the config
+pub const DEFAULT_MAX_FIXED_STEPS: u64 = 8;
+
+/// Wall time multiplied by the time scale, standing still while paused
+#[derive(Debug, Clone, Copy)]
+pub struct GameClock {
+    /// Multiplier applied to the update delta, 1.0 is real time. Negative and non-finite scales stop the clock.
+    pub time_scale: f64,
+    /// Stops the game clock entirely, the wall clock keeps running
+    pub paused: bool,
+    /// Scaled seconds passed while not paused, restored from save games as playtime
+    pub elapsed: f64,
+    delta: f64,
+    ticks: u64,
+}
+
+impl GameClock {
+    pub fn new() -> Self {
+        Self {
+            time_scale: 1.0,
+            paused: false,
+            elapsed: 0.0,
+            delta: 0.0,
+            ticks: 0,
+        }
+    }
+
+    /// Scaled seconds the current frame covers, 0 while paused
+    pub fn delta(&self) -> f64 {
+        self.delta
+    }
+
+    /// Frames the game clock was running for
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Whether the clock stands still this frame, through pause or a scale that is not positive and finite
+    pub fn is_stopped(&self) -> bool {
+        self.paused || !(self.time_scale > 0.0 && self.time_scale.is_finite())
+    }
+
+    fn scaled(&self, unscaled_delta: f64) -> f64 {
+        match self.is_stopped() {
+            true => 0.0,
+            false => unscaled_delta * self.time_scale
+        }
+    }
+
+    pub(crate) fn advance(&mut self, delta: f64) {
+        self.delta = delta;
+        if !self.paused {
+            self.elapsed += delta;
+            self.ticks += 1;
+        }
+    }
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits time into fixed steps for the simulation, the leftover carries over to the next frame
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock {
+    /// Whether fixed steps follow the game clock's time scale and pause, or wall time
+    pub scaled: bool,
+    /// Most steps run in one frame, at least 1. Time beyond them is dropped, so a long hitch or a
+    /// large time scale slows the simulation down instead of stalling the frame.
+    pub max_steps: u64,
+    accumulator: f64,
+    step: f64,
+    ticks: u64,
+}
+
+impl FixedClock {
+    pub fn new() -> Self {
+        Self {
+            scaled: true,
+            max_steps: DEFAULT_MAX_FIXED_STEPS,
+            accumulator: 0.0,
+            step: 0.0,
+            ticks: 0,
+        }
+    }
+
+    /// Fixed steps run so far
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Seconds of one step
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// Simulated seconds, `ticks` steps long
+    pub fn elapsed(&self) -> f64 {
+        self.ticks as f64 * self.step
+    }
+
+    /// How far the leftover time is into the next step, in 0..1
+    pub fn alpha(&self) -> f64 {
+        match self.step > 0.0 {
+            true => self.accumulator / self.step,
+            false => 0.0
+        }
+    }
+
+    fn accumulate(&mut self, delta: f64, step: f64) -> u64 {
+        self.step = step;
+        // A NaN or negative delta would poison the accumulator for good
+        if delta.is_finite() && delta > 0.0 {
+            self.accumulator += delta;
         }
+        let steps = (self.accumulator / step) as u64;
+        self.accumulator %= step;
+        steps.min(self.max_steps.max(1))
+    }
+
+    pub(crate) fn tick(&mut self) {
+        self.ticks += 1;
+    }
+}
+
+impl Default for FixedClock {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct UpdateTimes {
     pub delta: f64,
+    /// Real time since the last update, unaffected by time scale or pause
+    pub unscaled_delta: f64,
     pub fixed_delta: f64,
-    pub fixed_steps: u64
+    pub fixed_steps: u64,
+    /// How far the leftover time is into the next fixed step, in 0..1.
+    /// Used to blend between the previous and current fixed step states when rendering.
+    pub alpha: f64,
+    /// Real seconds since the application started, at the end of the time this update covers
+    pub time: f64,
+}
+
+/// Lowest frame rate the limiter waits for, longer frame times would overflow the deadline
+const MIN_TARGET_FPS: f64 = 1.0;
+
+/// Caps the frame rate by sleeping through most of the frame and spinning for the rest,
+/// since OS sleeps routinely overshoot by a millisecond or more
+pub struct FrameLimiter {
+    /// Values below one frame per second are raised to it, zero or less turns the limit off
+    pub target_fps: Option<f64>,
+    pub spin_margin: Duration,
+    last_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(target_fps: Option<f64>) -> Self {
+        Self {
+            target_fps,
+            spin_margin: Duration::from_micros(1500),
+            last_frame: Instant::now(),
+        }
+    }
+
+    /// When the next frame should start, if the frame rate is limited
+    pub fn next_frame(&self) -> Option<Instant> {
+        let fps = self.target_fps.filter(|fps| *fps > 0.0)?;
+        Some(self.last_frame + Duration::from_secs_f64(1.0 / fps.max(MIN_TARGET_FPS)))
+    }
+
+    /// Returns the time to sleep until if the next frame is still far away,
+    /// otherwise spins until it is due and returns `None`
+    pub fn wait(&self) -> Option<Instant> {
+        let deadline = self.next_frame()?;
+
+        if deadline > Instant::now() + self.spin_margin {
+            return Some(deadline - self.spin_margin)
+        }
+
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+
+        None
+    }
+
+    pub fn frame_started(&mut self) {
+        let now = Instant::now();
+
+        // Keep an even cadence unless the frame is already late
+        self.last_frame = match self.next_frame() {
+            Some(deadline) if now.duration_since(deadline) < self.spin_margin => deadline,
+            _ => now
+        };
+    }
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_limiter_handles_extreme_targets() {
+        let mut limiter = FrameLimiter::new(Some(1e-300));
+        let start = limiter.last_frame;
+        assert_eq!(limiter.next_frame(), Some(start + Duration::from_secs(1)));
+
+        limiter.target_fps = Some(f64::INFINITY);
+        assert_eq!(limiter.next_frame(), Some(start));
+
+        for fps in [0.0, -60.0, f64::NAN] {
+            limiter.target_fps = Some(fps);
+            assert_eq!(limiter.next_frame(), None);
+        }
+    }
+
+    #[test]
+    fn non_finite_time_scales_stop_the_game_clock() {
+        let mut timing = TimingStruct::new();
+        for scale in [f64::INFINITY, f64::NAN, -1.0] {
+            timing.game.time_scale = scale;
+            let times = timing.advance(0.1, 60);
+            assert_eq!((times.delta, times.fixed_steps), (0.0, 0));
+            assert!(timing.fixed.alpha().is_finite());
+        }
+    }
+
+    #[test]
+    fn fixed_steps_are_capped_per_frame() {
+        let mut timing = TimingStruct::new();
+        timing.game.time_scale = 1000.0;
+        assert_eq!(timing.advance(0.1, 60).fixed_steps, DEFAULT_MAX_FIXED_STEPS);
+
+        timing.game.time_scale = 1.0;
+        assert_eq!(timing.advance(0.5 / 60.0, 60).fixed_steps, 0);
+
+        timing.fixed.max_steps = 0;
+        assert_eq!(timing.advance(1.0, 60).fixed_steps, 1);
+    }
+
+    #[test]
+    fn long_hitches_and_bad_deltas_do_not_flood_fixed_steps() {
+        let mut timing = TimingStruct::new();
+        assert_eq!(timing.advance(10.0, 60).fixed_steps, DEFAULT_MAX_FIXED_STEPS);
+        assert!(timing.fixed.alpha() < 1.0);
+
+        for delta in [f64::NAN, f64::INFINITY, -1.0] {
+            assert_eq!(timing.advance(delta, 60).fixed_steps, 0);
+        }
+        assert!(timing.fixed.alpha().is_finite());
+        assert_eq!(timing.advance(1.0 / 60.0, 60).fixed_steps, 1);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+use crate::profiler::FrameProfile;
+
+/// Frames kept for the statistics, enough for a meaningful 0.1% low
+const WINDOW: usize = 2000;
+
+/// Sent to the event bus after a frame took longer than `FrameStats::hitch_threshold`
+#[derive(Debug, Clone)]
+pub struct FrameHitch {
+    /// Seconds
+    pub frame_time: f64,
+    /// Spans of the slow frame, `None` unless the profiler is enabled
+    pub profile: Option<FrameProfile>,
+}
+
+/// Rolling frame time statistics for finding stutter, fed with the real time between frames
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    /// Frames slower than this many seconds count as hitches
+    pub hitch_threshold: f64,
+    samples: VecDeque<f64>,
+    hitches: u64,
+    pending_hitch: Option<f64>,
+    skip_next: bool,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self {
+            hitch_threshold: 0.05,
+            samples: VecDeque::with_capacity(WINDOW),
+            hitches: 0,
+            pending_hitch: None,
+            // The first frame includes startup
+            skip_next: true,
+        }
+    }
+
+    pub(crate) fn record(&mut self, frame_time: f64) {
+        if std::mem::take(&mut self.skip_next) {
+            return
+        }
+
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time);
+
+        if frame_time > self.hitch_threshold {
+            self.hitches += 1;
+            self.pending_hitch = Some(frame_time);
+        }
+    }
+
+    /// Leaves the next frame out, its time covers a pause rather than work
+    pub(crate) fn skip_next(&mut self) {
+        self.skip_next = true;
+    }
+
+    pub(crate) fn take_hitch(&mut self) -> Option<f64> {
+        self.pending_hitch.take()
+    }
+
+    /// Frame times in seconds, oldest first
+    pub fn samples(&self) -> impl Iterator<Item = f64> + '_ {
+        self.samples.iter().copied()
+    }
+
+    /// Hitches since the start or the last `reset`
+    pub fn hitches(&self) -> u64 {
+        self.hitches
+    }
+
+    pub fn average(&self) -> f64 {
+        match self.samples.is_empty() {
+            true => 0.0,
+            false => self.samples.iter().sum::<f64>() / self.samples.len() as f64
+        }
+    }
+
+    pub fn average_fps(&self) -> f64 {
+        fps(self.average())
+    }
+
+    /// Frame time below which `percentile` (0..100) of the frames fall
+    pub fn percentile(&self, percentile: f64) -> f64 {
+        let sorted = self.sorted();
+        if sorted.is_empty() {
+            return 0.0
+        }
+        let index = ((percentile / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f64).round() as usize;
+        sorted[index]
+    }
+
+    /// Average frame rate of the slowest 1% of frames
+    pub fn one_percent_low(&self) -> f64 {
+        self.low(0.01)
+    }
+
+    /// Average frame rate of the slowest 0.1% of frames
+    pub fn point_one_percent_low(&self) -> f64 {
+        self.low(0.001)
+    }
+
+    /// Frame counts in buckets `bucket_width` seconds wide starting at 0, the last bucket also holds everything slower
+    pub fn histogram(&self, bucket_width: f64, buckets: usize) -> Vec<u32> {
+        let mut histogram = vec![0; buckets];
+        if buckets == 0 || bucket_width <= 0.0 {
+            return histogram
+        }
+
+        for sample in self.samples.iter() {
+            let bucket = ((sample / bucket_width) as usize).min(buckets - 1);
+            histogram[bucket] += 1;
+        }
+        histogram
+    }
+
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.hitches = 0;
+        self.pending_hitch = None;
+    }
+
+    fn low(&self, fraction: f64) -> f64 {
+        let sorted = self.sorted();
+        if sorted.is_empty() {
+            return 0.0
+        }
+
+        let count = ((sorted.len() as f64 * fraction).ceil() as usize).max(1);
+        let slowest = &sorted[sorted.len() - count..];
+        fps(slowest.iter().sum::<f64>() / count as f64)
+    }
+
+    fn sorted(&self) -> Vec<f64> {
+        let mut sorted: Vec<f64> = self.samples.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        sorted
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn fps(frame_time: f64) -> f64 {
+    match frame_time > 0.0 {
+        true => 1.0 / frame_time,
+        false => 0.0
+    }
+}
\ No newline at end of file
@@ -0,0 +1,295 @@
+use crate::application::Context;
+
+/// A repeating timer fires at most this often in one frame, the intervals past it are skipped
+const MAX_FIRES_PER_FRAME: u64 = 64;
+
+type Callback<UD> = Box<dyn FnMut(&mut UD, &mut Context<UD>)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+#[derive(Debug, Clone, Copy)]
+enum Delay {
+    Seconds(f64),
+    Frames(u64),
+}
+
+struct Timer<UD> {
+    id: TimerId,
+    remaining: Delay,
+    interval: Option<Delay>,
+    callback: Callback<UD>,
+    done: bool,
+}
+
+/// Callbacks scheduled by time or frame count, run by the application before each update
+pub struct Timers<UD> {
+    next_id: u64,
+    timers: Vec<Timer<UD>>,
+    cancelled: Vec<TimerId>,
+}
+
+impl<UD> Timers<UD> {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            timers: Vec::new(),
+            cancelled: Vec::new(),
+        }
+    }
+
+    /// Runs the callback once after the given number of seconds
+    pub fn after(&mut self, seconds: f64, callback: impl FnOnce(&mut UD, &mut Context<UD>) + 'static) -> TimerId {
+        assert!(seconds.is_finite(), "timer delay must be finite");
+        self.schedule(Delay::Seconds(seconds), None, once(callback))
+    }
+
+    /// Runs the callback every `seconds`, starting one interval from now
+    pub fn every(&mut self, seconds: f64, callback: impl FnMut(&mut UD, &mut Context<UD>) + 'static) -> TimerId {
+        assert!(seconds > 0.0 && seconds.is_finite(), "timer interval must be positive and finite");
+        self.schedule(Delay::Seconds(seconds), Some(Delay::Seconds(seconds)), Box::new(callback))
+    }
+
+    /// Runs the callback once after the given number of frames
+    pub fn after_frames(&mut self, frames: u64, callback: impl FnOnce(&mut UD, &mut Context<UD>) + 'static) -> TimerId {
+        self.schedule(Delay::Frames(frames), None, once(callback))
+    }
+
+    /// Runs the callback every `frames` frames, starting one interval from now
+    pub fn every_frames(&mut self, frames: u64, callback: impl FnMut(&mut UD, &mut Context<UD>) + 'static) -> TimerId {
+        assert!(frames > 0, "timer interval must be at least one frame");
+        self.schedule(Delay::Frames(frames), Some(Delay::Frames(frames)), Box::new(callback))
+    }
+
+    pub fn cancel(&mut self, id: TimerId) {
+        self.cancelled.push(id);
+    }
+
+    pub fn is_pending(&self, id: TimerId) -> bool {
+        !self.cancelled.contains(&id) && self.timers.iter().any(|timer| timer.id == id && !timer.done)
+    }
+
+    pub fn clear(&mut self) {
+        self.cancelled.extend(self.timers.iter().map(|timer| timer.id));
+    }
+
+    fn schedule(&mut self, delay: Delay, interval: Option<Delay>, callback: Callback<UD>) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+
+        self.timers.push(Timer {
+            id,
+            remaining: delay,
+            interval,
+            callback,
+            done: false,
+        });
+
+        id
+    }
+
+    /// Advances every timer by one frame of `delta` seconds and runs the ones that are due.
+    /// Timers scheduled from inside a callback start counting on the next frame.
+    /// The timers stay in place while callbacks run, so `cancel`, `clear` and `is_pending` see them.
+    pub(crate) fn run(user_data: &mut UD, ctx: &mut Context<UD>, delta: f64) {
+        // Nothing is removed until the end, so indices stay valid while callbacks schedule more timers
+        let count = ctx.timers().timers.len();
+
+        for index in 0..count {
+            let timers = ctx.timers();
+            let id = timers.timers[index].id;
+            if timers.cancelled.contains(&id) {
+                timers.timers[index].done = true;
+                continue
+            }
+
+            let timer = &mut timers.timers[index];
+            match &mut timer.remaining {
+                Delay::Seconds(seconds) => *seconds -= delta,
+                Delay::Frames(frames) => *frames = frames.saturating_sub(1)
+            }
+
+            if !timer.remaining.is_due() {
+                continue
+            }
+
+            // Long frames fire a repeating timer once for every interval that passed
+            let fires = timer.remaining.fires(timer.interval);
+            for _ in 0..fires {
+                let mut callback = std::mem::replace(&mut ctx.timers().timers[index].callback, Box::new(|_, _| {}));
+                callback(user_data, ctx);
+
+                let timers = ctx.timers();
+                timers.timers[index].callback = callback;
+                if timers.cancelled.contains(&id) {
+                    timers.timers[index].done = true;
+                    break
+                }
+            }
+
+            let timer = &mut ctx.timers().timers[index];
+            match timer.interval {
+                Some(interval) => timer.remaining = timer.remaining.rescheduled(interval),
+                None => timer.done = true
+            }
+        }
+
+        let timers = ctx.timers();
+        let cancelled = std::mem::take(&mut timers.cancelled);
+        timers.timers.retain(|timer| !timer.done && !cancelled.contains(&timer.id));
+    }
+}
+
+impl<UD> Default for Timers<UD> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Delay {
+    fn is_due(&self) -> bool {
+        match self {
+            Delay::Seconds(seconds) => *seconds <= 0.0,
+            Delay::Frames(frames) => *frames == 0
+        }
+    }
+
+    /// How often a due timer fires this frame
+    fn fires(&self, interval: Option<Delay>) -> u64 {
+        match (self, interval) {
+            (Delay::Seconds(seconds), Some(Delay::Seconds(interval))) => ((-seconds / interval).floor() as u64 + 1).min(MAX_FIRES_PER_FRAME),
+            _ => 1
+        }
+    }
+
+    /// Time left until the next fire once a due timer fired, intervals skipped by the per frame cap are dropped
+    fn rescheduled(self, interval: Delay) -> Delay {
+        match (self, interval) {
+            (Delay::Seconds(seconds), Delay::Seconds(interval)) => Delay::Seconds(match seconds.rem_euclid(interval) {
+                left if left > 0.0 => left,
+                _ => interval
+            }),
+            (Delay::Frames(frames), Delay::Frames(interval)) => Delay::Frames(frames + interval),
+            (_, interval) => interval
+        }
+    }
+}
+
+fn once<UD>(callback: impl FnOnce(&mut UD, &mut Context<UD>) + 'static) -> Callback<UD> {
+    let mut callback = Some(callback);
+    Box::new(move |user_data, ctx| {
+        if let Some(callback) = callback.take() {
+            callback(user_data, ctx)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Log {
+        fired: Vec<&'static str>,
+        pending: Vec<bool>,
+    }
+
+    fn run(log: &mut Log, ctx: &mut Context<Log>, frames: usize, delta: f64) {
+        for _ in 0..frames {
+            Timers::run(log, ctx, delta);
+        }
+    }
+
+    #[test]
+    fn fires_after_delays_and_intervals() {
+        let mut log = Log::default();
+        let mut ctx = Context::new();
+        ctx.timers().after(0.25, |log: &mut Log, _| log.fired.push("after"));
+        ctx.timers().every(0.1, |log: &mut Log, _| log.fired.push("every"));
+        ctx.timers().after_frames(2, |log: &mut Log, _| log.fired.push("frames"));
+
+        run(&mut log, &mut ctx, 3, 0.1);
+        assert_eq!(log.fired, vec!["every", "every", "frames", "after", "every"]);
+    }
+
+    #[test]
+    fn long_frames_fire_repeating_timers_for_every_interval() {
+        let mut log = Log::default();
+        let mut ctx = Context::new();
+        ctx.timers().every(0.1, |log: &mut Log, _| log.fired.push("every"));
+
+        run(&mut log, &mut ctx, 1, 0.35);
+        assert_eq!(log.fired.len(), 3);
+    }
+
+    #[test]
+    fn clear_from_a_callback_cancels_the_remaining_timers() {
+        let mut log = Log::default();
+        let mut ctx = Context::new();
+        ctx.timers().after(0.0, |log: &mut Log, _| log.fired.push("before"));
+        let later = ctx.timers().after(1.0, |log: &mut Log, _| log.fired.push("later"));
+        ctx.timers().every(0.1, move |log: &mut Log, ctx| {
+            log.fired.push("clear");
+            log.pending.push(ctx.timers().is_pending(later));
+            ctx.timers().clear();
+        });
+        // Due in the same frame, after the clearing timer
+        ctx.timers().after(0.0, |log: &mut Log, _| log.fired.push("after clear"));
+
+        run(&mut log, &mut ctx, 1, 0.1);
+        assert_eq!(log.fired, vec!["before", "clear"]);
+        assert_eq!(log.pending, vec![true]);
+
+        run(&mut log, &mut ctx, 20, 0.1);
+        assert_eq!(log.fired, vec!["before", "clear"]);
+        assert!(ctx.timers().timers.is_empty());
+    }
+
+    #[test]
+    fn cancel_from_a_callback_and_scheduling_during_run() {
+        let mut log = Log::default();
+        let mut ctx = Context::new();
+        let repeating = ctx.timers().every_frames(1, |log: &mut Log, _| log.fired.push("repeating"));
+        ctx.timers().after_frames(2, move |log: &mut Log, ctx| {
+            log.fired.push("cancel");
+            ctx.timers().cancel(repeating);
+            ctx.timers().after_frames(1, |log: &mut Log, _| log.fired.push("scheduled"));
+        });
+
+        run(&mut log, &mut ctx, 4, 0.0);
+        assert_eq!(log.fired, vec!["repeating", "repeating", "cancel", "scheduled"]);
+        assert!(!ctx.timers().is_pending(repeating));
+    }
+
+    #[test]
+    fn tiny_intervals_fire_a_bounded_number_of_times() {
+        let mut log = Log::default();
+        let mut ctx = Context::new();
+        ctx.timers().every(1e-20, |log: &mut Log, _| log.fired.push("tiny"));
+        ctx.timers().every(1e-6, |log: &mut Log, _| log.fired.push("small"));
+
+        run(&mut log, &mut ctx, 2, 0.1);
+        assert_eq!(log.fired.len(), 4 * MAX_FIRES_PER_FRAME as usize);
+        assert_eq!(ctx.timers().timers.len(), 2);
+    }
+
+    #[test]
+    fn repeating_timers_keep_their_phase() {
+        let mut log = Log::default();
+        let mut ctx = Context::new();
+        ctx.timers().every(0.1, |log: &mut Log, _| log.fired.push("every"));
+
+        run(&mut log, &mut ctx, 1, 0.25);
+        run(&mut log, &mut ctx, 1, 0.04);
+        assert_eq!(log.fired.len(), 2);
+        run(&mut log, &mut ctx, 1, 0.02);
+        assert_eq!(log.fired.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "finite")]
+    fn non_finite_delays_are_rejected() {
+        let mut ctx: Context<Log> = Context::new();
+        ctx.timers().after(f64::NAN, |_, _| {});
+    }
+}
\ No newline at end of file
@@ -0,0 +1,595 @@
+use std::{
+    any::Any,
+    f32::consts::PI,
+    marker::PhantomData
+};
+
+use crate::math::{
+    Quat,
+    Vec2,
+    Vec3,
+    Vec4
+};
+
+/// Values that can be interpolated by a tween
+pub trait Lerp: Clone {
+    fn lerp(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t as f64
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Vec2::lerp(*self, *to, t)
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Vec3::lerp(*self, *to, t)
+    }
+}
+
+impl Lerp for Vec4 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Vec4::lerp(*self, *to, t)
+    }
+}
+
+impl Lerp for Quat {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self.slerp(*to, t)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticOut,
+    BounceOut,
+}
+
+impl Easing {
+    /// Maps linear progress in 0..1 to eased progress
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        const BACK: f32 = 1.70158;
+        const BACK_IN_OUT: f32 = BACK * 1.525;
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => match t < 0.5 {
+                true => 2.0 * t * t,
+                false => 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+            },
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => match t < 0.5 {
+                true => 4.0 * t * t * t,
+                false => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+            },
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => -((PI * t).cos() - 1.0) / 2.0,
+            Easing::ExpoIn => match t == 0.0 {
+                true => 0.0,
+                false => 2f32.powf(10.0 * t - 10.0)
+            },
+            Easing::ExpoOut => match t == 1.0 {
+                true => 1.0,
+                false => 1.0 - 2f32.powf(-10.0 * t)
+            },
+            Easing::ExpoInOut => match t {
+                0.0 => 0.0,
+                1.0 => 1.0,
+                t if t < 0.5 => 2f32.powf(20.0 * t - 10.0) / 2.0,
+                t => (2.0 - 2f32.powf(-20.0 * t + 10.0)) / 2.0
+            },
+            Easing::BackIn => (BACK + 1.0) * t * t * t - BACK * t * t,
+            Easing::BackOut => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+            Easing::BackInOut => match t < 0.5 {
+                true => (2.0 * t).powi(2) * ((BACK_IN_OUT + 1.0) * 2.0 * t - BACK_IN_OUT) / 2.0,
+                false => ((2.0 * t - 2.0).powi(2) * ((BACK_IN_OUT + 1.0) * (t * 2.0 - 2.0) + BACK_IN_OUT) + 2.0) / 2.0
+            },
+            Easing::ElasticOut => match t {
+                0.0 => 0.0,
+                1.0 => 1.0,
+                t => 2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+            },
+            Easing::BounceOut => {
+                const N: f32 = 7.5625;
+                const D: f32 = 2.75;
+
+                if t < 1.0 / D {
+                    N * t * t
+                } else if t < 2.0 / D {
+                    let t = t - 1.5 / D;
+                    N * t * t + 0.75
+                } else if t < 2.5 / D {
+                    let t = t - 2.25 / D;
+                    N * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / D;
+                    N * t * t + 0.984375
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Once,
+    /// Plays this many times in total
+    Times(u32),
+    Forever,
+}
+
+/// Animates a value between two endpoints, advanced with the frame delta
+#[derive(Debug, Clone)]
+pub struct Tween<T: Lerp> {
+    from: T,
+    to: T,
+    duration: f64,
+    elapsed: f64,
+    easing: Easing,
+    repeat: Repeat,
+    ping_pong: bool,
+    /// Finished loops, only the parity is kept for `Repeat::Forever`
+    loops: u32,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: f64) -> Self {
+        Self {
+            from,
+            to,
+            duration: duration.max(0.0),
+            elapsed: 0.0,
+            easing: Easing::Linear,
+            repeat: Repeat::Once,
+            ping_pong: false,
+            loops: 0,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Every other loop plays backwards
+    pub fn ping_pong(mut self) -> Self {
+        self.ping_pong = true;
+        self
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.loops = 0;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.repeat {
+            Repeat::Once => self.loops >= 1,
+            Repeat::Times(times) => self.loops >= times,
+            Repeat::Forever => false
+        }
+    }
+
+    /// Advances the tween and returns the new value
+    pub fn tick(&mut self, delta: f64) -> T {
+        self.advance(delta);
+        self.value()
+    }
+
+    pub fn value(&self) -> T {
+        let mut progress = match self.duration > 0.0 {
+            true => (self.elapsed / self.duration) as f32,
+            false => 1.0
+        };
+
+        let reversed = self.ping_pong && !self.loops.is_multiple_of(2);
+        if self.is_finished() {
+            // Rest on the end of the final loop rather than snapping back to the start
+            progress = 1.0;
+            if self.ping_pong && self.loops.is_multiple_of(2) {
+                progress = 0.0;
+            }
+        } else if reversed {
+            progress = 1.0 - progress;
+        }
+
+        self.from.lerp(&self.to, self.easing.apply(progress))
+    }
+
+    /// Advances the tween, returning the time left over once it finishes.
+    /// Whole loops are counted with a division, so tiny durations cannot stall it.
+    fn advance(&mut self, delta: f64) -> f64 {
+        if self.is_finished() {
+            return delta
+        }
+
+        self.elapsed += delta;
+        if self.elapsed < self.duration {
+            return 0.0
+        }
+
+        let completed = match self.duration > 0.0 {
+            true => (self.elapsed / self.duration).floor(),
+            // A zero length loop completes once per advance
+            false => 1.0
+        };
+
+        let total = match self.repeat {
+            Repeat::Once => 1,
+            Repeat::Times(times) => times,
+            Repeat::Forever => {
+                self.loops = (self.loops + (completed % 2.0) as u32) % 2;
+                self.elapsed = match self.duration > 0.0 {
+                    true => self.elapsed.rem_euclid(self.duration),
+                    false => 0.0
+                };
+                return 0.0
+            }
+        };
+
+        let left = (total - self.loops) as f64;
+        match completed < left {
+            true => {
+                self.loops += completed as u32;
+                self.elapsed = self.elapsed.rem_euclid(self.duration);
+                0.0
+            },
+            false => {
+                self.loops = total;
+                std::mem::take(&mut self.elapsed) - left * self.duration
+            }
+        }
+    }
+
+    /// Time one full play takes, infinite for `Repeat::Forever`
+    fn total_duration(&self) -> f64 {
+        match self.repeat {
+            Repeat::Once => self.duration,
+            Repeat::Times(times) => self.duration * times as f64,
+            Repeat::Forever => f64::INFINITY
+        }
+    }
+}
+
+/// Tweens played one after another, each starting where the time of the previous one ran out
+#[derive(Debug, Clone)]
+pub struct Sequence<T: Lerp> {
+    steps: Vec<Tween<T>>,
+    current: usize,
+    looping: bool,
+}
+
+impl<T: Lerp> Sequence<T> {
+    pub fn new(first: Tween<T>) -> Self {
+        Self {
+            steps: vec![first],
+            current: 0,
+            looping: false,
+        }
+    }
+
+    pub fn then(mut self, tween: Tween<T>) -> Self {
+        self.steps.push(tween);
+        self
+    }
+
+    /// Restarts from the first step after the last one finishes
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.current == self.steps.len() - 1 && self.steps[self.current].is_finished()
+    }
+
+    pub fn reset(&mut self) {
+        self.current = 0;
+        for step in self.steps.iter_mut() {
+            step.reset();
+        }
+    }
+
+    pub fn tick(&mut self, delta: f64) -> T {
+        let mut remaining = delta;
+
+        loop {
+            remaining = self.steps[self.current].advance(remaining);
+            if !self.steps[self.current].is_finished() {
+                break
+            }
+
+            let cycle: f64 = self.steps.iter().map(Tween::total_duration).sum();
+            if self.current + 1 < self.steps.len() {
+                self.current += 1;
+            } else if self.looping && remaining > 0.0 && cycle > 0.0 {
+                // Whole cycles are skipped at once, so short steps cannot stall a long frame
+                self.reset();
+                remaining = remaining.rem_euclid(cycle);
+            } else {
+                break
+            }
+        }
+
+        self.value()
+    }
+
+    pub fn value(&self) -> T {
+        self.steps[self.current].value()
+    }
+}
+
+/// Something the engine can play in a `Tweens` set
+pub trait Animation<T>: 'static {
+    /// Advances by `delta` seconds and returns the new value
+    fn tick(&mut self, delta: f64) -> T;
+    fn value(&self) -> T;
+    fn is_finished(&self) -> bool;
+}
+
+impl<T: Lerp + 'static> Animation<T> for Tween<T> {
+    fn tick(&mut self, delta: f64) -> T {
+        Tween::tick(self, delta)
+    }
+
+    fn value(&self) -> T {
+        Tween::value(self)
+    }
+
+    fn is_finished(&self) -> bool {
+        Tween::is_finished(self)
+    }
+}
+
+impl<T: Lerp + 'static> Animation<T> for Sequence<T> {
+    fn tick(&mut self, delta: f64) -> T {
+        Sequence::tick(self, delta)
+    }
+
+    fn value(&self) -> T {
+        Sequence::value(self)
+    }
+
+    fn is_finished(&self) -> bool {
+        Sequence::is_finished(self)
+    }
+}
+
+/// Handle to an animation playing in a `Tweens` set
+pub struct TweenId<T> {
+    id: u64,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for TweenId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TweenId<T> {}
+
+impl<T> PartialEq for TweenId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for TweenId<T> {}
+
+impl<T> std::fmt::Debug for TweenId<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TweenId").field(&self.id).finish()
+    }
+}
+
+/// Type erased form of the animations in a set
+trait Playing {
+    fn tick(&mut self, delta: f64);
+    fn is_finished(&self) -> bool;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: 'static> Playing for Box<dyn Animation<T>> {
+    fn tick(&mut self, delta: f64) {
+        (**self).tick(delta);
+    }
+
+    fn is_finished(&self) -> bool {
+        (**self).is_finished()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Animations advanced by the application on the game clock, after the timers and before each update.
+/// They stand still while the game is paused and follow its time scale.
+/// A finished animation keeps its final value for the rest of the frame and is removed on the next one.
+pub struct Tweens {
+    next_id: u64,
+    playing: Vec<(u64, Box<dyn Playing>)>,
+}
+
+impl Tweens {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            playing: Vec::new(),
+        }
+    }
+
+    /// Starts playing a tween or sequence, its value is read with `value`
+    pub fn play<T: 'static>(&mut self, animation: impl Animation<T>) -> TweenId<T> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let animation: Box<dyn Animation<T>> = Box::new(animation);
+        self.playing.push((id, Box::new(animation)));
+
+        TweenId {
+            id,
+            _value: PhantomData,
+        }
+    }
+
+    /// Current value, `None` once the animation was stopped or removed after finishing
+    pub fn value<T: 'static>(&self, id: TweenId<T>) -> Option<T> {
+        self.get(id.id)
+            .and_then(|playing| playing.as_any().downcast_ref::<Box<dyn Animation<T>>>())
+            .map(|animation| animation.value())
+    }
+
+    pub fn is_playing<T>(&self, id: TweenId<T>) -> bool {
+        self.get(id.id).is_some_and(|playing| !playing.is_finished())
+    }
+
+    pub fn stop<T>(&mut self, id: TweenId<T>) {
+        self.playing.retain(|(playing, _)| *playing != id.id);
+    }
+
+    pub fn clear(&mut self) {
+        self.playing.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.playing.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.playing.is_empty()
+    }
+
+    /// Removes the animations that finished last frame and advances the rest by `delta` game seconds
+    pub(crate) fn tick(&mut self, delta: f64) {
+        self.playing.retain(|(_, playing)| !playing.is_finished());
+        for (_, playing) in self.playing.iter_mut() {
+            playing.tick(delta);
+        }
+    }
+
+    fn get(&self, id: u64) -> Option<&dyn Playing> {
+        self.playing.iter()
+            .find(|(playing, _)| *playing == id)
+            .map(|(_, playing)| playing.as_ref())
+    }
+}
+
+impl Default for Tweens {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ping_pong_plays_every_other_loop_backwards() {
+        let mut tween = Tween::new(0.0f32, 1.0, 1.0).with_repeat(Repeat::Times(3)).ping_pong();
+        assert_eq!(tween.tick(0.25), 0.25);
+        assert_eq!(tween.tick(1.0), 0.75);
+        assert_eq!(tween.tick(1.0), 0.25);
+        assert_eq!(tween.tick(1.0), 1.0);
+        assert!(tween.is_finished());
+
+        let mut tween = Tween::new(0.0f32, 1.0, 1.0).with_repeat(Repeat::Times(2)).ping_pong();
+        assert_eq!(tween.tick(5.0), 0.0);
+    }
+
+    #[test]
+    fn sequences_carry_time_over_between_steps() {
+        let mut sequence = Sequence::new(Tween::new(0.0f32, 1.0, 1.0)).then(Tween::new(1.0, 3.0, 2.0));
+        assert_eq!(sequence.tick(2.0), 2.0);
+        assert_eq!(sequence.tick(5.0), 3.0);
+        assert!(sequence.is_finished());
+    }
+
+    #[test]
+    fn tiny_durations_do_not_stall() {
+        let mut forever = Tween::new(0.0f32, 1.0, 1e-20).with_repeat(Repeat::Forever);
+        forever.tick(1.0);
+        assert!(!forever.is_finished());
+
+        let mut many = Tween::new(0.0f32, 1.0, 1e-6).with_repeat(Repeat::Times(u32::MAX));
+        many.tick(1.0);
+        assert!(!many.is_finished());
+        assert_eq!(many.tick(1e9), 1.0);
+        assert!(many.is_finished());
+
+        let mut sequence = Sequence::new(Tween::new(0.0f32, 1.0, 1e-20)).then(Tween::new(1.0, 0.0, 1e-20)).looping();
+        sequence.tick(1e6);
+        assert!(!sequence.is_finished());
+    }
+
+    #[test]
+    fn looping_sequences_keep_their_phase() {
+        let mut sequence = Sequence::new(Tween::new(0.0f32, 1.0, 1.0)).then(Tween::new(1.0, 0.0, 1.0)).looping();
+        assert_eq!(sequence.tick(4.5), 0.5);
+        assert_eq!(sequence.tick(1.25), 0.25);
+    }
+
+    #[test]
+    fn set_plays_and_removes_finished_animations() {
+        let mut tweens = Tweens::new();
+        let fade = tweens.play(Tween::new(0.0f32, 1.0, 1.0));
+        let path = tweens.play(Sequence::new(Tween::new(Vec2::ZERO, Vec2::ONE, 2.0)));
+
+        tweens.tick(0.5);
+        assert_eq!(tweens.value(fade), Some(0.5));
+        assert_eq!(tweens.value(path), Some(Vec2::splat(0.25)));
+
+        tweens.tick(0.5);
+        assert_eq!(tweens.value(fade), Some(1.0));
+        assert!(!tweens.is_playing(fade));
+
+        tweens.tick(0.5);
+        assert_eq!(tweens.value(fade), None);
+        assert!(tweens.is_playing(path));
+
+        tweens.stop(path);
+        assert!(tweens.is_empty());
+    }
+}
\ No newline at end of file
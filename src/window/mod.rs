@@ -0,0 +1,417 @@
+use std::{
+    cell::{
+        Cell,
+        RefCell
+    },
+    cmp::Reverse
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use winit::{
+    dpi::{
+        LogicalSize,
+        PhysicalPosition,
+        PhysicalSize
+    },
+    error::ExternalError,
+    event_loop::EventLoopWindowTarget,
+    monitor::{
+        MonitorHandle,
+        VideoMode
+    },
+    window::{
+        CursorGrabMode,
+        Fullscreen,
+        Window,
+        WindowBuilder,
+        WindowId,
+        WindowLevel
+    }
+};
+
+#[cfg(target_arch = "wasm32")]
+use winit::platform::web::WindowBuilderExtWebSys;
+
+use crate::{
+    config::WindowConfig,
+    math::{
+        Rect,
+        Vec2
+    }
+};
+
+pub use winit::window::ResizeDirection;
+
+/// Resolution and refresh rate of an exclusive fullscreen mode
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DisplayMode {
+    pub width: u32,
+    pub height: u32,
+    /// Hz
+    pub refresh_rate: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FullscreenMode {
+    Windowed,
+    /// Covers the monitor without changing its mode, switches between applications quickly
+    Borderless,
+    /// Takes over the monitor and switches it to the mode, for the lowest latency and exact refresh rates
+    Exclusive(DisplayMode),
+}
+
+impl FullscreenMode {
+    pub fn from_config(config: &WindowConfig) -> Self {
+        match (config.fullscreen, config.video_mode) {
+            (false, _) => FullscreenMode::Windowed,
+            (true, None) => FullscreenMode::Borderless,
+            (true, Some(mode)) => FullscreenMode::Exclusive(mode)
+        }
+    }
+}
+
+/// What a point of the window does when pressed with the left mouse button
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitRegion {
+    /// Handled by the game
+    Client,
+    /// Moves the window, like a title bar
+    Caption,
+    Resize(ResizeDirection),
+}
+
+#[derive(Default)]
+struct HitRegions {
+    captions: Vec<Rect>,
+    resize_border: f32,
+}
+
+/// The application's OS window, created when graphics are enabled
+pub struct AspenWindow {
+    window: Window,
+    hit_regions: RefCell<HitRegions>,
+    cursor_area: Cell<Option<Rect>>,
+    cursor_grabbed: Cell<bool>,
+    /// Opened on first use and kept alive, on X11 copied text is lost once the clipboard is dropped
+    #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+    clipboard: RefCell<Option<arboard::Clipboard>>,
+}
+
+impl AspenWindow {
+    pub(crate) fn new<T>(elwt: &EventLoopWindowTarget<T>, config: &WindowConfig) -> Self {
+        let monitor = elwt.primary_monitor().or_else(|| elwt.available_monitors().next());
+        let fullscreen = fullscreen(FullscreenMode::from_config(config), monitor);
+
+        let builder = WindowBuilder::new()
+            .with_title(&config.title)
+            .with_inner_size(LogicalSize::new(config.width, config.height))
+            .with_resizable(config.resizable)
+            .with_decorations(config.decorations)
+            .with_fullscreen(fullscreen)
+            .with_transparent(config.transparent)
+            .with_window_level(window_level(config.always_on_top));
+
+        // Adds the canvas to the page body, games wanting it elsewhere can move `winit().canvas()` afterwards
+        #[cfg(target_arch = "wasm32")]
+        let builder = builder.with_append(true);
+
+        let window = builder
+            .build(elwt)
+            .expect("window creation failed");
+
+        let window = Self {
+            window,
+            hit_regions: RefCell::new(HitRegions::default()),
+            cursor_area: Cell::new(None),
+            cursor_grabbed: Cell::new(false),
+            #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+            clipboard: RefCell::new(None),
+        };
+        if config.click_through {
+            window.set_click_through(true);
+        }
+        window
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    /// The underlying winit window, for anything the engine does not wrap
+    pub fn winit(&self) -> &Window {
+        &self.window
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.window.inner_size()
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.window.scale_factor()
+    }
+
+    pub fn set_title(&self, title: &str) {
+        self.window.set_title(title)
+    }
+
+    pub fn request_redraw(&self) {
+        self.window.request_redraw()
+    }
+
+    /// Exclusive fullscreen modes of the monitor the window is on, largest and fastest first
+    pub fn display_modes(&self) -> Vec<DisplayMode> {
+        let Some(monitor) = self.window.current_monitor() else {
+            return Vec::new()
+        };
+
+        let mut modes: Vec<DisplayMode> = monitor.video_modes().map(|mode| display_mode(&mode)).collect();
+
+        // Modes differing only in bit depth show up once
+        modes.sort_by(|a, b| (b.width, b.height).cmp(&(a.width, a.height)).then(b.refresh_rate.total_cmp(&a.refresh_rate)));
+        modes.dedup();
+        modes
+    }
+
+    /// Exclusive fullscreen uses the closest mode the window's monitor supports,
+    /// falling back to borderless where exclusive fullscreen is unavailable
+    pub fn set_fullscreen(&self, mode: FullscreenMode) {
+        self.window.set_fullscreen(fullscreen(mode, self.window.current_monitor()));
+    }
+
+    pub fn fullscreen(&self) -> FullscreenMode {
+        match self.window.fullscreen() {
+            None => FullscreenMode::Windowed,
+            Some(Fullscreen::Borderless(_)) => FullscreenMode::Borderless,
+            Some(Fullscreen::Exclusive(mode)) => FullscreenMode::Exclusive(display_mode(&mode))
+        }
+    }
+
+    /// Needs the window to have been created transparent on some platforms
+    pub fn set_transparent(&self, transparent: bool) {
+        self.window.set_transparent(transparent)
+    }
+
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        self.window.set_window_level(window_level(always_on_top))
+    }
+
+    /// Returns false where the platform cannot pass mouse input through
+    pub fn set_click_through(&self, click_through: bool) -> bool {
+        self.window.set_cursor_hittest(!click_through).is_ok()
+    }
+
+    pub fn set_decorations(&self, decorations: bool) {
+        self.window.set_decorations(decorations)
+    }
+
+    pub fn decorations(&self) -> bool {
+        self.window.is_decorated()
+    }
+
+    /// Areas in physical pixels that move the window when dragged, such as a custom title bar.
+    /// Buttons drawn inside them should be left out of the areas.
+    pub fn set_caption_areas(&self, areas: Vec<Rect>) {
+        self.hit_regions.borrow_mut().captions = areas;
+    }
+
+    /// Width in physical pixels of the edges that resize an undecorated window, 0 disables them
+    pub fn set_resize_border(&self, width: f32) {
+        self.hit_regions.borrow_mut().resize_border = width.max(0.0);
+    }
+
+    /// What pressing at a position in physical pixels does, resize borders take precedence over captions
+    pub fn hit_test(&self, position: Vec2) -> HitRegion {
+        let regions = self.hit_regions.borrow();
+        let size = self.size();
+        let size = Vec2::new(size.width as f32, size.height as f32);
+
+        if regions.resize_border > 0.0 && self.window.is_resizable() && !self.window.is_decorated() {
+            let border = regions.resize_border;
+            let west = position.x < border;
+            let east = position.x >= size.x - border;
+            let north = position.y < border;
+            let south = position.y >= size.y - border;
+
+            let direction = match (north, south, west, east) {
+                (true, _, true, _) => Some(ResizeDirection::NorthWest),
+                (true, _, _, true) => Some(ResizeDirection::NorthEast),
+                (_, true, true, _) => Some(ResizeDirection::SouthWest),
+                (_, true, _, true) => Some(ResizeDirection::SouthEast),
+                (true, _, _, _) => Some(ResizeDirection::North),
+                (_, true, _, _) => Some(ResizeDirection::South),
+                (_, _, true, _) => Some(ResizeDirection::West),
+                (_, _, _, true) => Some(ResizeDirection::East),
+                _ => None
+            };
+            if let Some(direction) = direction {
+                return HitRegion::Resize(direction)
+            }
+        }
+
+        match regions.captions.iter().any(|area| area.contains_point(position)) {
+            true => HitRegion::Caption,
+            false => HitRegion::Client
+        }
+    }
+
+    /// Starts moving the window with the mouse, only works while a mouse button is held
+    pub fn drag_window(&self) -> bool {
+        self.window.drag_window().is_ok()
+    }
+
+    /// Starts resizing the window with the mouse, only works while a mouse button is held
+    pub fn drag_resize(&self, direction: ResizeDirection) -> bool {
+        self.window.drag_resize_window(direction).is_ok()
+    }
+
+    /// Starts a move or resize if the left button was pressed on a caption or resize border
+    pub(crate) fn handle_press(&self, position: Vec2) {
+        match self.hit_test(position) {
+            HitRegion::Client => (),
+            HitRegion::Caption => {
+                self.drag_window();
+            },
+            HitRegion::Resize(direction) => {
+                self.drag_resize(direction);
+            }
+        }
+    }
+
+    /// Locks and hides the cursor for raw mouse input, falling back to confining it where locking is unsupported
+    pub fn set_cursor_grabbed(&self, grabbed: bool) {
+        // Grabbing fails on some platforms while the window is unfocused, it is retried on focus
+        self.cursor_grabbed.set(grabbed);
+        let _ = match grabbed {
+            true => self.window.set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| self.window.set_cursor_grab(CursorGrabMode::Confined)),
+            false => self.apply_confinement(self.window.has_focus())
+        };
+        self.window.set_cursor_visible(!grabbed);
+    }
+
+    /// Keeps the cursor inside an area of the window in physical pixels, e.g. for edge scrolling.
+    /// The whole window is confined by the platform and smaller areas by moving the cursor back in.
+    /// Released while the window is unfocused, `None` releases it for good.
+    pub fn confine_cursor(&self, area: Option<Rect>) {
+        self.cursor_area.set(area);
+        // A grab for raw mouse input already holds the cursor, the area applies once it is released
+        if !self.cursor_grabbed.get() {
+            let _ = self.apply_confinement(self.window.has_focus());
+        }
+    }
+
+    pub fn cursor_confinement(&self) -> Option<Rect> {
+        self.cursor_area.get()
+    }
+
+    pub fn has_focus(&self) -> bool {
+        self.window.has_focus()
+    }
+
+    pub(crate) fn focus_changed(&self, focused: bool) {
+        if self.cursor_area.get().is_some() && !self.cursor_grabbed.get() {
+            let _ = self.apply_confinement(focused);
+        }
+    }
+
+    pub(crate) fn cursor_moved(&self, position: Vec2) {
+        let Some(area) = self.cursor_area.get() else {
+            return
+        };
+        if self.window.has_focus() && !self.cursor_grabbed.get() && !area.contains_point(position) {
+            let clamped = position.clamp(area.min, area.max);
+            let _ = self.window.set_cursor_position(PhysicalPosition::new(clamped.x, clamped.y));
+        }
+    }
+
+    fn apply_confinement(&self, focused: bool) -> Result<(), ExternalError> {
+        match focused && self.cursor_area.get().is_some() {
+            true => self.window.set_cursor_grab(CursorGrabMode::Confined),
+            false => self.window.set_cursor_grab(CursorGrabMode::None)
+        }
+    }
+
+    /// Enable while a text field has focus so the IME can compose text, off by default
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.window.set_ime_allowed(allowed)
+    }
+
+    /// Area of the text being edited in physical pixels, the IME places its candidate window next to it
+    pub fn set_ime_cursor_area(&self, position: Vec2, size: Vec2) {
+        self.window.set_ime_cursor_area(
+            PhysicalPosition::new(position.x, position.y),
+            PhysicalSize::new(size.x, size.y)
+        )
+    }
+
+    /// `None` if the clipboard is empty, holds something other than text, or is unavailable on this platform
+    pub fn clipboard_text(&self) -> Option<String> {
+        #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+        return self.with_clipboard(|clipboard| clipboard.get_text().ok());
+
+        #[cfg(any(target_os = "android", target_arch = "wasm32"))]
+        None
+    }
+
+    /// Returns false if the text could not be copied
+    pub fn set_clipboard_text(&self, text: &str) -> bool {
+        #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+        return self.with_clipboard(|clipboard| clipboard.set_text(text).ok()).is_some();
+
+        #[cfg(any(target_os = "android", target_arch = "wasm32"))]
+        {
+            let _ = text;
+            false
+        }
+    }
+
+    #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+    fn with_clipboard<T>(&self, f: impl FnOnce(&mut arboard::Clipboard) -> Option<T>) -> Option<T> {
+        let mut clipboard = self.clipboard.borrow_mut();
+        if clipboard.is_none() {
+            *clipboard = arboard::Clipboard::new().ok();
+        }
+
+        clipboard.as_mut().and_then(f)
+    }
+}
+
+fn fullscreen(mode: FullscreenMode, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+    match mode {
+        FullscreenMode::Windowed => None,
+        FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+        FullscreenMode::Exclusive(mode) => match monitor.as_ref().and_then(|monitor| closest_video_mode(monitor, mode)) {
+            Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+            None => Some(Fullscreen::Borderless(monitor))
+        }
+    }
+}
+
+/// Matching size first, then refresh rate, preferring the highest bit depth
+fn closest_video_mode(monitor: &MonitorHandle, mode: DisplayMode) -> Option<VideoMode> {
+    monitor.video_modes().min_by_key(|candidate| {
+        let size = candidate.size();
+        let size_error = size.width.abs_diff(mode.width) as u64 + size.height.abs_diff(mode.height) as u64;
+        let refresh_error = (candidate.refresh_rate_millihertz() as f64 - mode.refresh_rate * 1000.0).abs() as u64;
+        (size_error, refresh_error, Reverse(candidate.bit_depth()))
+    })
+}
+
+fn display_mode(mode: &VideoMode) -> DisplayMode {
+    DisplayMode {
+        width: mode.size().width,
+        height: mode.size().height,
+        refresh_rate: mode.refresh_rate_millihertz() as f64 / 1000.0,
+    }
+}
+
+fn window_level(always_on_top: bool) -> WindowLevel {
+    match always_on_top {
+        true => WindowLevel::AlwaysOnTop,
+        false => WindowLevel::Normal
+    }
+}
\ No newline at end of file
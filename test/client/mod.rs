@@ -1,4 +1,7 @@
-use aspen_engine::interface;
+use aspen_engine::{
+    application::Context,
+    interface
+};
 
 pub struct AppData {
 
@@ -13,11 +16,11 @@ impl AppData {
 }
 
 impl interface::Client for AppData {
-    fn fixed_update(&mut self, delta: f64) {
+    fn fixed_update(&mut self, _ctx: &mut Context<Self>, _delta: f64) {
         
     }
 
-    fn update(&mut self, delta: f64) {
+    fn update(&mut self, _ctx: &mut Context<Self>, _delta: f64) {
         
     }
 }
\ No newline at end of file